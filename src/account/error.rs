@@ -0,0 +1,34 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+	#[error("http error: {0}")]
+	Http(#[from] reqwest::Error),
+	#[error("unexpected status code {status} from {url}")]
+	UnexpectedStatus { url: String, status: StatusCode },
+	#[error("authorization declined by user")]
+	Declined,
+	#[error("device code expired")]
+	Expired,
+	#[error("oauth error: {0}")]
+	OAuth(String),
+	#[error("this Microsoft account has no Xbox account")]
+	NoXboxAccount,
+	#[error("Xbox Live is not available in this account's region")]
+	XboxRegionBlocked,
+	#[error("child account must be added to a family by an adult")]
+	ChildAccount,
+	#[error("xsts error code {0}")]
+	Xsts(u64),
+	#[error("this account does not own Minecraft")]
+	NotOwned,
+	#[error("this account has no Minecraft profile")]
+	NoProfile,
+	#[error("this account has multiple profiles, choose one of: {}", .0.join(", "))]
+	MultipleProfiles(Vec<String>),
+	#[error("account session expired, please log in again")]
+	SessionExpired,
+	#[error("authentication server error {error}: {message}")]
+	Yggdrasil { error: String, message: String },
+}
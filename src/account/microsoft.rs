@@ -0,0 +1,535 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::account::error::AuthError;
+use crate::account::{Account, unix_now};
+
+const SCOPE: &str = "XboxLive.signin offline_access";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 登录流程各服务的基础地址，测试时可指向本地服务器
+#[derive(Debug, Clone)]
+pub struct MicrosoftEndpoints {
+	pub oauth: String,
+	pub xbox_user: String,
+	pub xsts: String,
+	pub minecraft: String,
+}
+
+impl Default for MicrosoftEndpoints {
+	fn default() -> Self {
+		Self {
+			oauth: "https://login.microsoftonline.com/consumers/oauth2/v2.0".into(),
+			xbox_user: "https://user.auth.xboxlive.com".into(),
+			xsts: "https://xsts.auth.xboxlive.com".into(),
+			minecraft: "https://api.minecraftservices.com".into(),
+		}
+	}
+}
+
+/// 展示给用户的设备码信息
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+	pub device_code: String,
+	pub user_code: String,
+	pub verification_uri: String,
+	pub expires_in: u64,
+	#[serde(default = "default_interval")]
+	pub interval: u64,
+	#[serde(default)]
+	pub message: Option<String>,
+}
+
+fn default_interval() -> u64 {
+	5
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthToken {
+	pub access_token: String,
+	#[serde(default)]
+	pub refresh_token: Option<String>,
+	#[serde(default)]
+	pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+	error: String,
+	#[serde(default)]
+	error_description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct XboxToken {
+	pub token: String,
+	pub user_hash: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct XboxResponse {
+	token: String,
+	display_claims: XboxClaims,
+}
+
+#[derive(Deserialize)]
+struct XboxClaims {
+	xui: Vec<XboxUser>,
+}
+
+#[derive(Deserialize)]
+struct XboxUser {
+	uhs: String,
+}
+
+#[derive(Deserialize)]
+struct XstsError {
+	#[serde(rename = "XErr")]
+	xerr: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MinecraftToken {
+	pub access_token: String,
+	#[serde(default)]
+	pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct Entitlements {
+	#[serde(default)]
+	items: Vec<Entitlement>,
+}
+
+#[derive(Deserialize)]
+struct Entitlement {
+	name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MinecraftProfile {
+	pub id: String,
+	pub name: String,
+}
+
+pub struct MicrosoftAuth {
+	client: Client,
+	client_id: String,
+	endpoints: MicrosoftEndpoints,
+}
+
+impl MicrosoftAuth {
+	pub fn new(client_id: impl Into<String>) -> Self {
+		Self {
+			client: Client::new(),
+			client_id: client_id.into(),
+			endpoints: MicrosoftEndpoints::default(),
+		}
+	}
+
+	pub fn with_endpoints(mut self, endpoints: MicrosoftEndpoints) -> Self {
+		self.endpoints = endpoints;
+		self
+	}
+
+	/// 完整的设备码登录流程，`on_code` 在拿到设备码后调用，用于提示用户前往验证页面
+	pub async fn login<F>(&self, on_code: F) -> Result<Account, AuthError>
+	where
+		F: FnOnce(&DeviceCode),
+	{
+		let code = self.request_device_code().await?;
+		on_code(&code);
+		let token = self.poll_token(&code).await?;
+		self.login_with_oauth(token).await
+	}
+
+	/// 用刷新令牌换取新的 OAuth 令牌并重新完成认证
+	pub async fn refresh(&self, refresh_token: &str) -> Result<Account, AuthError> {
+		let url = format!("{}/token", self.endpoints.oauth);
+		let resp = self
+			.client
+			.post(&url)
+			.form(&[
+				("grant_type", "refresh_token"),
+				("client_id", self.client_id.as_str()),
+				("refresh_token", refresh_token),
+				("scope", SCOPE),
+			])
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?;
+		if resp.status() == StatusCode::BAD_REQUEST {
+			return Err(AuthError::SessionExpired);
+		}
+
+		let mut token: OAuthToken = parse_json(url, resp).await?;
+		// 部分响应不会轮换刷新令牌，沿用旧的
+		token
+			.refresh_token
+			.get_or_insert_with(|| refresh_token.to_string());
+		self.login_with_oauth(token).await
+	}
+
+	/// 从微软 OAuth 令牌开始完成 Xbox 与 Minecraft 认证
+	pub async fn login_with_oauth(&self, token: OAuthToken) -> Result<Account, AuthError> {
+		let xbl = self.xbox_live(&token.access_token).await?;
+		let xsts = self.xsts(&xbl.token).await?;
+		let mc = self.minecraft_login(&xsts).await?;
+		self.check_entitlement(&mc.access_token).await?;
+		let profile = self.profile(&mc.access_token).await?;
+
+		let uuid = Uuid::parse_str(&profile.id).map_err(|_| AuthError::NoProfile)?;
+		Ok(Account::Microsoft {
+			username: profile.name,
+			uuid,
+			access_token: mc.access_token,
+			refresh_token: token.refresh_token,
+			client_id: Some(self.client_id.clone()),
+			expires_at: Some(unix_now() + mc.expires_in),
+		})
+	}
+
+	pub async fn request_device_code(&self) -> Result<DeviceCode, AuthError> {
+		let url = format!("{}/devicecode", self.endpoints.oauth);
+		let resp = self
+			.client
+			.post(&url)
+			.form(&[("client_id", self.client_id.as_str()), ("scope", SCOPE)])
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?;
+		parse_json(url, resp).await
+	}
+
+	/// 按设备码给出的间隔轮询，直到用户完成授权、拒绝或设备码过期
+	pub async fn poll_token(&self, code: &DeviceCode) -> Result<OAuthToken, AuthError> {
+		let url = format!("{}/token", self.endpoints.oauth);
+		let deadline = tokio::time::Instant::now() + Duration::from_secs(code.expires_in);
+		let mut interval = Duration::from_secs(code.interval);
+
+		loop {
+			if tokio::time::Instant::now() >= deadline {
+				return Err(AuthError::Expired);
+			}
+
+			let resp = self
+				.client
+				.post(&url)
+				.form(&[
+					("grant_type", DEVICE_CODE_GRANT),
+					("client_id", self.client_id.as_str()),
+					("device_code", code.device_code.as_str()),
+				])
+				.timeout(REQUEST_TIMEOUT)
+				.send()
+				.await?;
+
+			if resp.status().is_success() {
+				return Ok(resp.json().await?);
+			}
+
+			let status = resp.status();
+			let err: OAuthError = resp.json().await.map_err(|_| AuthError::UnexpectedStatus {
+				url: url.clone(),
+				status,
+			})?;
+			match err.error.as_str() {
+				"authorization_pending" => {}
+				"slow_down" => interval += Duration::from_secs(5),
+				"authorization_declined" => return Err(AuthError::Declined),
+				"expired_token" => return Err(AuthError::Expired),
+				_ => return Err(AuthError::OAuth(err.error_description.unwrap_or(err.error))),
+			}
+			tokio::time::sleep(interval).await;
+		}
+	}
+
+	pub async fn xbox_live(&self, ms_access_token: &str) -> Result<XboxToken, AuthError> {
+		let url = format!("{}/user/authenticate", self.endpoints.xbox_user);
+		let body = json!({
+			"Properties": {
+				"AuthMethod": "RPS",
+				"SiteName": "user.auth.xboxlive.com",
+				"RpsTicket": format!("d={ms_access_token}"),
+			},
+			"RelyingParty": "http://auth.xboxlive.com",
+			"TokenType": "JWT",
+		});
+		let resp = self.xbox_post(&url, &body).await?;
+		let resp: XboxResponse = parse_json(url, resp).await?;
+		xbox_token(resp)
+	}
+
+	pub async fn xsts(&self, xbl_token: &str) -> Result<XboxToken, AuthError> {
+		let url = format!("{}/xsts/authorize", self.endpoints.xsts);
+		let body = json!({
+			"Properties": {
+				"SandboxId": "RETAIL",
+				"UserTokens": [xbl_token],
+			},
+			"RelyingParty": "rp://api.minecraftservices.com/",
+			"TokenType": "JWT",
+		});
+		let resp = self.xbox_post(&url, &body).await?;
+
+		if resp.status() == StatusCode::UNAUTHORIZED {
+			let status = resp.status();
+			let err: XstsError = resp
+				.json()
+				.await
+				.map_err(|_| AuthError::UnexpectedStatus { url, status })?;
+			return Err(match err.xerr {
+				2148916233 => AuthError::NoXboxAccount,
+				2148916235 => AuthError::XboxRegionBlocked,
+				2148916238 => AuthError::ChildAccount,
+				code => AuthError::Xsts(code),
+			});
+		}
+
+		let resp: XboxResponse = parse_json(url, resp).await?;
+		xbox_token(resp)
+	}
+
+	pub async fn minecraft_login(&self, xsts: &XboxToken) -> Result<MinecraftToken, AuthError> {
+		let url = format!(
+			"{}/authentication/login_with_xbox",
+			self.endpoints.minecraft
+		);
+		let body = json!({
+			"identityToken": format!("XBL3.0 x={};{}", xsts.user_hash, xsts.token),
+		});
+		let resp = self
+			.client
+			.post(&url)
+			.json(&body)
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?;
+		parse_json(url, resp).await
+	}
+
+	pub async fn check_entitlement(&self, mc_access_token: &str) -> Result<(), AuthError> {
+		let url = format!("{}/entitlements/mcstore", self.endpoints.minecraft);
+		let resp = self.minecraft_get(&url, mc_access_token).await?;
+		let entitlements: Entitlements = parse_json(url, resp).await?;
+
+		let owned = entitlements
+			.items
+			.iter()
+			.any(|e| e.name == "game_minecraft" || e.name == "product_minecraft");
+		if owned {
+			Ok(())
+		} else {
+			Err(AuthError::NotOwned)
+		}
+	}
+
+	pub async fn profile(&self, mc_access_token: &str) -> Result<MinecraftProfile, AuthError> {
+		let url = format!("{}/minecraft/profile", self.endpoints.minecraft);
+		let resp = self.minecraft_get(&url, mc_access_token).await?;
+		if resp.status() == StatusCode::NOT_FOUND {
+			return Err(AuthError::NoProfile);
+		}
+		parse_json(url, resp).await
+	}
+
+	async fn xbox_post(
+		&self,
+		url: &str,
+		body: &serde_json::Value,
+	) -> Result<reqwest::Response, AuthError> {
+		Ok(self
+			.client
+			.post(url)
+			.header("Accept", "application/json")
+			.json(body)
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?)
+	}
+
+	async fn minecraft_get(
+		&self,
+		url: &str,
+		access_token: &str,
+	) -> Result<reqwest::Response, AuthError> {
+		Ok(self
+			.client
+			.get(url)
+			.bearer_auth(access_token)
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?)
+	}
+}
+
+async fn parse_json<T: DeserializeOwned>(
+	url: String,
+	resp: reqwest::Response,
+) -> Result<T, AuthError> {
+	let status = resp.status();
+	if !status.is_success() {
+		return Err(AuthError::UnexpectedStatus { url, status });
+	}
+	Ok(resp.json().await?)
+}
+
+/// 从 Minecraft 访问令牌（JWT）的载荷中读取 xuid
+pub fn token_xuid(access_token: &str) -> Option<String> {
+	let payload = access_token.split('.').nth(1)?;
+	let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+	let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+	match claims.get("xuid")? {
+		serde_json::Value::String(s) => Some(s.clone()),
+		serde_json::Value::Number(n) => Some(n.to_string()),
+		_ => None,
+	}
+}
+
+fn xbox_token(resp: XboxResponse) -> Result<XboxToken, AuthError> {
+	let user_hash = resp
+		.display_claims
+		.xui
+		.into_iter()
+		.next()
+		.map(|u| u.uhs)
+		.ok_or(AuthError::NoXboxAccount)?;
+	Ok(XboxToken {
+		token: resp.token,
+		user_hash,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::account::test_server;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	const PROFILE_ID: &str = "069a79f444e94726a5befca90e38aaf5";
+
+	/// 按路径返回各服务的固定响应，令牌接口前两次返回 authorization_pending
+	async fn start_auth_server(owned: bool) -> (String, Arc<AtomicUsize>) {
+		let polls = Arc::new(AtomicUsize::new(0));
+		let counter = Arc::clone(&polls);
+
+		let url = test_server::start(move |req| {
+			let (status, body) = match req.path.as_str() {
+				"/oauth/devicecode" => (
+					"200 OK",
+					json!({
+						"device_code": "dc",
+						"user_code": "ABCD-1234",
+						"verification_uri": "https://microsoft.com/link",
+						"expires_in": 60,
+						"interval": 0,
+					}),
+				),
+				"/oauth/token" if req.body.contains("grant_type=refresh_token") => (
+					"200 OK",
+					json!({ "access_token": "ms2", "expires_in": 3600 }),
+				),
+				"/oauth/token" if counter.fetch_add(1, Ordering::SeqCst) < 2 => (
+					"400 Bad Request",
+					json!({ "error": "authorization_pending" }),
+				),
+				"/oauth/token" => (
+					"200 OK",
+					json!({ "access_token": "ms", "refresh_token": "refresh", "expires_in": 3600 }),
+				),
+				"/xbox/user/authenticate" | "/xsts/xsts/authorize" => (
+					"200 OK",
+					json!({ "Token": "xbox", "DisplayClaims": { "xui": [{ "uhs": "hash" }] } }),
+				),
+				"/mc/authentication/login_with_xbox" => {
+					assert!(req.body.contains("XBL3.0 x=hash;xbox"));
+					(
+						"200 OK",
+						json!({ "access_token": "mc-token", "expires_in": 86400 }),
+					)
+				}
+				"/mc/entitlements/mcstore" => {
+					let items = if owned {
+						json!([{ "name": "product_minecraft" }, { "name": "game_minecraft" }])
+					} else {
+						json!([])
+					};
+					("200 OK", json!({ "items": items }))
+				}
+				"/mc/minecraft/profile" => ("200 OK", json!({ "id": PROFILE_ID, "name": "Notch" })),
+				_ => ("404 Not Found", json!({})),
+			};
+			(status, Some(body))
+		})
+		.await;
+
+		(url, polls)
+	}
+
+	fn endpoints(base: &str) -> MicrosoftEndpoints {
+		MicrosoftEndpoints {
+			oauth: format!("{base}/oauth"),
+			xbox_user: format!("{base}/xbox"),
+			xsts: format!("{base}/xsts"),
+			minecraft: format!("{base}/mc"),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_device_code_login() {
+		let (base, polls) = start_auth_server(true).await;
+		let auth = MicrosoftAuth::new("client").with_endpoints(endpoints(&base));
+
+		let mut shown = None;
+		let account = auth
+			.login(|code| shown = Some(code.user_code.clone()))
+			.await
+			.unwrap();
+
+		assert_eq!(shown.as_deref(), Some("ABCD-1234"));
+		assert_eq!(polls.load(Ordering::SeqCst), 3);
+		assert_eq!(account.username(), "Notch");
+		assert_eq!(account.uuid(), &Uuid::parse_str(PROFILE_ID).unwrap());
+		assert_eq!(account.access_token(), Some("mc-token"));
+	}
+
+	#[tokio::test]
+	async fn test_refresh_keeps_refresh_token() {
+		let (base, polls) = start_auth_server(true).await;
+		let auth = MicrosoftAuth::new("client").with_endpoints(endpoints(&base));
+
+		let account = auth.refresh("refresh").await.unwrap();
+		assert_eq!(polls.load(Ordering::SeqCst), 0);
+		assert!(!account.needs_refresh());
+		let Account::Microsoft { refresh_token, .. } = account else {
+			panic!("expected microsoft account");
+		};
+		assert_eq!(refresh_token.as_deref(), Some("refresh"));
+	}
+
+	#[test]
+	fn test_token_xuid() {
+		let payload = URL_SAFE_NO_PAD.encode(r#"{"xuid":"2535400000000000","exp":0}"#);
+		let token = format!("eyJhbGciOiJIUzI1NiJ9.{payload}.sig");
+		assert_eq!(token_xuid(&token).as_deref(), Some("2535400000000000"));
+		assert_eq!(token_xuid("not-a-jwt"), None);
+	}
+
+	#[tokio::test]
+	async fn test_login_without_ownership_fails() {
+		let (base, _) = start_auth_server(false).await;
+		let auth = MicrosoftAuth::new("client").with_endpoints(endpoints(&base));
+
+		let err = auth.login(|_| {}).await.unwrap_err();
+		assert!(matches!(err, AuthError::NotOwned));
+	}
+}
@@ -1,7 +1,30 @@
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
 use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::core::paths;
+
+pub mod error;
+pub mod microsoft;
+pub mod store;
+#[cfg(test)]
+mod test_server;
+pub mod yggdrasil;
+
+use error::AuthError;
+use microsoft::{DeviceCode, MicrosoftAuth};
+use store::{FileSecretStore, SecretStore};
+use yggdrasil::YggdrasilClient;
+
+const ACCOUNTS_FILE: &str = "accounts.yml";
+/// 令牌在到期前这么多秒内就视为需要刷新
+const REFRESH_MARGIN_SECS: u64 = 300;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Account {
@@ -12,32 +35,88 @@ pub enum Account {
 	Microsoft {
 		username: String,
 		uuid: Uuid,
+		/// 令牌不写入 accounts.yml，由 `SecretStore` 单独保存
+		#[serde(skip)]
+		access_token: String,
+		#[serde(skip)]
+		refresh_token: Option<String>,
+		#[serde(default)]
+		client_id: Option<String>,
+		/// 访问令牌过期时间（Unix 秒）
+		#[serde(default)]
+		expires_at: Option<u64>,
+	},
+	/// 第三方 Yggdrasil 认证服务器（authlib-injector）
+	Yggdrasil {
+		username: String,
+		uuid: Uuid,
+		/// API 根地址
+		server: String,
+		#[serde(skip)]
 		access_token: String,
+		#[serde(skip)]
+		client_token: String,
 	},
 }
 
 impl Account {
 	pub fn offline(username: impl Into<String>) -> Self {
 		let username = username.into();
-		let uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, username.as_bytes());
+		let uuid = offline_uuid(&username);
 		Self::Offline { username, uuid }
 	}
 
 	pub fn username(&self) -> &str {
 		match self {
-			Self::Offline { username, .. } | Self::Microsoft { username, .. } => username,
+			Self::Offline { username, .. }
+			| Self::Microsoft { username, .. }
+			| Self::Yggdrasil { username, .. } => username,
 		}
 	}
 
 	pub fn uuid(&self) -> &Uuid {
 		match self {
-			Self::Offline { uuid, .. } | Self::Microsoft { uuid, .. } => uuid,
+			Self::Offline { uuid, .. }
+			| Self::Microsoft { uuid, .. }
+			| Self::Yggdrasil { uuid, .. } => uuid,
 		}
 	}
 
 	pub fn access_token(&self) -> Option<&str> {
 		match self {
-			Self::Microsoft { access_token, .. } => Some(access_token),
+			Self::Microsoft { access_token, .. } | Self::Yggdrasil { access_token, .. } => {
+				Some(access_token)
+			}
+			_ => None,
+		}
+	}
+
+	/// 启动参数中的 userType
+	pub fn user_type(&self) -> &'static str {
+		match self {
+			Self::Offline { .. } => "legacy",
+			Self::Microsoft { .. } => "msa",
+			Self::Yggdrasil { .. } => "mojang",
+		}
+	}
+
+	pub fn xuid(&self) -> Option<String> {
+		match self {
+			Self::Microsoft { access_token, .. } => microsoft::token_xuid(access_token),
+			_ => None,
+		}
+	}
+
+	pub fn yggdrasil_server(&self) -> Option<&str> {
+		match self {
+			Self::Yggdrasil { server, .. } => Some(server),
+			_ => None,
+		}
+	}
+
+	pub fn client_id(&self) -> Option<&str> {
+		match self {
+			Self::Microsoft { client_id, .. } => client_id.as_deref(),
 			_ => None,
 		}
 	}
@@ -45,27 +124,189 @@ impl Account {
 	pub fn is_offline(&self) -> bool {
 		matches!(self, Self::Offline { .. })
 	}
+
+	pub fn needs_refresh(&self) -> bool {
+		match self {
+			Self::Microsoft {
+				access_token,
+				expires_at,
+				..
+			} => {
+				access_token.is_empty()
+					|| expires_at.is_some_and(|t| t <= unix_now() + REFRESH_MARGIN_SECS)
+			}
+			Self::Yggdrasil { access_token, .. } => access_token.is_empty(),
+			Self::Offline { .. } => false,
+		}
+	}
+
+	fn secret_key(&self) -> String {
+		let kind = match self {
+			Self::Offline { .. } => "offline",
+			Self::Microsoft { .. } => "microsoft",
+			Self::Yggdrasil { .. } => "yggdrasil",
+		};
+		format!("{kind}:{}", self.uuid().simple())
+	}
+
+	fn secrets(&self) -> Option<AccountSecrets> {
+		match self {
+			Self::Offline { .. } => None,
+			Self::Microsoft {
+				access_token,
+				refresh_token,
+				..
+			} => Some(AccountSecrets {
+				access_token: access_token.clone(),
+				refresh_token: refresh_token.clone(),
+				client_token: None,
+			}),
+			Self::Yggdrasil {
+				access_token,
+				client_token,
+				..
+			} => Some(AccountSecrets {
+				access_token: access_token.clone(),
+				refresh_token: None,
+				client_token: Some(client_token.clone()),
+			}),
+		}
+	}
+
+	fn restore_secrets(&mut self, saved: AccountSecrets) {
+		match self {
+			Self::Offline { .. } => {}
+			Self::Microsoft {
+				access_token,
+				refresh_token,
+				..
+			} => {
+				*access_token = saved.access_token;
+				*refresh_token = saved.refresh_token;
+			}
+			Self::Yggdrasil {
+				access_token,
+				client_token,
+				..
+			} => {
+				*access_token = saved.access_token;
+				*client_token = saved.client_token.unwrap_or_default();
+			}
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct AccountSecrets {
+	access_token: String,
+	#[serde(default)]
+	refresh_token: Option<String>,
+	#[serde(default)]
+	client_token: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct AccountsFile {
+	#[serde(default)]
+	current: Option<usize>,
+	#[serde(default)]
+	accounts: Vec<Account>,
+}
+
+struct AccountStorage {
+	path: PathBuf,
+	secrets: Box<dyn SecretStore>,
 }
 
 pub struct AccountManager {
 	accounts: RwLock<Vec<Account>>,
 	current: RwLock<Option<usize>>,
+	storage: Option<AccountStorage>,
 }
 
 impl AccountManager {
+	/// 仅保存在内存中的账户列表
 	pub fn new() -> Self {
 		Self {
 			accounts: RwLock::new(Vec::new()),
 			current: RwLock::new(None),
+			storage: None,
+		}
+	}
+
+	pub fn open_default() -> Result<Self> {
+		let dir = paths::config_dir()?;
+		Self::with_storage(dir.join(ACCOUNTS_FILE), Box::new(FileSecretStore::new(dir)))
+	}
+
+	/// 从 `path` 读取账户列表，令牌从 `secrets` 中恢复
+	pub fn with_storage(path: PathBuf, secrets: Box<dyn SecretStore>) -> Result<Self> {
+		let file: AccountsFile = match fs::read_to_string(&path) {
+			Ok(content) => serde_yaml::from_str(&content).context("parse accounts")?,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => AccountsFile::default(),
+			Err(e) => return Err(e).context("read accounts"),
+		};
+
+		let mut accounts = file.accounts;
+		for account in accounts.iter_mut().filter(|a| !a.is_offline()) {
+			let key = account.secret_key();
+			match secrets.get(&key) {
+				Ok(Some(raw)) => {
+					let saved = serde_json::from_str(&raw).context("parse account secrets")?;
+					account.restore_secrets(saved);
+				}
+				Ok(None) => {}
+				Err(e) => warn!("Failed to load secrets of {}: {}", key, e),
+			}
+		}
+		let current = file.current.filter(|&idx| idx < accounts.len());
+
+		Ok(Self {
+			accounts: RwLock::new(accounts),
+			current: RwLock::new(current),
+			storage: Some(AccountStorage { path, secrets }),
+		})
+	}
+
+	fn save(&self) -> Result<()> {
+		let Some(storage) = &self.storage else {
+			return Ok(());
+		};
+
+		let file = AccountsFile {
+			current: *self.current.read().unwrap(),
+			accounts: self.list(),
+		};
+		for account in &file.accounts {
+			if let Some(secrets) = account.secrets() {
+				storage
+					.secrets
+					.set(&account.secret_key(), &serde_json::to_string(&secrets)?)?;
+			}
+		}
+
+		if let Some(dir) = storage.path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		fs::write(&storage.path, serde_yaml::to_string(&file)?)?;
+		Ok(())
+	}
+
+	fn save_or_warn(&self) {
+		if let Err(e) = self.save() {
+			warn!("Failed to save accounts: {:#}", e);
 		}
 	}
 
 	pub fn add_offline(&self, username: impl Into<String>) -> usize {
 		let account = Account::offline(username);
-		let mut accounts = self.accounts.write().unwrap();
-		let idx = accounts.len();
-		accounts.push(account);
+		let idx = {
+			let mut accounts = self.accounts.write().unwrap();
+			accounts.push(account);
+			accounts.len() - 1
+		};
 		*self.current.write().unwrap() = Some(idx);
+		self.save_or_warn();
 		idx
 	}
 
@@ -74,8 +315,13 @@ impl AccountManager {
 		self.accounts.read().unwrap().get(idx).cloned()
 	}
 
+	pub fn current_index(&self) -> Option<usize> {
+		*self.current.read().unwrap()
+	}
+
 	pub fn select(&self, idx: Option<usize>) {
 		*self.current.write().unwrap() = idx;
+		self.save_or_warn();
 	}
 
 	pub fn list(&self) -> Vec<Account> {
@@ -83,9 +329,12 @@ impl AccountManager {
 	}
 
 	pub fn remove(&self, idx: usize) {
-		let mut accounts = self.accounts.write().unwrap();
-		if idx < accounts.len() {
-			accounts.remove(idx);
+		let removed = {
+			let mut accounts = self.accounts.write().unwrap();
+			if idx >= accounts.len() {
+				return;
+			}
+			let removed = accounts.remove(idx);
 			let mut current = self.current.write().unwrap();
 			if *current == Some(idx) {
 				*current = None;
@@ -94,19 +343,119 @@ impl AccountManager {
 					*current = Some(c - 1);
 				}
 			}
+			removed
+		};
+
+		if let Some(storage) = &self.storage
+			&& removed.secrets().is_some()
+			&& let Err(e) = storage.secrets.remove(&removed.secret_key())
+		{
+			warn!("Failed to remove account secrets: {:#}", e);
 		}
+		self.save_or_warn();
+		invalidate_in_background(removed);
+	}
+
+	/// 返回当前账户，令牌过期或失效时先刷新
+	pub async fn refresh_current(&self) -> Result<Option<Account>, AuthError> {
+		let Some(account) = self.current() else {
+			return Ok(None);
+		};
+
+		let refreshed = match &account {
+			Account::Offline { .. } => return Ok(Some(account)),
+			Account::Microsoft { .. } if !account.needs_refresh() => return Ok(Some(account)),
+			Account::Microsoft {
+				refresh_token: Some(refresh_token),
+				client_id: Some(client_id),
+				..
+			} => {
+				MicrosoftAuth::new(client_id.as_str())
+					.refresh(refresh_token)
+					.await?
+			}
+			Account::Microsoft { .. } => return Err(AuthError::SessionExpired),
+			Account::Yggdrasil {
+				username,
+				uuid,
+				server,
+				access_token,
+				client_token,
+			} => {
+				let client = YggdrasilClient::new(server.as_str());
+				match client.validate(access_token, client_token).await {
+					Ok(true) => return Ok(Some(account)),
+					Ok(false) => {}
+					// 验证服务器不可达时仍尝试使用现有令牌启动
+					Err(e) => {
+						warn!("Validate yggdrasil token failed: {}", e);
+						return Ok(Some(account));
+					}
+				}
+				let session = client.refresh(access_token, client_token, None).await?;
+				Account::Yggdrasil {
+					username: username.clone(),
+					uuid: *uuid,
+					server: server.clone(),
+					access_token: session.access_token,
+					client_token: session.client_token,
+				}
+			}
+		};
+
+		self.add_or_replace(refreshed.clone());
+		Ok(Some(refreshed))
 	}
 
-	// TODO: Microsoft OAuth 登录
-	pub async fn login_microsoft(&self, client_id: &str) -> Result<usize, ()> {
-		todo!("")
-		//     1. 设备码流程获取 device_code
-		//     2. 用户授权后获取 access_token
-		//     3. Xbox Live 认证
-		//     4. XSTS 认证
-		//     5. Minecraft 认证获取 MC access_token
-		//     6. 获取用户 profile (username, uuid)
-		//     7. 存储到 keyring
+	/// 登录第三方认证服务器，`server` 可以是用户输入的任意入口地址。
+	/// 账户有多个角色时需通过 `profile` 指定角色名，未指定则返回
+	/// [`AuthError::MultipleProfiles`]
+	pub async fn login_yggdrasil(
+		&self,
+		server: &str,
+		username: &str,
+		password: &str,
+		profile: Option<&str>,
+	) -> Result<usize, AuthError> {
+		let client = YggdrasilClient::resolve(server).await?;
+		let client_token = Uuid::new_v4().simple().to_string();
+		let session = client
+			.authenticate(username, password, &client_token)
+			.await?;
+		let account = client
+			.bind_profile(session, profile)
+			.await?
+			.into_account(client.server())?;
+		Ok(self.add_or_replace(account))
+	}
+
+	/// 设备码登录微软账户，`on_code` 用于向用户展示验证码
+	pub async fn login_microsoft<F>(&self, client_id: &str, on_code: F) -> Result<usize, AuthError>
+	where
+		F: FnOnce(&DeviceCode),
+	{
+		let account = MicrosoftAuth::new(client_id).login(on_code).await?;
+		Ok(self.add_or_replace(account))
+	}
+
+	/// 同一 UUID 的账户只保留最新的一份，并设为当前账户
+	fn add_or_replace(&self, account: Account) -> usize {
+		let idx = {
+			let mut accounts = self.accounts.write().unwrap();
+			match accounts.iter().position(|a| a.uuid() == account.uuid()) {
+				Some(idx) => {
+					accounts[idx] = account;
+					idx
+				}
+				None => {
+					accounts.push(account);
+					accounts.len() - 1
+				}
+			}
+		};
+		*self.current.write().unwrap() = Some(idx);
+		self.save_or_warn();
+		idx
 	}
 }
 
@@ -116,6 +465,98 @@ impl Default for AccountManager {
 	}
 }
 
+/// 移除第三方认证账户时吊销其令牌，失败不影响移除
+fn invalidate_in_background(account: Account) {
+	let Account::Yggdrasil {
+		server,
+		access_token,
+		client_token,
+		..
+	} = account
+	else {
+		return;
+	};
+	let Ok(rt) = tokio::runtime::Handle::try_current() else {
+		warn!("No runtime to invalidate yggdrasil token");
+		return;
+	};
+	rt.spawn(async move {
+		if let Err(e) = YggdrasilClient::new(server)
+			.invalidate(&access_token, &client_token)
+			.await
+		{
+			warn!("Invalidate yggdrasil token failed: {}", e);
+		}
+	});
+}
+
 pub fn offline_uuid(username: &str) -> Uuid {
 	Uuid::new_v5(&Uuid::NAMESPACE_OID, username.as_bytes())
 }
+
+pub(crate) fn unix_now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+	use store::MemorySecretStore;
+
+	/// 测试间共享同一个内存存储，模拟重启后重新读取
+	struct SharedStore(Arc<MemorySecretStore>);
+
+	impl SecretStore for SharedStore {
+		fn get(&self, key: &str) -> Result<Option<String>> {
+			self.0.get(key)
+		}
+		fn set(&self, key: &str, value: &str) -> Result<()> {
+			self.0.set(key, value)
+		}
+		fn remove(&self, key: &str) -> Result<()> {
+			self.0.remove(key)
+		}
+	}
+
+	#[test]
+	fn test_accounts_persist_without_plaintext_tokens() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join(ACCOUNTS_FILE);
+		let secrets = Arc::new(MemorySecretStore::default());
+		let open = || {
+			AccountManager::with_storage(path.clone(), Box::new(SharedStore(Arc::clone(&secrets))))
+				.unwrap()
+		};
+
+		let manager = open();
+		manager.add_offline("Steve");
+		manager.add_or_replace(Account::Microsoft {
+			username: "Notch".into(),
+			uuid: Uuid::new_v4(),
+			access_token: "mc-token".into(),
+			refresh_token: Some("refresh".into()),
+			client_id: Some("client".into()),
+			expires_at: Some(unix_now() + 3600),
+		});
+		manager.select(Some(0));
+
+		let content = fs::read_to_string(&path).unwrap();
+		assert!(!content.contains("mc-token"));
+		assert!(!content.contains("refresh"));
+
+		let reopened = open();
+		assert_eq!(reopened.list().len(), 2);
+		assert_eq!(reopened.current().unwrap().username(), "Steve");
+		let msa = &reopened.list()[1];
+		assert_eq!(msa.access_token(), Some("mc-token"));
+		assert!(!msa.needs_refresh());
+
+		reopened.remove(1);
+		assert!(secrets.get(&msa.secret_key()).unwrap().is_none());
+		assert_eq!(open().list().len(), 1);
+	}
+}
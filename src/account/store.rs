@@ -0,0 +1,206 @@
+use anyhow::{Context, Result, anyhow};
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SECRETS_FILE: &str = "secrets.bin";
+const KEY_FILE: &str = "secrets.key";
+
+/// 账户令牌等敏感信息的存储后端
+pub trait SecretStore: Send + Sync {
+	fn get(&self, key: &str) -> Result<Option<String>>;
+	fn set(&self, key: &str, value: &str) -> Result<()>;
+	fn remove(&self, key: &str) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct MemorySecretStore {
+	entries: Mutex<HashMap<String, String>>,
+}
+
+impl SecretStore for MemorySecretStore {
+	fn get(&self, key: &str) -> Result<Option<String>> {
+		Ok(self.entries.lock().unwrap().get(key).cloned())
+	}
+
+	fn set(&self, key: &str, value: &str) -> Result<()> {
+		self.entries
+			.lock()
+			.unwrap()
+			.insert(key.to_string(), value.to_string());
+		Ok(())
+	}
+
+	fn remove(&self, key: &str) -> Result<()> {
+		self.entries.lock().unwrap().remove(key);
+		Ok(())
+	}
+}
+
+/// AES-256-GCM 加密的本地文件，密钥单独保存在同一目录下。
+/// 只用于避免令牌以明文出现在配置或日志中，不能防御能读取用户目录的本地程序。
+pub struct FileSecretStore {
+	dir: PathBuf,
+	rng: SystemRandom,
+	lock: Mutex<()>,
+}
+
+impl FileSecretStore {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self {
+			dir: dir.into(),
+			rng: SystemRandom::new(),
+			lock: Mutex::new(()),
+		}
+	}
+
+	fn key(&self) -> Result<LessSafeKey> {
+		let path = self.dir.join(KEY_FILE);
+		let bytes = match fs::read(&path) {
+			Ok(bytes) => bytes,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				let mut bytes = vec![0u8; AES_256_GCM.key_len()];
+				self.rng
+					.fill(&mut bytes)
+					.map_err(|_| anyhow!("Failed to generate secret key"))?;
+				fs::create_dir_all(&self.dir).context("Failed to create secret directory")?;
+				write_private(&path, &bytes)?;
+				bytes
+			}
+			Err(e) => return Err(e).context("Failed to read secret key"),
+		};
+		let key =
+			UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| anyhow!("Invalid secret key"))?;
+		Ok(LessSafeKey::new(key))
+	}
+
+	fn load(&self, key: &LessSafeKey) -> Result<HashMap<String, String>> {
+		let data = match fs::read(self.dir.join(SECRETS_FILE)) {
+			Ok(data) => data,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+			Err(e) => return Err(e).context("Failed to read secrets"),
+		};
+		if data.len() < NONCE_LEN {
+			return Err(anyhow!("Secrets file is corrupted"));
+		}
+
+		let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+		let nonce =
+			Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Invalid nonce"))?;
+		let mut buf = ciphertext.to_vec();
+		let plain = key
+			.open_in_place(nonce, Aad::empty(), &mut buf)
+			.map_err(|_| anyhow!("Failed to decrypt secrets"))?;
+		serde_json::from_slice(plain).context("Failed to parse secrets")
+	}
+
+	fn save(&self, key: &LessSafeKey, entries: &HashMap<String, String>) -> Result<()> {
+		let mut nonce = [0u8; NONCE_LEN];
+		self.rng
+			.fill(&mut nonce)
+			.map_err(|_| anyhow!("Failed to generate nonce"))?;
+
+		let mut buf = serde_json::to_vec(entries)?;
+		key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut buf)
+			.map_err(|_| anyhow!("Failed to encrypt secrets"))?;
+
+		let mut data = nonce.to_vec();
+		data.extend_from_slice(&buf);
+		fs::create_dir_all(&self.dir).context("Failed to create secret directory")?;
+		write_private(&self.dir.join(SECRETS_FILE), &data)
+	}
+
+	fn modify<F>(&self, f: F) -> Result<()>
+	where
+		F: FnOnce(&mut HashMap<String, String>),
+	{
+		let _guard = self.lock.lock().unwrap();
+		let key = self.key()?;
+		let mut entries = self.load(&key)?;
+		f(&mut entries);
+		self.save(&key, &entries)
+	}
+}
+
+impl SecretStore for FileSecretStore {
+	fn get(&self, key: &str) -> Result<Option<String>> {
+		let _guard = self.lock.lock().unwrap();
+		if !self.dir.join(SECRETS_FILE).exists() {
+			return Ok(None);
+		}
+		Ok(self.load(&self.key()?)?.remove(key))
+	}
+
+	fn set(&self, key: &str, value: &str) -> Result<()> {
+		self.modify(|entries| {
+			entries.insert(key.to_string(), value.to_string());
+		})
+	}
+
+	fn remove(&self, key: &str) -> Result<()> {
+		self.modify(|entries| {
+			entries.remove(key);
+		})
+	}
+}
+
+/// 临时文件创建时即为仅所有者可读写，密钥不会短暂地对其他用户可见
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+	let temp = path.with_extension("tmp");
+	// 上次中断留下的临时文件权限未知，重新创建
+	let _ = fs::remove_file(&temp);
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::OpenOptionsExt;
+		options.mode(0o600);
+	}
+	options
+		.open(&temp)
+		.and_then(|mut file| {
+			file.write_all(data)?;
+			file.sync_all()
+		})
+		.with_context(|| format!("Failed to write {}", temp.display()))?;
+	fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_file_store_encrypts_and_roundtrips() {
+		let dir = tempfile::tempdir().unwrap();
+		let store = FileSecretStore::new(dir.path());
+
+		assert_eq!(store.get("token").unwrap(), None);
+		store.set("token", "very-secret-value").unwrap();
+		store.set("other", "x").unwrap();
+
+		let raw = fs::read(dir.path().join(SECRETS_FILE)).unwrap();
+		assert!(!String::from_utf8_lossy(&raw).contains("very-secret-value"));
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			for file in [SECRETS_FILE, KEY_FILE] {
+				let meta = fs::metadata(dir.path().join(file)).unwrap();
+				assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+			}
+		}
+
+		let reopened = FileSecretStore::new(dir.path());
+		assert_eq!(
+			reopened.get("token").unwrap().as_deref(),
+			Some("very-secret-value")
+		);
+		reopened.remove("token").unwrap();
+		assert_eq!(reopened.get("token").unwrap(), None);
+		assert_eq!(reopened.get("other").unwrap().as_deref(), Some("x"));
+	}
+}
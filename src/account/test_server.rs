@@ -0,0 +1,73 @@
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 测试服务端收到的请求，`body` 按 Content-Length 读取完整
+pub struct Request {
+	pub path: String,
+	pub body: String,
+}
+
+/// 启动本地 HTTP 服务端，每个请求交给 `handler` 返回状态行和 JSON 响应体
+pub async fn start<F>(handler: F) -> String
+where
+	F: Fn(&Request) -> (&'static str, Option<Value>) + Send + Sync + 'static,
+{
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let url = format!("http://{}", listener.local_addr().unwrap());
+	let handler = Arc::new(handler);
+
+	tokio::spawn(async move {
+		loop {
+			let (mut socket, _) = listener.accept().await.unwrap();
+			let handler = Arc::clone(&handler);
+			tokio::spawn(async move {
+				let req = read_request(&mut socket).await;
+				let (status, body) = handler(&req);
+				let body = body.map(|b| b.to_string()).unwrap_or_default();
+				let response = format!(
+					"HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+					status,
+					body.len(),
+					body
+				);
+				socket.write_all(response.as_bytes()).await.unwrap();
+			});
+		}
+	});
+
+	url
+}
+
+/// 先读到请求头结束，再读取 Content-Length 字节的请求体，请求头和请求体可能分开到达
+async fn read_request(socket: &mut TcpStream) -> Request {
+	let mut data = Vec::new();
+	let mut buf = [0u8; 4096];
+	let header_end = loop {
+		if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+			break pos + 4;
+		}
+		let n = socket.read(&mut buf).await.unwrap();
+		assert!(n > 0, "connection closed before end of headers");
+		data.extend_from_slice(&buf[..n]);
+	};
+
+	let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+	let length = head
+		.lines()
+		.filter_map(|line| line.split_once(':'))
+		.find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+		.and_then(|(_, value)| value.trim().parse::<usize>().ok())
+		.unwrap_or(0);
+	while data.len() < header_end + length {
+		let n = socket.read(&mut buf).await.unwrap();
+		assert!(n > 0, "connection closed before end of body");
+		data.extend_from_slice(&buf[..n]);
+	}
+
+	Request {
+		path: head.split_whitespace().nth(1).unwrap_or("").to_string(),
+		body: String::from_utf8_lossy(&data[header_end..header_end + length]).to_string(),
+	}
+}
@@ -0,0 +1,456 @@
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::account::Account;
+use crate::account::error::AuthError;
+use crate::net::download::{Checksum, DownloadClient, DownloadRequest};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const ALI_HEADER: &str = "x-authlib-injector-api-location";
+const AUTHLIB_INJECTOR_LATEST: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+const AUTHLIB_INJECTOR_JAR: &str = "authlib-injector.jar";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameProfile {
+	pub id: String,
+	pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YggdrasilSession {
+	pub access_token: String,
+	pub client_token: String,
+	#[serde(default)]
+	pub available_profiles: Vec<GameProfile>,
+	#[serde(default)]
+	pub selected_profile: Option<GameProfile>,
+}
+
+impl YggdrasilSession {
+	/// 令牌必须已绑定角色，未绑定时先调用 [`YggdrasilClient::bind_profile`]
+	pub fn into_account(self, server: &str) -> Result<Account, AuthError> {
+		let profile = self.selected_profile.ok_or(AuthError::NoProfile)?;
+		let uuid = Uuid::parse_str(&profile.id).map_err(|_| AuthError::NoProfile)?;
+		Ok(Account::Yggdrasil {
+			username: profile.name,
+			uuid,
+			server: server.to_string(),
+			access_token: self.access_token,
+			client_token: self.client_token,
+		})
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ErrorResponse {
+	error: String,
+	#[serde(default)]
+	error_message: Option<String>,
+}
+
+/// authlib-injector 规范的 Yggdrasil 客户端，`server` 为 API 根地址
+pub struct YggdrasilClient {
+	client: Client,
+	server: String,
+}
+
+impl YggdrasilClient {
+	pub fn new(server: impl Into<String>) -> Self {
+		Self {
+			client: Client::new(),
+			server: server.into().trim_end_matches('/').to_string(),
+		}
+	}
+
+	/// 按 API 地址指示（ALI）解析用户输入的地址，得到真正的 API 根地址
+	pub async fn resolve(url: &str) -> Result<Self, AuthError> {
+		let url = if url.starts_with("http://") || url.starts_with("https://") {
+			url.to_string()
+		} else {
+			format!("https://{url}")
+		};
+		let resp = Client::new()
+			.get(&url)
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?;
+
+		let location = resp
+			.headers()
+			.get(ALI_HEADER)
+			.and_then(|v| v.to_str().ok())
+			.and_then(|v| resp.url().join(v).ok());
+		Ok(match location {
+			Some(api) => Self::new(api.as_str()),
+			None => Self::new(url),
+		})
+	}
+
+	pub fn server(&self) -> &str {
+		&self.server
+	}
+
+	/// API 元数据，原样传给 authlib-injector 以免游戏启动时再请求一次
+	pub async fn metadata(&self) -> Result<String, AuthError> {
+		let url = format!("{}/", self.server);
+		let resp = self
+			.client
+			.get(&url)
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?;
+		if !resp.status().is_success() {
+			return Err(AuthError::UnexpectedStatus {
+				url,
+				status: resp.status(),
+			});
+		}
+		Ok(resp.text().await?)
+	}
+
+	pub async fn authenticate(
+		&self,
+		username: &str,
+		password: &str,
+		client_token: &str,
+	) -> Result<YggdrasilSession, AuthError> {
+		let body = json!({
+			"agent": { "name": "Minecraft", "version": 1 },
+			"username": username,
+			"password": password,
+			"clientToken": client_token,
+			"requestUser": false,
+		});
+		let resp = self.post("authserver/authenticate", &body).await?;
+		parse_response(resp).await
+	}
+
+	/// `selected_profile` 用于把令牌绑定到指定角色，已绑定的令牌传 `None`
+	pub async fn refresh(
+		&self,
+		access_token: &str,
+		client_token: &str,
+		selected_profile: Option<&GameProfile>,
+	) -> Result<YggdrasilSession, AuthError> {
+		let mut body = json!({
+			"accessToken": access_token,
+			"clientToken": client_token,
+			"requestUser": false,
+		});
+		if let Some(profile) = selected_profile {
+			body["selectedProfile"] = json!(profile);
+		}
+		let resp = self.post("authserver/refresh", &body).await?;
+		parse_response(resp).await
+	}
+
+	/// 登录后服务端未选定角色时，把令牌绑定到 `wanted`（角色名）或唯一的可用角色。
+	/// 有多个角色且未指定时返回 [`AuthError::MultipleProfiles`] 供调用方询问用户
+	pub async fn bind_profile(
+		&self,
+		session: YggdrasilSession,
+		wanted: Option<&str>,
+	) -> Result<YggdrasilSession, AuthError> {
+		if let Some(selected) = &session.selected_profile
+			&& wanted.is_none_or(|name| selected.name == name)
+		{
+			return Ok(session);
+		}
+
+		let profile = match wanted {
+			Some(name) => session
+				.available_profiles
+				.iter()
+				.find(|p| p.name == name)
+				.ok_or(AuthError::NoProfile)?,
+			None => match session.available_profiles.as_slice() {
+				[] => return Err(AuthError::NoProfile),
+				[only] => only,
+				many => {
+					return Err(AuthError::MultipleProfiles(
+						many.iter().map(|p| p.name.clone()).collect(),
+					));
+				}
+			},
+		};
+		let mut bound = self
+			.refresh(&session.access_token, &session.client_token, Some(profile))
+			.await?;
+		if bound.selected_profile.is_none() {
+			bound.selected_profile = Some(profile.clone());
+		}
+		Ok(bound)
+	}
+
+	pub async fn validate(
+		&self,
+		access_token: &str,
+		client_token: &str,
+	) -> Result<bool, AuthError> {
+		let body = json!({ "accessToken": access_token, "clientToken": client_token });
+		let resp = self.post("authserver/validate", &body).await?;
+		match resp.status() {
+			StatusCode::NO_CONTENT => Ok(true),
+			StatusCode::FORBIDDEN => Ok(false),
+			status => Err(AuthError::UnexpectedStatus {
+				url: resp.url().to_string(),
+				status,
+			}),
+		}
+	}
+
+	pub async fn invalidate(
+		&self,
+		access_token: &str,
+		client_token: &str,
+	) -> Result<(), AuthError> {
+		let body = json!({ "accessToken": access_token, "clientToken": client_token });
+		let resp = self.post("authserver/invalidate", &body).await?;
+		parse_empty(resp).await
+	}
+
+	async fn post(
+		&self,
+		path: &str,
+		body: &serde_json::Value,
+	) -> Result<reqwest::Response, AuthError> {
+		Ok(self
+			.client
+			.post(format!("{}/{}", self.server, path))
+			.json(body)
+			.timeout(REQUEST_TIMEOUT)
+			.send()
+			.await?)
+	}
+}
+
+async fn parse_response<T: serde::de::DeserializeOwned>(
+	resp: reqwest::Response,
+) -> Result<T, AuthError> {
+	if resp.status().is_success() {
+		return Ok(resp.json().await?);
+	}
+	Err(server_error(resp).await)
+}
+
+async fn parse_empty(resp: reqwest::Response) -> Result<(), AuthError> {
+	if resp.status().is_success() {
+		return Ok(());
+	}
+	Err(server_error(resp).await)
+}
+
+async fn server_error(resp: reqwest::Response) -> AuthError {
+	let url = resp.url().to_string();
+	let status = resp.status();
+	match resp.json::<ErrorResponse>().await {
+		Ok(e) => AuthError::Yggdrasil {
+			message: e.error_message.unwrap_or_default(),
+			error: e.error,
+		},
+		Err(_) => AuthError::UnexpectedStatus { url, status },
+	}
+}
+
+/// 启动时注入到 JVM 的 authlib-injector 参数
+#[derive(Debug, Clone)]
+pub struct AuthlibInjector {
+	pub jar: PathBuf,
+	pub server: String,
+	/// Base64 编码的 API 元数据
+	pub prefetched: Option<String>,
+}
+
+impl AuthlibInjector {
+	pub fn jvm_args(&self) -> Vec<String> {
+		let mut args = vec![format!(
+			"-javaagent:{}={}",
+			self.jar.to_string_lossy(),
+			self.server
+		)];
+		if let Some(prefetched) = &self.prefetched {
+			args.push(format!(
+				"-Dauthlibinjector.yggdrasil.prefetched={prefetched}"
+			));
+		}
+		args
+	}
+}
+
+#[derive(Deserialize)]
+struct AuthlibArtifact {
+	download_url: String,
+	#[serde(default)]
+	checksums: AuthlibChecksums,
+}
+
+#[derive(Default, Deserialize)]
+struct AuthlibChecksums {
+	#[serde(default)]
+	sha256: Option<String>,
+}
+
+/// 下载最新的 authlib-injector 到 `dir`，离线时沿用已有的文件
+pub async fn ensure_authlib_injector(
+	client: &DownloadClient,
+	dir: &Path,
+) -> anyhow::Result<PathBuf> {
+	let jar = dir.join(AUTHLIB_INJECTOR_JAR);
+	let artifact = match client.fetch_text(AUTHLIB_INJECTOR_LATEST).await {
+		Ok(text) => serde_json::from_str::<AuthlibArtifact>(&text)
+			.context("Failed to parse authlib-injector metadata")?,
+		Err(e) if jar.exists() => {
+			tracing::warn!(
+				"Fetch authlib-injector metadata failed: {}, using local copy",
+				e
+			);
+			return Ok(jar);
+		}
+		Err(e) => return Err(e).context("Failed to fetch authlib-injector metadata"),
+	};
+
+	let mut request = DownloadRequest::new(artifact.download_url, &jar);
+	if let Some(sha256) = artifact.checksums.sha256 {
+		request = request.with_checksum(Checksum::Sha256(sha256));
+	}
+	client
+		.download(request, |_| {}, None)
+		.await
+		.context("Failed to download authlib-injector")?;
+	Ok(jar)
+}
+
+/// 预取 API 元数据，失败时交由 authlib-injector 自行请求
+pub async fn prefetch_metadata(server: &str) -> Option<String> {
+	match YggdrasilClient::new(server).metadata().await {
+		Ok(metadata) => Some(STANDARD.encode(metadata)),
+		Err(e) => {
+			tracing::warn!("Prefetch yggdrasil metadata failed: {}", e);
+			None
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::account::test_server;
+
+	const PROFILE_ID: &str = "4f8a1b2c3d4e4f5a8b9c0d1e2f3a4b5c";
+	const STEVE_ID: &str = "8667ba71b85a4004af54457a9734eed7";
+
+	/// 只认可令牌 "token-2"/"token-3" 的最小 Yggdrasil 服务端
+	async fn start_yggdrasil_server() -> String {
+		let url = test_server::start(|req| {
+			let profile = json!({ "id": PROFILE_ID, "name": "Alex" });
+			match req.path.as_str() {
+				"/api/" => ("200 OK", Some(json!({ "meta": { "serverName": "Test" } }))),
+				"/api/authserver/authenticate" if req.body.contains("\"username\":\"multi\"") => {
+					let steve = json!({ "id": STEVE_ID, "name": "Steve" });
+					(
+						"200 OK",
+						Some(json!({
+							"accessToken": "token-1",
+							"clientToken": "client",
+							"availableProfiles": [profile, steve],
+						})),
+					)
+				}
+				"/api/authserver/authenticate" if req.body.contains("\"password\":\"secret\"") => (
+					"200 OK",
+					Some(json!({
+						"accessToken": "token-1",
+						"clientToken": "client",
+						"availableProfiles": [profile],
+					})),
+				),
+				"/api/authserver/authenticate" => (
+					"403 Forbidden",
+					Some(json!({
+						"error": "ForbiddenOperationException",
+						"errorMessage": "Invalid credentials.",
+					})),
+				),
+				// 绑定角色的刷新返回 token-2，普通刷新返回 token-3
+				"/api/authserver/refresh" => (
+					"200 OK",
+					Some(json!({
+						"accessToken": if req.body.contains("selectedProfile") {
+							"token-2"
+						} else {
+							"token-3"
+						},
+						"clientToken": "client",
+						"selectedProfile": profile,
+					})),
+				),
+				"/api/authserver/validate"
+					if req.body.contains("token-2") || req.body.contains("token-3") =>
+				{
+					("204 No Content", None)
+				}
+				"/api/authserver/validate" => ("403 Forbidden", None),
+				"/api/authserver/invalidate" => ("204 No Content", None),
+				_ => ("404 Not Found", None),
+			}
+		})
+		.await;
+
+		format!("{url}/api")
+	}
+
+	#[tokio::test]
+	async fn test_yggdrasil_session_lifecycle() {
+		let server = start_yggdrasil_server().await;
+		let client = YggdrasilClient::new(&server);
+
+		let err = client
+			.authenticate("alex", "wrong", "client")
+			.await
+			.unwrap_err();
+		assert!(
+			matches!(err, AuthError::Yggdrasil { ref error, .. } if error == "ForbiddenOperationException")
+		);
+
+		// 服务端未选定角色，令牌需要先绑定到唯一的可用角色
+		let session = client
+			.authenticate("alex", "secret", "client")
+			.await
+			.unwrap();
+		assert!(session.selected_profile.is_none());
+		let bound = client.bind_profile(session, None).await.unwrap();
+		let account = bound.into_account(&server).unwrap();
+		assert_eq!(account.username(), "Alex");
+		assert_eq!(account.access_token(), Some("token-2"));
+
+		assert!(!client.validate("token-1", "client").await.unwrap());
+		let refreshed = client.refresh("token-2", "client", None).await.unwrap();
+		assert_eq!(refreshed.access_token, "token-3");
+		assert!(client.validate("token-3", "client").await.unwrap());
+		client.invalidate("token-3", "client").await.unwrap();
+
+		let multi = client
+			.authenticate("multi", "secret", "client")
+			.await
+			.unwrap();
+		let err = client.bind_profile(multi.clone(), None).await.unwrap_err();
+		assert!(
+			matches!(err, AuthError::MultipleProfiles(ref names) if names == &["Alex", "Steve"])
+		);
+		let bound = client.bind_profile(multi, Some("Alex")).await.unwrap();
+		assert_eq!(bound.access_token, "token-2");
+
+		let prefetched = prefetch_metadata(&server).await.unwrap();
+		let metadata = String::from_utf8(STANDARD.decode(prefetched).unwrap()).unwrap();
+		assert!(metadata.contains("serverName"));
+	}
+}
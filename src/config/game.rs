@@ -1,3 +1,4 @@
+use crate::game::args::QuickPlay;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -8,8 +9,15 @@ pub struct GameConfig {
 	pub max_memory_mb: Option<u32>,
 	pub window_width: Option<u32>,
 	pub window_height: Option<u32>,
+	pub fullscreen: Option<bool>,
+	/// 模组较多的实例启动更慢，可单独调整
+	pub startup_timeout_secs: Option<u64>,
+	/// 以试玩模式启动
+	pub demo: Option<bool>,
 	pub jvm_args: Option<String>,
 	pub game_args: Option<String>,
+	/// 启动后直接进入的世界或服务器
+	pub quick_play: Option<QuickPlay>,
 }
 
 impl GameConfig {
@@ -22,11 +30,18 @@ impl GameConfig {
 			max_memory_mb: self.max_memory_mb.unwrap_or(defaults.max_memory_mb),
 			window_width: self.window_width.unwrap_or(defaults.window_width),
 			window_height: self.window_height.unwrap_or(defaults.window_height),
+			fullscreen: self.fullscreen.unwrap_or(defaults.fullscreen),
+			demo: self.demo.unwrap_or(false),
+			startup_timeout_secs: self
+				.startup_timeout_secs
+				.unwrap_or(defaults.startup_timeout_secs),
+			ready_patterns: defaults.ready_patterns.clone(),
 			jvm_args: self
 				.jvm_args
 				.clone()
 				.unwrap_or_else(|| defaults.jvm_args.clone()),
 			game_args: self.game_args.clone().unwrap_or_default(),
+			quick_play: self.quick_play.clone(),
 		}
 	}
 }
@@ -37,6 +52,11 @@ pub struct ResolvedGameConfig {
 	pub max_memory_mb: u32,
 	pub window_width: u32,
 	pub window_height: u32,
+	pub fullscreen: bool,
+	pub demo: bool,
+	pub startup_timeout_secs: u64,
+	pub ready_patterns: Vec<String>,
 	pub jvm_args: String,
 	pub game_args: String,
+	pub quick_play: Option<QuickPlay>,
 }
@@ -1,3 +1,5 @@
+use crate::game::maven::DEFAULT_REPOSITORIES;
+use crate::net::mirror::DownloadSource;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -10,6 +12,13 @@ pub struct LauncherConfig {
 	pub window_width: u32,
 	pub window_height: u32,
 	pub download_concurrency: u8,
+	pub download_source: DownloadSource,
+	/// 当前下载源失败时自动尝试其他内置源
+	pub download_fallback: bool,
+	/// 库只有 Maven 坐标时依次尝试的仓库
+	pub maven_repositories: Vec<String>,
+	/// 微软登录使用的 Azure 应用客户端 ID
+	pub microsoft_client_id: String,
 	pub game: GameDefaults,
 }
 
@@ -20,7 +29,12 @@ pub struct GameDefaults {
 	pub max_memory_mb: u32,
 	pub window_width: u32,
 	pub window_height: u32,
+	pub fullscreen: bool,
 	pub jvm_args: String,
+	/// 等待游戏窗口出现的最长时间，超时后不再等待但游戏继续运行
+	pub startup_timeout_secs: u64,
+	/// 追加的就绪日志正则，匹配到任意一条即视为窗口已创建
+	pub ready_patterns: Vec<String>,
 }
 
 impl Default for LauncherConfig {
@@ -32,6 +46,10 @@ impl Default for LauncherConfig {
 			window_width: 900,
 			window_height: 550,
 			download_concurrency: 5,
+			download_source: DownloadSource::default(),
+			download_fallback: true,
+			maven_repositories: DEFAULT_REPOSITORIES.iter().map(|r| r.to_string()).collect(),
+			microsoft_client_id: String::new(),
 			game: GameDefaults::default(),
 		}
 	}
@@ -44,7 +62,10 @@ impl Default for GameDefaults {
 			max_memory_mb: 4096,
 			window_width: 854,
 			window_height: 480,
+			fullscreen: false,
 			jvm_args: String::new(),
+			startup_timeout_secs: 120,
+			ready_patterns: Vec::new(),
 		}
 	}
 }
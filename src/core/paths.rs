@@ -7,6 +7,12 @@ pub fn config_dir() -> Result<PathBuf> {
 		.map(|p| p.join("hako"))
 }
 
+pub fn data_dir() -> Result<PathBuf> {
+	dirs::data_dir()
+		.context("Failed to get data directory")
+		.map(|p| p.join("hako"))
+}
+
 pub fn cache_dir() -> Result<PathBuf> {
 	let cache = std::env::temp_dir().join("hako_cache");
 	if !cache.exists() {
@@ -1,6 +1,8 @@
 use crate::account::AccountManager;
 use crate::config::manager::ConfigManager;
 use crate::game::instance::GameInstance;
+use crate::game::java::{self, JavaInstallation};
+use crate::game::process::GameRegistry;
 use crate::task::game::download::{DownloadProgressState, ProgressRef};
 use crate::task::handle::TaskId;
 use crate::task::manager::TaskManager;
@@ -15,8 +17,10 @@ pub struct AppState {
 	pub accounts: AccountManager,
 	pub task_manager: Arc<TaskManager>,
 	pub instances: RwLock<Vec<GameInstance>>,
+	pub java_installations: RwLock<Vec<JavaInstallation>>,
 	pub current_instance: Mutex<Option<usize>>,
 	pub task_progress: Mutex<HashMap<TaskId, ProgressRef>>,
+	pub games: GameRegistry,
 }
 
 impl AppState {
@@ -25,7 +29,10 @@ impl AppState {
 			let state = Self::create();
 			state.scan_instances();
 			state
-		})
+		});
+		// 探测 Java 需要逐个运行，放到后台线程
+		std::thread::spawn(|| Self::get().scan_java());
+		Self::get()
 	}
 
 	pub fn get() -> &'static Self {
@@ -35,11 +42,16 @@ impl AppState {
 	fn create() -> Self {
 		Self {
 			config: ConfigManager::default(),
-			accounts: AccountManager::new(),
+			accounts: AccountManager::open_default().unwrap_or_else(|e| {
+				tracing::warn!("Failed to load accounts: {:#}", e);
+				AccountManager::new()
+			}),
 			task_manager: Arc::new(TaskManager::new()),
 			instances: RwLock::new(Vec::new()),
+			java_installations: RwLock::new(Vec::new()),
 			current_instance: Mutex::new(None),
 			task_progress: Mutex::new(HashMap::new()),
+			games: GameRegistry::new(),
 		}
 	}
 
@@ -58,6 +70,16 @@ impl AppState {
 		}
 	}
 
+	pub fn scan_java(&self) {
+		let found = java::discover_java();
+		tracing::info!("Found {} java installations", found.len());
+		*self.java_installations.write().unwrap() = found;
+	}
+
+	pub fn java_installations(&self) -> Vec<JavaInstallation> {
+		self.java_installations.read().unwrap().clone()
+	}
+
 	pub fn set_cluster_path(&self, path: PathBuf) {
 		let _ = self.config.update(|c| c.cluster_path = Some(path));
 		self.scan_instances();
@@ -74,10 +96,12 @@ impl AppState {
 
 	pub fn register_progress(&self, id: TaskId) -> ProgressRef {
 		let progress = Arc::new(tokio::sync::RwLock::new(DownloadProgressState::default()));
-		self.task_progress
-			.lock()
-			.unwrap()
-			.insert(id, Arc::clone(&progress));
+		self.track_progress(id, Arc::clone(&progress));
 		progress
 	}
+
+	/// 登记提交前就已交给任务的进度
+	pub fn track_progress(&self, id: TaskId, progress: ProgressRef) {
+		self.task_progress.lock().unwrap().insert(id, progress);
+	}
 }
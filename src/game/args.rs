@@ -1,6 +1,10 @@
+use crate::account::Account;
+use crate::account::yggdrasil::AuthlibInjector;
+use crate::game::log4j::logging_jvm_arg;
 use crate::game::profile::{ArgValueInner, ArgumentValue, Rule, RuleOs, VersionProfile};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
@@ -8,40 +12,123 @@ static TEMPLATE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\{([^}]+)\}").unwr
 
 #[derive(Debug, Clone, Default)]
 pub struct Features {
-	#[allow(dead_code)]
 	pub is_demo_user: bool,
-	#[allow(dead_code)]
 	pub has_custom_resolution: bool,
-	#[allow(dead_code)]
 	pub has_quick_plays_support: bool,
-	#[allow(dead_code)]
 	pub is_quick_play_singleplayer: bool,
-	#[allow(dead_code)]
 	pub is_quick_play_multiplayer: bool,
-	#[allow(dead_code)]
 	pub is_quick_play_realms: bool,
 }
 
+/// 启动后直接进入的世界、服务器或 Realm
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "target", rename_all = "snake_case")]
+pub enum QuickPlay {
+	Singleplayer(String),
+	Multiplayer(String),
+	Realms(String),
+}
+
+/// 来自实例配置、写入启动参数的选项
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+	pub width: u32,
+	pub height: u32,
+	pub fullscreen: bool,
+	pub demo: bool,
+	pub quick_play: Option<QuickPlay>,
+}
+
+impl LaunchOptions {
+	/// 参数规则中使用的特性开关
+	pub fn features(&self) -> Features {
+		let quick_play = self.quick_play.as_ref();
+		Features {
+			is_demo_user: self.demo,
+			has_custom_resolution: !self.fullscreen,
+			has_quick_plays_support: quick_play.is_some(),
+			is_quick_play_singleplayer: matches!(quick_play, Some(QuickPlay::Singleplayer(_))),
+			is_quick_play_multiplayer: matches!(quick_play, Some(QuickPlay::Multiplayer(_))),
+			is_quick_play_realms: matches!(quick_play, Some(QuickPlay::Realms(_))),
+		}
+	}
+}
+
+impl Default for LaunchOptions {
+	fn default() -> Self {
+		Self {
+			width: 854,
+			height: 480,
+			fullscreen: false,
+			demo: false,
+			quick_play: None,
+		}
+	}
+}
+
+/// 展开后仍未被替换的 `${...}`，原样留在参数中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedPlaceholder {
+	pub placeholder: String,
+	pub argument: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExpandedArgs {
+	pub args: Vec<String>,
+	pub unresolved: Vec<UnresolvedPlaceholder>,
+}
+
+/// 写入启动参数的身份信息，未登录或离线账户使用占位值
+#[derive(Debug, Clone)]
+pub struct LaunchAuth {
+	pub username: String,
+	pub uuid: String,
+	pub access_token: String,
+	pub user_type: &'static str,
+	pub xuid: String,
+	pub client_id: String,
+	/// 第三方认证账户需要注入的 authlib-injector
+	pub authlib: Option<AuthlibInjector>,
+}
+
+impl LaunchAuth {
+	pub fn offline(username: &str) -> Self {
+		Self::from_account(&Account::offline(username))
+	}
+
+	pub fn from_account(account: &Account) -> Self {
+		Self {
+			username: account.username().to_string(),
+			uuid: account.uuid().simple().to_string(),
+			access_token: account.access_token().unwrap_or("0").to_string(),
+			user_type: account.user_type(),
+			xuid: account.xuid().unwrap_or_else(|| "0".into()),
+			client_id: account.client_id().unwrap_or("0").to_string(),
+			authlib: None,
+		}
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn collect_jvm_args(
 	profile: &VersionProfile,
 	game_dir: &Path,
 	version: &str,
 	classpath: &str,
 	assets_index: &str,
-	username: &str,
-	uuid: &str,
+	auth: &LaunchAuth,
 	natives_dir: &Path,
+	options: &LaunchOptions,
 	features: &Features,
-) -> Vec<String> {
-	let mut replacements = build_replacements(
-		game_dir,
-		version,
-		assets_index,
-		username,
-		uuid,
-		Some(natives_dir),
-		Some(classpath),
+) -> ExpandedArgs {
+	let mut replacements =
+		build_replacements(game_dir, version, profile, assets_index, auth, options);
+	replacements.insert(
+		"${natives_directory}".to_string(),
+		natives_dir.to_string_lossy().into_owned(),
 	);
+	replacements.insert("${classpath}".to_string(), classpath.to_string());
 	replacements.insert("${launcher_name}".to_string(), "Hako".to_string());
 	replacements.insert(
 		"${launcher_version}".to_string(),
@@ -55,74 +142,117 @@ pub fn collect_jvm_args(
 		"${classpath_separator}".to_string(),
 		if cfg!(windows) { ";" } else { ":" }.to_string(),
 	);
-	collect_args(profile, true, &replacements, features)
+
+	let mut out = ExpandedArgs {
+		args: auth
+			.authlib
+			.as_ref()
+			.map(AuthlibInjector::jvm_args)
+			.unwrap_or_default(),
+		..Default::default()
+	};
+	if let Some(args) = &profile.arguments {
+		let expanded = expand_args(&args.jvm, &replacements, features, &mut out.unresolved);
+		out.args.extend(expanded);
+	}
+	out.args.extend(logging_jvm_arg(game_dir, profile));
+	out
 }
 
 pub fn collect_game_args(
 	game_dir: &Path,
 	version: &str,
 	profile: &VersionProfile,
-	username: &str,
-	uuid: &str,
+	auth: &LaunchAuth,
 	assets_index: &str,
+	options: &LaunchOptions,
 	features: &Features,
-) -> Vec<String> {
+) -> ExpandedArgs {
 	let mut replacements =
-		build_replacements(game_dir, version, assets_index, username, uuid, None, None);
+		build_replacements(game_dir, version, profile, assets_index, auth, options);
 	replacements.insert("${version}".to_string(), version.to_string());
 	replacements.insert("${assetIndex}".to_string(), assets_index.to_string());
-	replacements.insert("${accessToken}".to_string(), "0".to_string());
-	replacements.insert("${userType}".to_string(), "mojang".to_string());
+	replacements.insert("${accessToken}".to_string(), auth.access_token.clone());
+	replacements.insert("${userType}".to_string(), auth.user_type.to_string());
 
-	if profile.arguments.is_some() {
-		collect_args(profile, false, &replacements, features)
+	let mut out = ExpandedArgs::default();
+	if let Some(args) = &profile.arguments {
+		out.args = expand_args(&args.game, &replacements, features, &mut out.unresolved);
 	} else if let Some(legacy) = &profile.minecraft_arguments {
 		let assets_dir = game_dir.join("assets");
-		let mut out: Vec<String> = legacy
+		out.args = legacy
 			.split_whitespace()
-			.flat_map(|s| replace_and_split(s, &replacements))
+			.flat_map(|s| replace_and_split(s, &replacements, &mut out.unresolved))
 			.collect();
-		out.extend([
-			"--username".into(),
-			username.into(),
-			"--uuid".into(),
-			uuid.into(),
-			"--version".into(),
-			version.into(),
-			"--gameDir".into(),
-			game_dir.to_string_lossy().into_owned(),
-			"--assetsDir".into(),
-			assets_dir.to_string_lossy().into_owned(),
-			"--assetIndex".into(),
-			assets_index.into(),
-			"--accessToken".into(),
-			"0".into(),
-			"--userType".into(),
-			"mojang".into(),
-		]);
-		out
-	} else {
-		Vec::new()
+		let defaults = [
+			("--username", auth.username.clone()),
+			("--uuid", auth.uuid.clone()),
+			("--version", version.into()),
+			("--gameDir", game_dir.to_string_lossy().into_owned()),
+			("--assetsDir", assets_dir.to_string_lossy().into_owned()),
+			("--assetIndex", assets_index.into()),
+			("--accessToken", auth.access_token.clone()),
+			("--userType", auth.user_type.into()),
+		];
+		for (option, value) in defaults {
+			append_missing(&mut out.args, option, Some(value));
+		}
+		append_legacy_feature_args(&mut out.args, options, features);
 	}
+	// 新旧版本的参数模板都没有全屏开关
+	if options.fullscreen {
+		out.args.push("--fullscreen".into());
+	}
+	out
+}
+
+/// 旧版 minecraftArguments 没有按特性生效的参数，手动补上
+fn append_legacy_feature_args(
+	args: &mut Vec<String>,
+	options: &LaunchOptions,
+	features: &Features,
+) {
+	if features.is_demo_user {
+		append_missing(args, "--demo", None);
+	}
+	if features.has_custom_resolution {
+		append_missing(args, "--width", Some(options.width.to_string()));
+		append_missing(args, "--height", Some(options.height.to_string()));
+	}
+}
+
+/// 模板中已有的选项不再追加，joptsimple 不接受重复的单值选项
+fn append_missing(args: &mut Vec<String>, option: &str, value: Option<String>) {
+	if args.iter().any(|a| a == option) {
+		return;
+	}
+	args.push(option.to_string());
+	args.extend(value);
 }
 
 fn build_replacements(
 	game_dir: &Path,
 	version: &str,
+	profile: &VersionProfile,
 	assets_index: &str,
-	username: &str,
-	uuid: &str,
-	natives_dir: Option<&Path>,
-	classpath: Option<&str>,
+	auth: &LaunchAuth,
+	options: &LaunchOptions,
 ) -> HashMap<String, String> {
 	let assets_dir = game_dir.join("assets");
 	let mut replacements = HashMap::new();
 
 	replacements.insert("${version_name}".to_string(), version.to_string());
-	replacements.insert("${username}".to_string(), username.to_string());
-	replacements.insert("${auth_player_name}".to_string(), username.to_string());
-	replacements.insert("${uuid}".to_string(), uuid.to_string());
-	replacements.insert("${auth_uuid}".to_string(), uuid.to_string());
+	replacements.insert(
+		"${version_type}".to_string(),
+		profile
+			.version_type
+			.clone()
+			.unwrap_or_else(|| "release".to_string()),
+	);
+	replacements.insert("${username}".to_string(), auth.username.clone());
+	replacements.insert("${auth_player_name}".to_string(), auth.username.clone());
+	replacements.insert("${uuid}".to_string(), auth.uuid.clone());
+	replacements.insert("${auth_uuid}".to_string(), auth.uuid.clone());
 	replacements.insert(
 		"${gameDir}".to_string(),
 		game_dir.to_string_lossy().into_owned(),
@@ -145,35 +275,56 @@ fn build_replacements(
 	);
 	replacements.insert("${assetIndex}".to_string(), assets_index.to_string());
 	replacements.insert("${assets_index_name}".to_string(), assets_index.to_string());
-	replacements.insert("${auth_access_token}".to_string(), "0".to_string());
-	replacements.insert("${auth_session}".to_string(), "0".to_string());
-	replacements.insert("${user_type}".to_string(), "mojang".to_string());
-
-	if let Some(natives_dir) = natives_dir {
-		replacements.insert(
-			"${natives_directory}".to_string(),
-			natives_dir.to_string_lossy().into_owned(),
-		);
-	}
-	if let Some(classpath) = classpath {
-		replacements.insert("${classpath}".to_string(), classpath.to_string());
-	}
+	replacements.insert(
+		"${auth_access_token}".to_string(),
+		auth.access_token.clone(),
+	);
+	replacements.insert("${auth_session}".to_string(), auth.access_token.clone());
+	replacements.insert("${user_type}".to_string(), auth.user_type.to_string());
+	replacements.insert("${auth_xuid}".to_string(), auth.xuid.clone());
+	replacements.insert("${clientid}".to_string(), auth.client_id.clone());
+	// 1.7.2 到 1.12.2 用于 Twitch 等属性，游戏要求是合法的 JSON
+	replacements.insert("${user_properties}".to_string(), "{}".to_string());
 
-	replacements
-}
+	let jar = profile.jar.as_deref().unwrap_or(version);
+	replacements.insert(
+		"${primary_jar}".to_string(),
+		game_dir
+			.join("versions")
+			.join(jar)
+			.join(format!("{jar}.jar"))
+			.to_string_lossy()
+			.into_owned(),
+	);
+	replacements.insert("${resolution_width}".to_string(), options.width.to_string());
+	replacements.insert(
+		"${resolution_height}".to_string(),
+		options.height.to_string(),
+	);
 
-fn collect_args(
-	profile: &VersionProfile,
-	is_jvm: bool,
-	replacements: &HashMap<String, String>,
-	features: &Features,
-) -> Vec<String> {
-	if let Some(args) = &profile.arguments {
-		let values = if is_jvm { &args.jvm } else { &args.game };
-		expand_args(values, replacements, features)
-	} else {
-		Vec::new()
+	// 游戏把快速游玩的结果写入该文件
+	replacements.insert(
+		"${quickPlayPath}".to_string(),
+		game_dir
+			.join("quickPlay")
+			.join("log.json")
+			.to_string_lossy()
+			.into_owned(),
+	);
+	match &options.quick_play {
+		Some(QuickPlay::Singleplayer(world)) => {
+			replacements.insert("${quickPlaySingleplayer}".to_string(), world.clone());
+		}
+		Some(QuickPlay::Multiplayer(server)) => {
+			replacements.insert("${quickPlayMultiplayer}".to_string(), server.clone());
+		}
+		Some(QuickPlay::Realms(realm)) => {
+			replacements.insert("${quickPlayRealms}".to_string(), realm.clone());
+		}
+		None => {}
 	}
+
+	replacements
 }
 
 pub fn current_os_key() -> &'static str {
@@ -194,13 +345,21 @@ pub fn current_arch() -> &'static str {
 	}
 }
 
-fn replace_and_split(s: &str, replacements: &HashMap<String, String>) -> Vec<String> {
+fn replace_and_split(
+	s: &str,
+	replacements: &HashMap<String, String>,
+	unresolved: &mut Vec<UnresolvedPlaceholder>,
+) -> Vec<String> {
 	TEMPLATE_RE
 		.replace_all(s, |caps: &regex::Captures| {
-			replacements
-				.get(caps.get(0).unwrap().as_str())
-				.cloned()
-				.unwrap_or_else(|| caps.get(0).unwrap().as_str().to_string())
+			let placeholder = caps.get(0).unwrap().as_str();
+			replacements.get(placeholder).cloned().unwrap_or_else(|| {
+				unresolved.push(UnresolvedPlaceholder {
+					placeholder: placeholder.to_string(),
+					argument: s.to_string(),
+				});
+				placeholder.to_string()
+			})
 		})
 		.split_whitespace()
 		.map(String::from)
@@ -211,6 +370,7 @@ pub fn expand_args(
 	values: &[ArgumentValue],
 	replacements: &HashMap<String, String>,
 	features: &Features,
+	unresolved: &mut Vec<UnresolvedPlaceholder>,
 ) -> Vec<String> {
 	let os_key = current_os_key();
 	let arch = current_arch();
@@ -219,17 +379,17 @@ pub fn expand_args(
 	for v in values {
 		match v {
 			ArgumentValue::Plain(s) => {
-				out.extend(replace_and_split(s, replacements));
+				out.extend(replace_and_split(s, replacements, unresolved));
 			}
 			ArgumentValue::Obj(o) => {
 				if rule_allows(o.rules.as_ref(), os_key, arch, features) {
 					match &o.value {
 						ArgValueInner::One(s) => {
-							out.extend(replace_and_split(s, replacements));
+							out.extend(replace_and_split(s, replacements, unresolved));
 						}
 						ArgValueInner::Many(list) => {
 							for s in list {
-								out.extend(replace_and_split(s, replacements));
+								out.extend(replace_and_split(s, replacements, unresolved));
 							}
 						}
 					}
@@ -347,3 +507,122 @@ fn get_os_version() -> Option<String> {
 		None
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_unresolved_placeholders_are_reported() {
+		let profile: VersionProfile = serde_json::from_str(
+			r#"{
+				"type": "snapshot",
+				"arguments": {
+					"game": [
+						"--version", "${version_name}", "--versionType", "${version_type}",
+						"--quickPlayPath", "${quickPlayPath}",
+						{ "rules": [{ "action": "allow", "features": { "has_custom_resolution": true } }],
+						  "value": ["--width", "${resolution_width}"] },
+						"--mystery", "${not_a_placeholder}"
+					]
+				}
+			}"#,
+		)
+		.unwrap();
+		let features = Features {
+			has_custom_resolution: true,
+			..Default::default()
+		};
+		let options = LaunchOptions {
+			width: 1280,
+			..Default::default()
+		};
+		let out = collect_game_args(
+			Path::new("game"),
+			"24w10a",
+			&profile,
+			&LaunchAuth::offline("Steve"),
+			"24w10a",
+			&options,
+			&features,
+		);
+
+		assert_eq!(out.args[3], "snapshot");
+		assert!(out.args[5].ends_with("log.json"));
+		assert_eq!(out.args[6..8], ["--width", "1280"]);
+		assert_eq!(out.args[9], "${not_a_placeholder}");
+		assert_eq!(
+			out.unresolved,
+			[UnresolvedPlaceholder {
+				placeholder: "${not_a_placeholder}".into(),
+				argument: "${not_a_placeholder}".into(),
+			}]
+		);
+
+		let legacy: VersionProfile = serde_json::from_str(
+			r#"{ "minecraftArguments": "--username ${auth_player_name} --session ${auth_session} --userProperties ${user_properties} --userType ${user_type}" }"#,
+		)
+		.unwrap();
+		let out = collect_game_args(
+			Path::new("game"),
+			"1.8.9",
+			&legacy,
+			&LaunchAuth::offline("Steve"),
+			"1.8",
+			&options,
+			&features,
+		);
+		assert!(out.unresolved.is_empty());
+		assert_eq!(out.args[4..6], ["--userProperties", "{}"]);
+	}
+
+	#[test]
+	fn test_legacy_feature_args() {
+		let profile: VersionProfile = serde_json::from_str(
+			r#"{ "minecraftArguments": "--username ${auth_player_name} --version ${version_name}" }"#,
+		)
+		.unwrap();
+		let auth = LaunchAuth::offline("Steve");
+		let collect = |options: &LaunchOptions| {
+			collect_game_args(
+				Path::new("game"),
+				"1.7.10",
+				&profile,
+				&auth,
+				"1.7.10",
+				options,
+				&options.features(),
+			)
+			.args
+		};
+
+		let windowed = collect(&LaunchOptions {
+			width: 1280,
+			height: 720,
+			demo: true,
+			..Default::default()
+		});
+		assert!(windowed.ends_with(&[
+			"--demo".to_string(),
+			"--width".into(),
+			"1280".into(),
+			"--height".into(),
+			"720".into(),
+		]));
+
+		let fullscreen = collect(&LaunchOptions {
+			fullscreen: true,
+			..Default::default()
+		});
+		assert!(!fullscreen.contains(&"--width".to_string()));
+		assert_eq!(fullscreen.last().map(String::as_str), Some("--fullscreen"));
+
+		for args in [&windowed, &fullscreen] {
+			let options: Vec<_> = args.iter().filter(|a| a.starts_with("--")).collect();
+			let unique: std::collections::HashSet<_> = options.iter().collect();
+			assert_eq!(options.len(), unique.len(), "duplicate options in {args:?}");
+			assert_eq!(args[..4], ["--username", "Steve", "--version", "1.7.10"]);
+			assert!(args.contains(&"--accessToken".to_string()));
+		}
+	}
+}
@@ -1,4 +1,5 @@
 use crate::game::args::{Features, current_arch, current_os_key, rule_allows};
+use crate::game::maven::MavenCoord;
 use crate::game::profile::{Library, VersionProfile};
 use anyhow::Result;
 use std::collections::HashSet;
@@ -10,10 +11,11 @@ pub fn build_classpath(
 	profile: &VersionProfile,
 	features: &Features,
 ) -> Result<String> {
+	let jar = profile.jar.as_deref().unwrap_or(version);
 	let version_jar = game_dir
 		.join("versions")
-		.join(version)
-		.join(format!("{version}.jar"));
+		.join(jar)
+		.join(format!("{jar}.jar"));
 	if !version_jar.exists() {
 		return Err(anyhow::anyhow!(
 			"Version jar missing: {}",
@@ -31,7 +33,7 @@ pub fn build_classpath(
 			continue;
 		}
 
-		if let Some(p) = library_path(game_dir, lib, os_key, arch)? {
+		if let Some(p) = library_path(game_dir, lib, os_key)? {
 			if !p.exists() {
 				return Err(anyhow::anyhow!("Library missing: {}", p.display()));
 			}
@@ -50,73 +52,47 @@ pub fn build_classpath(
 		.join(sep))
 }
 
-fn maven_path(game_dir: &Path, coord: &str, classifier: Option<&str>) -> Result<PathBuf> {
-	let parts: Vec<&str> = coord.split(':').collect();
-	if parts.len() < 3 {
-		return Err(anyhow::anyhow!("Invalid maven coord: {coord}"));
-	}
-	let group = parts[0].replace('.', "/");
-	let artifact = parts[1];
-	let version = parts[2];
-	let classifier = classifier.or_else(|| parts.get(3).copied());
-
-	let file_name = if let Some(c) = classifier {
-		format!("{artifact}-{version}-{c}.jar")
-	} else {
-		format!("{artifact}-{version}.jar")
-	};
-
-	Ok(game_dir
-		.join("libraries")
-		.join(group)
-		.join(artifact)
-		.join(version)
-		.join(file_name))
-}
-
 pub fn library_applicable(lib: &Library, os_key: &str, arch: &str, features: &Features) -> bool {
 	rule_allows(lib.rules.as_ref(), os_key, arch, features)
 }
 
-pub fn library_path(
-	game_dir: &Path,
-	lib: &Library,
-	os_key: &str,
-	arch: &str,
-) -> Result<Option<PathBuf>> {
-	if let Some(natives) = &lib.natives {
-		if let Some(native_cls) = natives.get(os_key) {
-			let classifier = native_cls.replace("${arch}", arch);
-			if let Some(dl) = &lib.downloads {
-				if let Some(classifiers) = &dl.classifiers {
-					let key = format!("natives-{os_key}");
-					if let Some(artifact) = classifiers.get(&key) {
-						if let Some(path) = &artifact.path {
-							return Ok(Some(
-								game_dir
-									.join("libraries")
-									.join(path.replace('/', std::path::MAIN_SEPARATOR_STR)),
-							));
-						}
-					}
-				}
-			}
-			return Ok(Some(maven_path(game_dir, &lib.name, Some(&classifier))?));
-		}
-		return Ok(None);
+pub fn library_path(game_dir: &Path, lib: &Library, os_key: &str) -> Result<Option<PathBuf>> {
+	let libraries_dir = game_dir.join("libraries");
+	let classifier = match &lib.natives {
+		Some(_) => match native_classifier(lib, os_key) {
+			Some(c) => Some(c),
+			None => return Ok(None),
+		},
+		None => None,
+	};
+
+	let artifact = match (&classifier, &lib.downloads) {
+		(Some(c), Some(dl)) => dl.classifiers.as_ref().and_then(|m| m.get(c)),
+		(None, Some(dl)) => dl.artifact.as_ref(),
+		_ => None,
+	};
+	if let Some(path) = artifact.and_then(|a| a.path.as_ref()) {
+		return Ok(Some(
+			libraries_dir.join(path.replace('/', std::path::MAIN_SEPARATOR_STR)),
+		));
 	}
 
-	if let Some(dl) = &lib.downloads {
-		if let Some(artifact) = &dl.artifact {
-			if let Some(path) = &artifact.path {
-				return Ok(Some(
-					game_dir
-						.join("libraries")
-						.join(path.replace('/', std::path::MAIN_SEPARATOR_STR)),
-				));
-			}
-		}
+	let mut coord = MavenCoord::parse(&lib.name)?;
+	if let Some(c) = classifier {
+		coord = coord.with_classifier(c);
 	}
+	Ok(Some(coord.local_path(&libraries_dir)))
+}
 
-	Ok(Some(maven_path(game_dir, &lib.name, None)?))
+/// natives 中当前系统对应的 classifier，`${arch}` 为 32 或 64
+pub fn native_classifier(lib: &Library, os_key: &str) -> Option<String> {
+	let bits = if cfg!(target_pointer_width = "64") {
+		"64"
+	} else {
+		"32"
+	};
+	lib.natives
+		.as_ref()?
+		.get(os_key)
+		.map(|c| c.replace("${arch}", bits))
 }
@@ -0,0 +1,417 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// 崩溃报告中保留的游戏输出行数
+const LOG_TAIL: usize = 200;
+/// 单个文件读取上限，hs_err 日志可能很大
+const MAX_READ: u64 = 4 * 1024 * 1024;
+const MAX_DETAIL_CHARS: usize = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashKind {
+	WrongJava,
+	MissingDependency,
+	Graphics,
+	OutOfMemory,
+	Mixin,
+	DuplicateMods,
+}
+
+impl CrashKind {
+	pub fn title(self) -> &'static str {
+		match self {
+			CrashKind::WrongJava => "Java 版本不匹配",
+			CrashKind::MissingDependency => "缺少模组依赖",
+			CrashKind::Graphics => "显卡驱动或 OpenGL 错误",
+			CrashKind::OutOfMemory => "内存不足",
+			CrashKind::Mixin => "Mixin 注入失败",
+			CrashKind::DuplicateMods => "存在重复的模组",
+		}
+	}
+
+	pub fn advice(self) -> &'static str {
+		match self {
+			CrashKind::WrongJava => {
+				"在实例设置中选择游戏要求的 Java 版本，或清空 Java 路径让启动器自动选择"
+			}
+			CrashKind::MissingDependency => "安装缺少的前置模组，或移除依赖它的模组",
+			CrashKind::Graphics => "更新显卡驱动；笔记本请确认游戏使用独立显卡",
+			CrashKind::OutOfMemory => "调高最大内存，或减少模组与光影",
+			CrashKind::Mixin => "某个模组与当前游戏或其他模组不兼容，尝试更新或移除相关模组",
+			CrashKind::DuplicateMods => "mods 文件夹中同一模组有多个版本，只保留一个",
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnosis {
+	pub kind: CrashKind,
+	/// 命中的原文或从中提取的信息
+	pub detail: String,
+}
+
+struct Signature {
+	kind: CrashKind,
+	re: Regex,
+	/// 根据匹配结果生成说明，为空时使用整行
+	detail: Option<fn(&regex::Captures) -> String>,
+}
+
+fn signature(kind: CrashKind, pattern: &str) -> Signature {
+	Signature {
+		kind,
+		re: Regex::new(pattern).unwrap(),
+		detail: None,
+	}
+}
+
+static SIGNATURES: Lazy<Vec<Signature>> = Lazy::new(|| {
+	vec![
+		Signature {
+			kind: CrashKind::WrongJava,
+			re: Regex::new(
+				r"compiled by a more recent version of the Java Runtime \(class file version (\d+)",
+			)
+			.unwrap(),
+			detail: Some(|c| match c[1].parse::<u32>() {
+				Ok(v) if v > 44 => format!("需要 Java {} 或更高版本", v - 44),
+				_ => c[0].to_string(),
+			}),
+		},
+		signature(
+			CrashKind::WrongJava,
+			r"Unsupported class file major version \d+|UnsupportedClassVersionError|AppClassLoader cannot be cast to class java\.net\.URLClassLoader",
+		),
+		Signature {
+			kind: CrashKind::MissingDependency,
+			re: Regex::new(
+				r"requires (?:any version|version \S+) of '?([^',!]+?)'?,? which is missing",
+			)
+			.unwrap(),
+			detail: Some(|c| format!("缺少 {}", c[1].trim())),
+		},
+		Signature {
+			kind: CrashKind::MissingDependency,
+			re: Regex::new(r"Mod ID: '([^']+)', Requested by: '([^']+)'").unwrap(),
+			detail: Some(|c| format!("{} 需要 {}", &c[2], &c[1])),
+		},
+		signature(
+			CrashKind::Graphics,
+			r"Pixel format not accelerated|GLFW error 65542|WGL: The driver does not appear to support OpenGL|Couldn't set pixel format|No OpenGL context found|(?:atio6axx|atioglxx|ig\d+icd\d*|nvoglv\d+)\.dll",
+		),
+		signature(
+			CrashKind::OutOfMemory,
+			r"java\.lang\.OutOfMemoryError|Could not reserve enough space for|There is insufficient memory for the Java Runtime",
+		),
+		signature(
+			CrashKind::Mixin,
+			r"MixinApplyError|Mixin apply(?: for mod \S+)? failed|InvalidInjectionException|MixinTransformerError|Mixin prepare failed",
+		),
+		signature(
+			CrashKind::DuplicateMods,
+			r"DuplicateModsFoundException|Found duplicate mods|Duplicate mods found|duplicate mod ID|Mod ID '[^']+' has multiple",
+		),
+	]
+});
+
+/// 游戏异常退出后收集的信息
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+	pub instance: String,
+	pub exit_code: Option<i32>,
+	pub log_tail: Vec<String>,
+	/// crash-reports 中本次启动后生成的最新报告
+	pub crash_report: Option<PathBuf>,
+	/// JVM 崩溃时写出的 hs_err_pid*.log
+	pub jvm_error_log: Option<PathBuf>,
+	pub diagnoses: Vec<Diagnosis>,
+}
+
+impl CrashReport {
+	/// 一行概述，用于任务错误信息
+	pub fn headline(&self) -> String {
+		match self.diagnoses.first() {
+			Some(d) => format!("{}：{}", d.kind.title(), d.detail),
+			None => match self.exit_code {
+				Some(code) => format!("游戏异常退出，退出码 {code}"),
+				None => "游戏异常退出".to_string(),
+			},
+		}
+	}
+
+	pub fn summary(&self) -> String {
+		let mut out = format!("实例: {}\n退出码: {:?}\n", self.instance, self.exit_code);
+		if self.diagnoses.is_empty() {
+			out.push_str("未识别出已知的崩溃原因\n");
+		}
+		for d in &self.diagnoses {
+			out.push_str(&format!(
+				"\n[{}]\n{}\n建议: {}\n",
+				d.kind.title(),
+				d.detail,
+				d.kind.advice()
+			));
+		}
+		out
+	}
+}
+
+/// 分析游戏日志以及本次启动后生成的崩溃报告和 JVM 错误日志
+pub fn analyze(
+	game_dir: &Path,
+	instance: &str,
+	exit_code: Option<i32>,
+	logs: &[String],
+	since: SystemTime,
+) -> CrashReport {
+	let crash_report = newest_file(&game_dir.join("crash-reports"), since, |name| {
+		name.starts_with("crash-") && name.ends_with(".txt")
+	});
+	let jvm_error_log = newest_file(game_dir, since, |name| {
+		name.starts_with("hs_err_pid") && name.ends_with(".log")
+	});
+
+	let mut diagnoses = Vec::new();
+	let files = [&crash_report, &jvm_error_log];
+	let texts = files.into_iter().flatten().filter_map(|p| read_limited(p));
+	for text in logs.iter().cloned().chain(texts) {
+		for line in text.lines() {
+			match_line(line, &mut diagnoses);
+		}
+	}
+
+	CrashReport {
+		instance: instance.to_string(),
+		exit_code,
+		log_tail: logs[logs.len().saturating_sub(LOG_TAIL)..].to_vec(),
+		crash_report,
+		jvm_error_log,
+		diagnoses,
+	}
+}
+
+/// 每行只取第一个命中的特征
+fn match_line(line: &str, diagnoses: &mut Vec<Diagnosis>) {
+	let Some((sig, caps)) = SIGNATURES
+		.iter()
+		.find_map(|sig| Some((sig, sig.re.captures(line)?)))
+	else {
+		return;
+	};
+	let detail = match sig.detail {
+		Some(f) => f(&caps),
+		None => line.trim().chars().take(MAX_DETAIL_CHARS).collect(),
+	};
+	let diagnosis = Diagnosis {
+		kind: sig.kind,
+		detail,
+	};
+	if !diagnoses.contains(&diagnosis) {
+		diagnoses.push(diagnosis);
+	}
+}
+
+fn newest_file(dir: &Path, since: SystemTime, filter: impl Fn(&str) -> bool) -> Option<PathBuf> {
+	fs::read_dir(dir)
+		.ok()?
+		.flatten()
+		.filter(|e| e.file_name().to_str().is_some_and(&filter))
+		.filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+		.filter(|(modified, _)| *modified >= since)
+		.max_by_key(|(modified, _)| *modified)
+		.map(|(_, path)| path)
+}
+
+fn read_limited(path: &Path) -> Option<String> {
+	use std::io::Read;
+	let mut buf = Vec::new();
+	fs::File::open(path)
+		.ok()?
+		.take(MAX_READ)
+		.read_to_end(&mut buf)
+		.ok()?;
+	Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// 导出时替换访问令牌的占位文本
+const TOKEN_PLACEHOLDER: &str = "<access token>";
+
+/// 命令行中的 `--accessToken` 参数和旧版本的 `token:<令牌>:<uuid>` 会话字符串
+static TOKEN_RE: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"--accessToken[ =]+(\S+)|\btoken:([^:\s]+):").unwrap());
+
+/// 找出任一文本中出现的访问令牌，并在所有文本中替换为占位符
+fn redact_tokens(texts: &mut [String]) {
+	let mut tokens: Vec<String> = texts
+		.iter()
+		.flat_map(|text| TOKEN_RE.captures_iter(text))
+		.filter_map(|c| c.get(1).or_else(|| c.get(2)))
+		.map(|m| m.as_str().to_string())
+		// 离线账户的令牌是占位值
+		.filter(|t| t.len() > 1 && t != TOKEN_PLACEHOLDER)
+		.collect();
+	tokens.sort();
+	tokens.dedup();
+	// 先替换较长的令牌，避免其中一部分先被替换
+	tokens.sort_by_key(|t| std::cmp::Reverse(t.len()));
+	for text in texts.iter_mut() {
+		for token in &tokens {
+			if text.contains(token.as_str()) {
+				*text = text.replace(token.as_str(), TOKEN_PLACEHOLDER);
+			}
+		}
+	}
+}
+
+/// 把诊断结果、游戏输出、崩溃报告和最近的游戏日志打包为 zip，
+/// 其中的访问令牌会被替换
+pub fn export_bundle(report: &CrashReport, game_dir: &Path, dest: &Path) -> Result<()> {
+	let latest_log = game_dir.join("logs").join("latest.log");
+	let files = [
+		report.crash_report.as_deref(),
+		report.jvm_error_log.as_deref(),
+		Some(latest_log.as_path()),
+	];
+	let mut names = vec!["diagnosis.txt".to_string(), "output.log".to_string()];
+	let mut contents = vec![report.summary(), report.log_tail.join("\n")];
+	for path in files.into_iter().flatten() {
+		let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+			continue;
+		};
+		let Some(content) = read_limited(path) else {
+			continue;
+		};
+		names.push(name.to_string());
+		contents.push(content);
+	}
+	redact_tokens(&mut contents);
+
+	if let Some(parent) = dest.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	let file =
+		fs::File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+	let mut zip = ZipWriter::new(file);
+	let options = SimpleFileOptions::default();
+	for (name, content) in names.iter().zip(&contents) {
+		zip.start_file(name.as_str(), options)?;
+		zip.write_all(content.as_bytes())?;
+	}
+
+	zip.finish()?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Read;
+	use std::time::Duration;
+
+	fn kinds(report: &CrashReport) -> Vec<CrashKind> {
+		report.diagnoses.iter().map(|d| d.kind).collect()
+	}
+
+	#[test]
+	fn test_known_signatures() {
+		let logs: Vec<String> = [
+			"Exception in thread \"main\" java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 52.0",
+			"\t - Mod 'Sodium Extra' (sodium-extra) 0.5.1 requires any version of sodium, which is missing!",
+			"Mod ID: 'geckolib', Requested by: 'alexsmobs', Expected range: '[4.2,)', Actual version: '[MISSING]'",
+			"java.lang.OutOfMemoryError: Java heap space",
+			"org.spongepowered.asm.mixin.transformer.throwables.MixinTransformerError: An unexpected critical error was encountered",
+			"net.minecraftforge.fml.loading.moddiscovery.ModFileParser$DuplicateModsFoundException",
+		]
+		.iter()
+		.map(|s| s.to_string())
+		.collect();
+
+		let dir = tempfile::tempdir().unwrap();
+		let report = analyze(dir.path(), "test", Some(1), &logs, SystemTime::now());
+		assert_eq!(
+			kinds(&report),
+			[
+				CrashKind::WrongJava,
+				CrashKind::MissingDependency,
+				CrashKind::MissingDependency,
+				CrashKind::OutOfMemory,
+				CrashKind::Mixin,
+				CrashKind::DuplicateMods,
+			]
+		);
+		assert_eq!(report.diagnoses[0].detail, "需要 Java 21 或更高版本");
+		assert_eq!(report.diagnoses[1].detail, "缺少 sodium");
+		assert_eq!(report.diagnoses[2].detail, "alexsmobs 需要 geckolib");
+		assert!(report.headline().starts_with("Java 版本不匹配"));
+	}
+
+	#[test]
+	fn test_collects_new_crash_files_and_exports() {
+		let dir = tempfile::tempdir().unwrap();
+		let since = SystemTime::now() - Duration::from_secs(5);
+		let reports = dir.path().join("crash-reports");
+		fs::create_dir_all(&reports).unwrap();
+		fs::write(
+			reports.join("crash-2024-01-01_00.00.00-client.txt"),
+			"Description: Initializing game\n\njava.lang.RuntimeException: Pixel format not accelerated",
+		)
+		.unwrap();
+		fs::write(
+			dir.path().join("hs_err_pid1234.log"),
+			"# A fatal error\nCommand Line: -Xmx2G net.minecraft.client.main.Main --username Steve --accessToken eyJhbGciOi.secret --version 1.20.1\n",
+		)
+		.unwrap();
+		fs::create_dir_all(dir.path().join("logs")).unwrap();
+		fs::write(
+			dir.path().join("logs").join("latest.log"),
+			"[Client thread/INFO]: Setting user: Steve\n(Session ID is token:eyJhbGciOi.secret:0123abcd)\n",
+		)
+		.unwrap();
+		let output = vec!["Using token eyJhbGciOi.secret".to_string()];
+
+		let report = analyze(dir.path(), "test", Some(-1), &output, since);
+		assert!(report.crash_report.is_some());
+		assert!(report.jvm_error_log.is_some());
+		assert_eq!(kinds(&report), [CrashKind::Graphics]);
+
+		let bundle = dir.path().join("bundle.zip");
+		export_bundle(&report, dir.path(), &bundle).unwrap();
+		let mut archive = zip::ZipArchive::new(fs::File::open(&bundle).unwrap()).unwrap();
+		let mut names: Vec<_> = archive.file_names().map(String::from).collect();
+		names.sort();
+		assert_eq!(
+			names,
+			[
+				"crash-2024-01-01_00.00.00-client.txt",
+				"diagnosis.txt",
+				"hs_err_pid1234.log",
+				"latest.log",
+				"output.log",
+			]
+		);
+		assert!(archive.by_name("diagnosis.txt").is_ok());
+		for i in 0..archive.len() {
+			let mut content = String::new();
+			archive
+				.by_index(i)
+				.unwrap()
+				.read_to_string(&mut content)
+				.unwrap();
+			assert!(!content.contains("secret"), "{content}");
+		}
+		let mut hs_err = String::new();
+		archive
+			.by_name("hs_err_pid1234.log")
+			.unwrap()
+			.read_to_string(&mut hs_err)
+			.unwrap();
+		assert!(hs_err.contains("--accessToken <access token> --version 1.20.1"));
+	}
+}
@@ -0,0 +1,472 @@
+use anyhow::{Context, Result, anyhow};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::game::loader::LoaderVersion;
+use crate::game::maven::MavenCoord;
+use crate::game::profile::Library;
+use crate::net::download::DownloadClient;
+
+const FORGE_MAVEN: &str = "https://maven.minecraftforge.net";
+const NEOFORGE_MAVEN: &str = "https://maven.neoforged.net/releases";
+
+static METADATA_VERSION_RE: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"<version>([^<]+)</version>").unwrap());
+static DATA_REF_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+/// 使用 install_profile 安装器的加载器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForgeFlavor {
+	Forge,
+	NeoForge,
+}
+
+impl ForgeFlavor {
+	pub fn installer_url(self, game_version: &str, version: &str) -> String {
+		match self {
+			ForgeFlavor::Forge => format!(
+				"{FORGE_MAVEN}/net/minecraftforge/forge/{game_version}-{version}/forge-{game_version}-{version}-installer.jar"
+			),
+			ForgeFlavor::NeoForge => format!(
+				"{NEOFORGE_MAVEN}/net/neoforged/neoforge/{version}/neoforge-{version}-installer.jar"
+			),
+		}
+	}
+
+	fn metadata_url(self) -> String {
+		match self {
+			ForgeFlavor::Forge => {
+				format!("{FORGE_MAVEN}/net/minecraftforge/forge/maven-metadata.xml")
+			}
+			ForgeFlavor::NeoForge => {
+				format!("{NEOFORGE_MAVEN}/net/neoforged/neoforge/maven-metadata.xml")
+			}
+		}
+	}
+
+	/// 从 Maven 版本号中取出适用于指定游戏版本的加载器版本
+	fn match_version(self, maven_version: &str, game_version: &str) -> Option<String> {
+		match self {
+			ForgeFlavor::Forge => maven_version
+				.strip_prefix(game_version)?
+				.strip_prefix('-')
+				.map(String::from),
+			ForgeFlavor::NeoForge => (neoforge_game_version(maven_version)? == game_version)
+				.then(|| maven_version.to_string()),
+		}
+	}
+}
+
+/// NeoForge 的版本号以游戏版本开头：20.4.237 -> 1.20.4，21.0.10 -> 1.21
+fn neoforge_game_version(version: &str) -> Option<String> {
+	let mut parts = version.split('.');
+	let major: u32 = parts.next()?.parse().ok()?;
+	let minor: u32 = parts.next()?.parse().ok()?;
+	Some(if minor == 0 {
+		format!("1.{major}")
+	} else {
+		format!("1.{major}.{minor}")
+	})
+}
+
+pub async fn fetch_versions(
+	client: &DownloadClient,
+	flavor: ForgeFlavor,
+	game_version: &str,
+) -> Result<Vec<LoaderVersion>> {
+	let xml = client
+		.fetch_text(&flavor.metadata_url())
+		.await
+		.context("Failed to fetch installer versions")?;
+
+	let mut versions: Vec<String> = METADATA_VERSION_RE
+		.captures_iter(&xml)
+		.filter_map(|c| flavor.match_version(&c[1], game_version))
+		.collect();
+	versions.sort_by(|a, b| compare_versions(b, a));
+
+	Ok(versions
+		.into_iter()
+		.map(|version| LoaderVersion {
+			stable: Some(!version.contains("beta")),
+			version,
+		})
+		.collect())
+}
+
+/// Forge 与 NeoForge 的 Maven 在每个文件旁发布 `.sha1`
+pub async fn fetch_installer_sha1(client: &DownloadClient, installer_url: &str) -> Result<String> {
+	let text = client
+		.fetch_text(&format!("{installer_url}.sha1"))
+		.await
+		.context("Failed to fetch installer checksum")?;
+	parse_sha1(&text).ok_or_else(|| anyhow!("Invalid installer checksum: {}", text.trim()))
+}
+
+fn parse_sha1(text: &str) -> Option<String> {
+	let hash = text.split_whitespace().next()?;
+	(hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+		.then(|| hash.to_ascii_lowercase())
+}
+
+/// 按数字段比较版本号，47.10.0 大于 47.9.1
+fn compare_versions(a: &str, b: &str) -> Ordering {
+	let nums = |s: &str| -> Vec<u64> {
+		s.split(|c: char| !c.is_ascii_digit())
+			.filter_map(|p| p.parse().ok())
+			.collect()
+	};
+	nums(a).cmp(&nums(b))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstallProfile {
+	pub minecraft: String,
+	/// 安装器内版本 JSON 的路径，通常为 /version.json
+	pub json: String,
+	#[serde(default)]
+	pub data: HashMap<String, SidedData>,
+	#[serde(default)]
+	pub processors: Vec<Processor>,
+	#[serde(default)]
+	pub libraries: Vec<Library>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SidedData {
+	pub client: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Processor {
+	pub jar: String,
+	#[serde(default)]
+	pub classpath: Vec<String>,
+	#[serde(default)]
+	pub args: Vec<String>,
+	#[serde(default)]
+	pub outputs: HashMap<String, String>,
+	#[serde(default)]
+	pub sides: Option<Vec<String>>,
+}
+
+impl Processor {
+	pub fn runs_on_client(&self) -> bool {
+		self.sides
+			.as_ref()
+			.is_none_or(|sides| sides.iter().any(|s| s == "client"))
+	}
+}
+
+pub struct ForgeInstaller {
+	path: PathBuf,
+	archive: ZipArchive<fs::File>,
+}
+
+impl ForgeInstaller {
+	pub fn open(path: &Path) -> Result<Self> {
+		let file =
+			fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+		Ok(Self {
+			path: path.to_path_buf(),
+			archive: ZipArchive::new(file).context("Installer is not a valid jar")?,
+		})
+	}
+
+	pub fn install_profile(&mut self) -> Result<InstallProfile> {
+		let text = self.read_text("install_profile.json")?;
+		let value: serde_json::Value =
+			serde_json::from_str(&text).context("Failed to parse install_profile.json")?;
+		if value.get("install").is_some() {
+			return Err(anyhow!(
+				"Legacy installers (Minecraft 1.12.2 and earlier) are not supported"
+			));
+		}
+		serde_json::from_value(value).context("Failed to parse install_profile.json")
+	}
+
+	pub fn read_text(&mut self, name: &str) -> Result<String> {
+		let name = name.trim_start_matches('/');
+		let mut entry = self
+			.archive
+			.by_name(name)
+			.with_context(|| format!("{} not found in installer", name))?;
+		let mut text = String::new();
+		entry.read_to_string(&mut text)?;
+		Ok(text)
+	}
+
+	pub fn extract_file(&mut self, name: &str, dest: &Path) -> Result<()> {
+		let name = name.trim_start_matches('/');
+		let mut entry = self
+			.archive
+			.by_name(name)
+			.with_context(|| format!("{} not found in installer", name))?;
+		if let Some(parent) = dest.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		std::io::copy(&mut entry, &mut fs::File::create(dest)?)
+			.with_context(|| format!("Failed to write {}", dest.display()))?;
+		Ok(())
+	}
+
+	/// 安装器自带的库位于 maven/ 下，解压到游戏的 libraries 目录
+	pub fn extract_maven(&mut self, libraries_dir: &Path) -> Result<()> {
+		for i in 0..self.archive.len() {
+			let mut entry = self.archive.by_index(i)?;
+			if entry.is_dir() {
+				continue;
+			}
+			let Some(rel) = entry
+				.enclosed_name()
+				.and_then(|p| p.strip_prefix("maven").ok().map(Path::to_path_buf))
+			else {
+				continue;
+			};
+			let dest = libraries_dir.join(rel);
+			if let Some(parent) = dest.parent() {
+				fs::create_dir_all(parent)?;
+			}
+			std::io::copy(&mut entry, &mut fs::File::create(&dest)?)
+				.with_context(|| format!("Failed to write {}", dest.display()))?;
+		}
+		Ok(())
+	}
+}
+
+/// 处理器要执行的命令
+#[derive(Debug)]
+pub struct ProcessorCommand {
+	pub classpath: Vec<PathBuf>,
+	pub main_class: String,
+	pub args: Vec<String>,
+}
+
+/// 解析处理器参数中的 `{DATA}` 与 `[maven:coord]` 引用
+pub struct ProcessorEnv {
+	libraries_dir: PathBuf,
+	data: HashMap<String, String>,
+}
+
+impl ProcessorEnv {
+	/// `work_dir` 用于存放从安装器中解出的数据文件
+	pub fn new(
+		profile: &InstallProfile,
+		installer: &mut ForgeInstaller,
+		game_dir: &Path,
+		work_dir: &Path,
+	) -> Result<Self> {
+		let libraries_dir = game_dir.join("libraries");
+		let minecraft_jar = game_dir
+			.join("versions")
+			.join(&profile.minecraft)
+			.join(format!("{}.jar", profile.minecraft));
+
+		let mut env = Self {
+			libraries_dir,
+			data: HashMap::new(),
+		};
+		for (key, value) in &profile.data {
+			let resolved = if value.client.starts_with('/') {
+				let dest = work_dir.join(value.client.trim_start_matches('/'));
+				installer.extract_file(&value.client, &dest)?;
+				path_string(&dest)
+			} else {
+				env.resolve_value(&value.client)?
+			};
+			env.data.insert(key.clone(), resolved);
+		}
+
+		let builtins = [
+			("SIDE", "client".to_string()),
+			("MINECRAFT_JAR", path_string(&minecraft_jar)),
+			("MINECRAFT_VERSION", profile.minecraft.clone()),
+			("ROOT", path_string(game_dir)),
+			("INSTALLER", path_string(&installer.path)),
+			("LIBRARY_DIR", path_string(&env.libraries_dir)),
+		];
+		for (key, value) in builtins {
+			env.data.insert(key.to_string(), value);
+		}
+		Ok(env)
+	}
+
+	pub fn library(&self, coord: &str) -> Result<PathBuf> {
+		Ok(MavenCoord::parse(coord)?.local_path(&self.libraries_dir))
+	}
+
+	/// data 中的值：`[coord]` 为库路径，`'text'` 为字面量
+	fn resolve_value(&self, value: &str) -> Result<String> {
+		if let Some(coord) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+			return Ok(path_string(&self.library(coord)?));
+		}
+		if let Some(literal) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+			return Ok(literal.to_string());
+		}
+		Ok(value.to_string())
+	}
+
+	pub fn resolve(&self, arg: &str) -> Result<String> {
+		if arg.starts_with('[') || arg.starts_with('\'') {
+			return self.resolve_value(arg);
+		}
+		let mut missing = None;
+		let out = DATA_REF_RE.replace_all(arg, |caps: &regex::Captures| {
+			self.data.get(&caps[1]).cloned().unwrap_or_else(|| {
+				missing = Some(caps[1].to_string());
+				String::new()
+			})
+		});
+		match missing {
+			Some(key) => Err(anyhow!("Unknown installer data {{{}}}", key)),
+			None => Ok(out.into_owned()),
+		}
+	}
+
+	pub fn command(&self, processor: &Processor) -> Result<ProcessorCommand> {
+		let jar = self.library(&processor.jar)?;
+		let main_class = jar_main_class(&jar)?;
+		let mut classpath = vec![jar];
+		for coord in &processor.classpath {
+			classpath.push(self.library(coord)?);
+		}
+		let args = processor
+			.args
+			.iter()
+			.map(|a| self.resolve(a))
+			.collect::<Result<_>>()?;
+		Ok(ProcessorCommand {
+			classpath,
+			main_class,
+			args,
+		})
+	}
+
+	/// 所有输出文件都存在且 SHA-1 一致时可跳过该处理器
+	pub fn outputs_valid(&self, processor: &Processor) -> Result<bool> {
+		if processor.outputs.is_empty() {
+			return Ok(false);
+		}
+		for (file, sha1) in &processor.outputs {
+			let path = PathBuf::from(self.resolve(file)?);
+			let expected = self.resolve(sha1)?;
+			let Ok(data) = fs::read(&path) else {
+				return Ok(false);
+			};
+			if !hex::encode(Sha1::digest(&data)).eq_ignore_ascii_case(&expected) {
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+}
+
+fn path_string(path: &Path) -> String {
+	path.to_string_lossy().into_owned()
+}
+
+pub fn jar_main_class(jar: &Path) -> Result<String> {
+	let file = fs::File::open(jar).with_context(|| format!("Failed to open {}", jar.display()))?;
+	let mut archive = ZipArchive::new(file)?;
+	let mut manifest = String::new();
+	archive
+		.by_name("META-INF/MANIFEST.MF")
+		.with_context(|| format!("{} has no manifest", jar.display()))?
+		.read_to_string(&mut manifest)?;
+	manifest_main_class(&manifest).ok_or_else(|| anyhow!("{} has no Main-Class", jar.display()))
+}
+
+fn manifest_main_class(manifest: &str) -> Option<String> {
+	// 清单每行最长 72 字节，超出部分以空格开头续行
+	let mut lines = manifest.lines();
+	let mut value = lines
+		.by_ref()
+		.find_map(|l| l.strip_prefix("Main-Class:"))?
+		.trim()
+		.to_string();
+	for line in lines {
+		match line.strip_prefix(' ') {
+			Some(rest) => value.push_str(rest.trim_end()),
+			None => break,
+		}
+	}
+	Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_processor_env_resolves_references() {
+		let env = ProcessorEnv {
+			libraries_dir: PathBuf::from("/mc/libraries"),
+			data: HashMap::from([
+				(
+					"MAPPINGS".to_string(),
+					"/mc/libraries/net/minecraft/client/1.20.1/client-1.20.1-mappings.txt".into(),
+				),
+				("SIDE".to_string(), "client".to_string()),
+			]),
+		};
+
+		assert_eq!(env.resolve("--side").unwrap(), "--side");
+		assert_eq!(env.resolve("{SIDE}").unwrap(), "client");
+		assert_eq!(
+			env.resolve("{MAPPINGS}").unwrap(),
+			"/mc/libraries/net/minecraft/client/1.20.1/client-1.20.1-mappings.txt"
+		);
+		assert_eq!(env.resolve("'abc123'").unwrap(), "abc123");
+		assert!(env.resolve("{MISSING}").is_err());
+		assert_eq!(
+			env.library("de.oceanlabs.mcp:mcp_config:1.20.1-20230612.114412@zip")
+				.unwrap(),
+			PathBuf::from(
+				"/mc/libraries/de/oceanlabs/mcp/mcp_config/1.20.1-20230612.114412/mcp_config-1.20.1-20230612.114412.zip"
+			)
+		);
+	}
+
+	#[test]
+	fn test_versions_and_manifest() {
+		assert_eq!(
+			ForgeFlavor::Forge.match_version("1.20.1-47.2.0", "1.20.1"),
+			Some("47.2.0".to_string())
+		);
+		assert_eq!(
+			ForgeFlavor::Forge.match_version("1.20.10-1.0", "1.20.1"),
+			None
+		);
+		assert_eq!(
+			ForgeFlavor::NeoForge.match_version("21.0.10-beta", "1.21"),
+			Some("21.0.10-beta".to_string())
+		);
+		assert_eq!(
+			ForgeFlavor::NeoForge
+				.match_version("20.4.237", "1.20.4")
+				.as_deref(),
+			Some("20.4.237")
+		);
+		assert_eq!(compare_versions("47.10.0", "47.9.1"), Ordering::Greater);
+		assert_eq!(
+			parse_sha1("A94A8FE5CCB19BA61C4C0873D391E987982FBBD3  forge-installer.jar\n")
+				.as_deref(),
+			Some("a94a8fe5ccb19ba61c4c0873d391e987982fbbd3")
+		);
+		assert_eq!(parse_sha1("<html>404</html>"), None);
+
+		let manifest = "Manifest-Version: 1.0\r\nMain-Class: net.minecraftforge.installertools.Con\r\n soleTool\r\nCreated-By: Gradle\r\n";
+		assert_eq!(
+			manifest_main_class(manifest).as_deref(),
+			Some("net.minecraftforge.installertools.ConsoleTool")
+		);
+	}
+}
@@ -1,7 +1,32 @@
-use anyhow::Result;
-use std::path::PathBuf;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::core::paths;
+use crate::game::runtime;
+
+const JAVA_BIN: &str = if cfg!(windows) { "java.exe" } else { "java" };
+const CACHE_FILE: &str = "java_installations.json";
+/// 在候选根目录下查找 bin/java 的最大深度，Mojang 运行时目录需要四层
+const SCAN_DEPTH: usize = 4;
+/// 单个 java 可执行文件的探测时间上限
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JavaInstallation {
+	pub path: PathBuf,
+	pub version: String,
+	pub major: u32,
+	pub vendor: String,
+	pub arch: String,
+}
 
-// TODO: 查找多个Java实例
 pub fn find_java(prefer: Option<PathBuf>) -> Result<PathBuf> {
 	if let Some(p) = prefer {
 		if p.exists() {
@@ -9,25 +34,367 @@ pub fn find_java(prefer: Option<PathBuf>) -> Result<PathBuf> {
 		}
 	}
 
-	if let Ok(home) = std::env::var("JAVA_HOME") {
-		let candidate =
-			PathBuf::from(home)
-				.join("bin")
-				.join(if cfg!(windows) { "java.exe" } else { "java" });
-		if candidate.exists() {
-			return Ok(candidate);
-		}
+	if let Some(candidate) = java_home_bin() {
+		return Ok(candidate);
+	}
+
+	if let Some(candidate) = path_java_bins().into_iter().next() {
+		return Ok(candidate);
+	}
+
+	discover_java()
+		.into_iter()
+		.next()
+		.map(|j| j.path)
+		.ok_or_else(|| anyhow!("Java runtime not found"))
+}
+
+/// 扫描常见安装位置并探测每个 Java，结果按主版本号从高到低排列
+pub fn discover_java() -> Vec<JavaInstallation> {
+	let cache = JavaCache::open();
+	let env_bins = java_home_bin()
+		.into_iter()
+		.chain(path_java_bins())
+		.collect();
+	let found = discover_in(env_bins, &candidate_roots(), &cache);
+	if let Err(e) = cache.save() {
+		tracing::warn!("Failed to save java cache: {:#}", e);
 	}
+	found
+}
+
+/// `bins` 为 JAVA_HOME 和 PATH 中直接给出的 Java，优先于扫描 `roots` 得到的
+fn discover_in(
+	mut bins: Vec<PathBuf>,
+	roots: &[PathBuf],
+	cache: &JavaCache,
+) -> Vec<JavaInstallation> {
+	for root in roots {
+		collect_java_bins(root, SCAN_DEPTH, &mut bins);
+	}
+
+	let mut seen = HashSet::new();
+	let mut found: Vec<_> = bins
+		.into_iter()
+		.filter(|bin| seen.insert(fs::canonicalize(bin).unwrap_or_else(|_| bin.clone())))
+		.filter_map(|bin| match cache.probe(&bin) {
+			Ok(java) => Some(java),
+			Err(e) => {
+				tracing::debug!("Skip {}: {:#}", bin.display(), e);
+				None
+			}
+		})
+		.collect();
+	found.sort_by_key(|j| std::cmp::Reverse(j.major));
+	found
+}
+
+fn java_home_bin() -> Option<PathBuf> {
+	let home = std::env::var_os("JAVA_HOME")?;
+	let candidate = PathBuf::from(home).join("bin").join(JAVA_BIN);
+	candidate.exists().then_some(candidate)
+}
+
+fn path_java_bins() -> Vec<PathBuf> {
+	let Some(paths) = std::env::var_os("PATH") else {
+		return Vec::new();
+	};
+	std::env::split_paths(&paths)
+		.map(|p| p.join(JAVA_BIN))
+		.filter(|p| p.exists())
+		.collect()
+}
+
+fn candidate_roots() -> Vec<PathBuf> {
+	let mut roots: Vec<PathBuf> = Vec::new();
 
-	if let Some(paths) = std::env::var_os("PATH") {
-		let name = if cfg!(windows) { "java.exe" } else { "java" };
-		for p in std::env::split_paths(&paths) {
-			let candidate = p.join(name);
-			if candidate.exists() {
-				return Ok(candidate);
+	if cfg!(target_os = "linux") {
+		roots.extend(
+			[
+				"/usr/lib/jvm",
+				"/usr/lib64/jvm",
+				"/usr/java",
+				"/opt/java",
+				"/opt/jdk",
+			]
+			.map(PathBuf::from),
+		);
+	} else if cfg!(target_os = "macos") {
+		roots.push("/Library/Java/JavaVirtualMachines".into());
+	} else if cfg!(windows) {
+		for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+			if let Some(dir) = std::env::var_os(var).map(PathBuf::from) {
+				roots
+					.extend(["Java", "Eclipse Adoptium", "Zulu", "Microsoft"].map(|v| dir.join(v)));
 			}
 		}
 	}
 
-	Err(anyhow::anyhow!("Java runtime not found"))
+	if let Some(home) = dirs::home_dir() {
+		roots.push(home.join(".sdkman").join("candidates").join("java"));
+		roots.push(home.join(".jdks"));
+		roots.push(home.join(".gradle").join("jdks"));
+	}
+
+	// 启动器自己下载的以及官方启动器的运行时
+	if let Ok(dir) = runtime::runtimes_dir() {
+		roots.push(dir);
+	}
+	if let Some(mc) = paths::default_minecraft_dir() {
+		roots.push(mc.join("runtime"));
+	}
+
+	roots
+}
+
+fn collect_java_bins(dir: &Path, depth: usize, out: &mut Vec<PathBuf>) {
+	let bin = dir.join("bin").join(JAVA_BIN);
+	if bin.is_file() {
+		out.push(bin);
+		return;
+	}
+	// macOS 的 JDK 位于 xxx.jdk/Contents/Home
+	let mac_home = dir.join("Contents").join("Home");
+	if mac_home.is_dir() {
+		collect_java_bins(&mac_home, 0, out);
+		return;
+	}
+	if depth == 0 {
+		return;
+	}
+
+	let Ok(entries) = fs::read_dir(dir) else {
+		return;
+	};
+	let mut children: Vec<_> = entries
+		.flatten()
+		.map(|e| e.path())
+		.filter(|p| p.is_dir())
+		.collect();
+	children.sort();
+	for child in children {
+		collect_java_bins(&child, depth - 1, out);
+	}
+}
+
+/// 为要求的主版本挑选 Java：优先完全一致，其次选择最接近的更高版本。
+/// Java 8 的游戏（LaunchWrapper 等）在更高版本上经常无法运行，因此只接受 8。
+pub fn select_java(installations: &[JavaInstallation], required: u32) -> Option<&JavaInstallation> {
+	installations
+		.iter()
+		.filter(|j| j.major == required || (required > 8 && j.major > required))
+		.min_by_key(|j| j.major)
+}
+
+/// 执行 `java -XshowSettings:properties -version` 读取版本、厂商与架构
+pub fn probe_java(bin: &Path) -> Result<JavaInstallation> {
+	probe_java_with_timeout(bin, PROBE_TIMEOUT)
+}
+
+/// 扫描目录中可能有卡住的 java 或包装脚本，超时后结束进程
+fn probe_java_with_timeout(bin: &Path, timeout: Duration) -> Result<JavaInstallation> {
+	let mut child = Command::new(bin)
+		.args(["-XshowSettings:properties", "-version"])
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::piped())
+		.spawn()
+		.with_context(|| format!("Failed to run {}", bin.display()))?;
+
+	// 属性输出在 stderr
+	let mut stderr = child.stderr.take().context("stderr not captured")?;
+	let (tx, rx) = mpsc::channel();
+	std::thread::spawn(move || {
+		let mut buf = Vec::new();
+		let _ = stderr.read_to_end(&mut buf);
+		let _ = tx.send(buf);
+	});
+
+	let output = match rx.recv_timeout(timeout) {
+		Ok(output) => output,
+		Err(_) => {
+			let _ = child.kill();
+			let _ = child.wait();
+			return Err(anyhow!(
+				"{} did not respond within {:?}",
+				bin.display(),
+				timeout
+			));
+		}
+	};
+	let _ = child.wait();
+	let text = String::from_utf8_lossy(&output);
+	parse_java_properties(bin, &text)
+		.ok_or_else(|| anyhow!("Failed to read java properties of {}", bin.display()))
+}
+
+fn parse_java_properties(bin: &Path, output: &str) -> Option<JavaInstallation> {
+	let props: HashMap<&str, &str> = output
+		.lines()
+		.filter_map(|line| line.trim().split_once(" = "))
+		.collect();
+
+	let version = props.get("java.version")?.to_string();
+	Some(JavaInstallation {
+		path: bin.to_path_buf(),
+		major: major_version(&version)?,
+		version,
+		vendor: props
+			.get("java.vendor")
+			.or_else(|| props.get("java.vm.vendor"))
+			.unwrap_or(&"unknown")
+			.to_string(),
+		arch: props.get("os.arch").unwrap_or(&"unknown").to_string(),
+	})
+}
+
+/// "1.8.0_392" -> 8，"17.0.9" -> 17，"21" -> 21
+pub fn major_version(version: &str) -> Option<u32> {
+	let mut parts = version.split(|c: char| !c.is_ascii_digit());
+	let first: u32 = parts.next()?.parse().ok()?;
+	if first == 1 {
+		parts.next()?.parse().ok()
+	} else {
+		Some(first)
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+	modified: u64,
+	java: JavaInstallation,
+}
+
+/// 以 java 可执行文件的修改时间作为缓存键，避免每次启动都重新运行所有 Java
+struct JavaCache {
+	path: Option<PathBuf>,
+	entries: std::sync::Mutex<HashMap<PathBuf, CacheEntry>>,
+}
+
+impl JavaCache {
+	fn open() -> Self {
+		let path = paths::cache_dir().ok().map(|d| d.join(CACHE_FILE));
+		let entries = path
+			.as_ref()
+			.and_then(|p| fs::read_to_string(p).ok())
+			.and_then(|text| serde_json::from_str(&text).ok())
+			.unwrap_or_default();
+		Self {
+			path,
+			entries: std::sync::Mutex::new(entries),
+		}
+	}
+
+	fn probe(&self, bin: &Path) -> Result<JavaInstallation> {
+		let modified = fs::metadata(bin)?
+			.modified()?
+			.duration_since(UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or(0);
+
+		let mut entries = self.entries.lock().unwrap();
+		if let Some(entry) = entries.get(bin).filter(|e| e.modified == modified) {
+			return Ok(entry.java.clone());
+		}
+		let java = probe_java(bin)?;
+		entries.insert(
+			bin.to_path_buf(),
+			CacheEntry {
+				modified,
+				java: java.clone(),
+			},
+		);
+		Ok(java)
+	}
+
+	fn save(&self) -> Result<()> {
+		let Some(path) = &self.path else {
+			return Ok(());
+		};
+		let entries = self.entries.lock().unwrap();
+		fs::write(path, serde_json::to_string(&*entries)?)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_major_version() {
+		assert_eq!(major_version("1.8.0_392"), Some(8));
+		assert_eq!(major_version("17.0.9"), Some(17));
+		assert_eq!(major_version("21"), Some(21));
+		assert_eq!(major_version("22-ea"), Some(22));
+		assert_eq!(major_version("abc"), None);
+	}
+
+	#[test]
+	fn test_select_java() {
+		let java = |major: u32| JavaInstallation {
+			path: PathBuf::from(format!("/jvm/{major}/bin/java")),
+			version: major.to_string(),
+			major,
+			vendor: "test".into(),
+			arch: "amd64".into(),
+		};
+		let installs = [java(21), java(17), java(8)];
+
+		assert_eq!(select_java(&installs, 17).map(|j| j.major), Some(17));
+		assert_eq!(select_java(&installs, 16).map(|j| j.major), Some(17));
+		assert_eq!(select_java(&installs, 8).map(|j| j.major), Some(8));
+		assert_eq!(select_java(&installs[..2], 8), None);
+		assert_eq!(select_java(&installs, 25), None);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_discover_fake_jdks() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let dir = tempfile::tempdir().unwrap();
+		let fake_jdk = |name: &str, version: &str| {
+			let bin = dir.path().join(name).join("bin");
+			fs::create_dir_all(&bin).unwrap();
+			let script = format!(
+				"#!/bin/sh\ncat >&2 <<EOF\nProperty settings:\n    java.vendor = Eclipse Adoptium\n    java.version = {version}\n    os.arch = amd64\n\nopenjdk version \"{version}\"\nEOF\n"
+			);
+			let java = bin.join("java");
+			fs::write(&java, script).unwrap();
+			fs::set_permissions(&java, fs::Permissions::from_mode(0o755)).unwrap();
+		};
+		fake_jdk("jdk-17", "17.0.9");
+		fake_jdk("jdk8", "1.8.0_392");
+		fs::create_dir_all(dir.path().join("broken").join("bin")).unwrap();
+
+		let cache = JavaCache {
+			path: None,
+			entries: Default::default(),
+		};
+		let found = discover_in(Vec::new(), &[dir.path().to_path_buf()], &cache);
+
+		assert_eq!(found.len(), 2);
+		assert_eq!(found[0].major, 17);
+		assert_eq!(found[0].vendor, "Eclipse Adoptium");
+		assert_eq!(found[1].version, "1.8.0_392");
+		assert_eq!(found[1].arch, "amd64");
+		let entries = cache.entries.lock().unwrap();
+		assert!(found.iter().all(|j| entries.contains_key(&j.path)));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_probe_java_times_out() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let dir = tempfile::tempdir().unwrap();
+		let bin = dir.path().join("java");
+		fs::write(&bin, "#!/bin/sh\nexec sleep 30\n").unwrap();
+		fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+
+		let started = std::time::Instant::now();
+		assert!(probe_java_with_timeout(&bin, Duration::from_millis(200)).is_err());
+		assert!(started.elapsed() < Duration::from_secs(5));
+	}
 }
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+use crate::game::forge::{self, ForgeFlavor};
+use crate::net::download::DownloadClient;
+
+/// 通过 `inheritsFrom` 叠加在原版之上的模组加载器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoaderKind {
+	Fabric,
+	Quilt,
+	Forge,
+	NeoForge,
+}
+
+impl LoaderKind {
+	pub const ALL: [LoaderKind; 4] = [
+		LoaderKind::Fabric,
+		LoaderKind::Quilt,
+		LoaderKind::Forge,
+		LoaderKind::NeoForge,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			LoaderKind::Fabric => "Fabric",
+			LoaderKind::Quilt => "Quilt",
+			LoaderKind::Forge => "Forge",
+			LoaderKind::NeoForge => "NeoForge",
+		}
+	}
+
+	/// 需要运行安装器处理器的加载器
+	pub fn forge_flavor(self) -> Option<ForgeFlavor> {
+		match self {
+			LoaderKind::Forge => Some(ForgeFlavor::Forge),
+			LoaderKind::NeoForge => Some(ForgeFlavor::NeoForge),
+			LoaderKind::Fabric | LoaderKind::Quilt => None,
+		}
+	}
+
+	/// 通过 meta 服务直接提供版本 JSON 的加载器
+	fn meta_base(self) -> Option<&'static str> {
+		match self {
+			LoaderKind::Fabric => Some("https://meta.fabricmc.net/v2"),
+			LoaderKind::Quilt => Some("https://meta.quiltmc.org/v3"),
+			LoaderKind::Forge | LoaderKind::NeoForge => None,
+		}
+	}
+
+	/// 加载器版本对应的版本 JSON
+	pub fn profile_url(self, game_version: &str, loader_version: &str) -> Option<String> {
+		Some(format!(
+			"{}/versions/loader/{}/{}/profile/json",
+			self.meta_base()?,
+			game_version,
+			loader_version
+		))
+	}
+
+	/// 与官方安装器生成的目录名一致
+	pub fn default_instance_name(self, game_version: &str, loader_version: &str) -> String {
+		match self {
+			LoaderKind::Fabric => format!("fabric-loader-{loader_version}-{game_version}"),
+			LoaderKind::Quilt => format!("quilt-loader-{loader_version}-{game_version}"),
+			LoaderKind::Forge => format!("{game_version}-forge-{loader_version}"),
+			LoaderKind::NeoForge => format!("neoforge-{loader_version}"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoaderVersion {
+	pub version: String,
+	/// 只有 Fabric 会标记稳定版，其余带 `-beta`、`-rc`、`-pre` 等预发布后缀的视为不稳定
+	#[serde(default)]
+	pub stable: Option<bool>,
+}
+
+impl LoaderVersion {
+	pub fn is_stable(&self) -> bool {
+		self.stable.unwrap_or_else(|| !self.version.contains('-'))
+	}
+}
+
+#[derive(Deserialize)]
+struct LoaderEntry {
+	loader: LoaderVersion,
+}
+
+pub async fn fetch_loader_versions(
+	client: &DownloadClient,
+	kind: LoaderKind,
+	game_version: &str,
+) -> Result<Vec<LoaderVersion>> {
+	if let Some(flavor) = kind.forge_flavor() {
+		return forge::fetch_versions(client, flavor, game_version).await;
+	}
+	let base = kind
+		.meta_base()
+		.ok_or_else(|| anyhow!("{} has no meta server", kind.name()))?;
+	let text = client
+		.fetch_text(&format!("{base}/versions/loader/{game_version}"))
+		.await
+		.with_context(|| format!("Failed to fetch {} versions", kind.name()))?;
+	let entries: Vec<LoaderEntry> = serde_json::from_str(&text)
+		.with_context(|| format!("Failed to parse {} versions", kind.name()))?;
+	Ok(entries.into_iter().map(|e| e.loader).collect())
+}
+
+/// 将加载器的版本 JSON 改写为实例使用的形式：id 与实例目录一致，并继承原版
+pub fn prepare_profile(json: &str, instance: &str, game_version: &str) -> Result<String> {
+	let mut value: serde_json::Value =
+		serde_json::from_str(json).context("Failed to parse loader profile")?;
+	let obj = value
+		.as_object_mut()
+		.ok_or_else(|| anyhow!("Loader profile is not an object"))?;
+
+	match obj.get("inheritsFrom").and_then(|v| v.as_str()) {
+		Some(parent) if parent != game_version => {
+			return Err(anyhow!(
+				"Loader profile inherits {}, expected {}",
+				parent,
+				game_version
+			));
+		}
+		Some(_) => {}
+		None => {
+			obj.insert("inheritsFrom".into(), game_version.into());
+		}
+	}
+	obj.insert("id".into(), instance.into());
+	// 加载器不带游戏本体，客户端 jar 沿用原版
+	obj.entry("jar").or_insert_with(|| game_version.into());
+
+	Ok(serde_json::to_string_pretty(&value)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_prepare_fabric_profile() {
+		let json = r#"{
+			"id": "fabric-loader-0.16.0-1.21",
+			"inheritsFrom": "1.21",
+			"mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+			"libraries": [
+				{ "name": "net.fabricmc:fabric-loader:0.16.0", "url": "https://maven.fabricmc.net/" }
+			]
+		}"#;
+
+		let out = prepare_profile(json, "my-pack", "1.21").unwrap();
+		let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+		assert_eq!(value["id"], "my-pack");
+		assert_eq!(value["inheritsFrom"], "1.21");
+		assert_eq!(value["jar"], "1.21");
+		assert_eq!(value["libraries"][0]["url"], "https://maven.fabricmc.net/");
+
+		assert!(prepare_profile(json, "my-pack", "1.20.1").is_err());
+		assert_eq!(
+			LoaderKind::Fabric.default_instance_name("1.21", "0.16.0"),
+			"fabric-loader-0.16.0-1.21"
+		);
+	}
+
+	#[test]
+	fn test_quilt_loader_versions() {
+		let json = r#"[
+			{ "loader": { "separator": ".", "build": 1, "maven": "org.quiltmc:quilt-loader:0.26.4-beta.1", "version": "0.26.4-beta.1" } },
+			{ "loader": { "separator": ".", "build": 2, "maven": "org.quiltmc:quilt-loader:0.26.3", "version": "0.26.3" } },
+			{ "loader": { "separator": ".", "build": 3, "maven": "org.quiltmc:quilt-loader:0.27.0-rc.2", "version": "0.27.0-rc.2" } },
+			{ "loader": { "separator": ".", "build": 4, "maven": "org.quiltmc:quilt-loader:0.27.0-pre.1", "version": "0.27.0-pre.1" } }
+		]"#;
+		let entries: Vec<LoaderEntry> = serde_json::from_str(json).unwrap();
+		let stable: Vec<_> = entries.iter().map(|e| e.loader.is_stable()).collect();
+		assert_eq!(stable, [false, true, false, false]);
+
+		assert_eq!(
+			LoaderKind::Quilt.profile_url("1.21", "0.26.3").as_deref(),
+			Some("https://meta.quiltmc.org/v3/versions/loader/1.21/0.26.3/profile/json")
+		);
+		assert_eq!(
+			LoaderKind::Quilt.default_instance_name("1.21", "0.26.3"),
+			"quilt-loader-0.26.3-1.21"
+		);
+	}
+}
@@ -0,0 +1,280 @@
+use crate::game::profile::{LoggingFile, VersionProfile};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+static EVENT_ATTR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+static MESSAGE_RE: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"(?s)<log4j:Message>(.*?)</log4j:Message>").unwrap());
+static THROWABLE_RE: Lazy<Regex> =
+	Lazy::new(|| Regex::new(r"(?s)<log4j:Throwable>(.*?)</log4j:Throwable>").unwrap());
+/// 旧版本或未使用 XML 配置时的纯文本格式，如 `[12:00:00] [main/INFO]: ...`
+static PLAIN_LEVEL_RE: Lazy<Regex> = Lazy::new(|| {
+	Regex::new(r"^\[[^\]]*\] \[[^\]]*/(TRACE|DEBUG|INFO|WARN|ERROR|FATAL)\]").unwrap()
+});
+
+const EVENT_END: &str = "</log4j:Event>";
+/// 超过该行数仍未闭合的事件按普通文本输出
+const MAX_EVENT_LINES: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+	Trace,
+	Debug,
+	Info,
+	Warn,
+	Error,
+	Fatal,
+}
+
+impl LogLevel {
+	pub fn parse(s: &str) -> Option<Self> {
+		match s {
+			"TRACE" => Some(LogLevel::Trace),
+			"DEBUG" => Some(LogLevel::Debug),
+			"INFO" => Some(LogLevel::Info),
+			"WARN" => Some(LogLevel::Warn),
+			"ERROR" => Some(LogLevel::Error),
+			"FATAL" => Some(LogLevel::Fatal),
+			_ => None,
+		}
+	}
+
+	/// 从纯文本日志行中识别级别
+	pub fn infer(line: &str) -> Option<Self> {
+		Self::parse(&PLAIN_LEVEL_RE.captures(line)?[1])
+	}
+}
+
+impl fmt::Display for LogLevel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			LogLevel::Trace => "TRACE",
+			LogLevel::Debug => "DEBUG",
+			LogLevel::Info => "INFO",
+			LogLevel::Warn => "WARN",
+			LogLevel::Error => "ERROR",
+			LogLevel::Fatal => "FATAL",
+		})
+	}
+}
+
+/// 一条 `<log4j:Event>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+	pub level: LogLevel,
+	pub thread: String,
+	pub logger: String,
+	/// 毫秒时间戳
+	pub timestamp: u64,
+	pub message: String,
+	pub throwable: Option<String>,
+}
+
+impl LogRecord {
+	/// 与原版纯文本日志相近的单条文本，异常堆栈另起一行
+	pub fn display_text(&self) -> String {
+		let mut text = format!("[{}/{}]: {}", self.thread, self.level, self.message);
+		if let Some(throwable) = &self.throwable {
+			text.push('\n');
+			text.push_str(throwable.trim_end());
+		}
+		text
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Parsed {
+	Line(String),
+	Event(LogRecord),
+}
+
+/// 逐行解析 stdout，把跨多行的 XML 事件合并为一条记录，其余行原样返回
+#[derive(Debug, Default)]
+pub struct Log4jParser {
+	pending: Vec<String>,
+}
+
+impl Log4jParser {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn feed(&mut self, line: &str) -> Option<Parsed> {
+		if self.pending.is_empty() && !line.trim_start().starts_with("<log4j:Event") {
+			return Some(Parsed::Line(line.to_string()));
+		}
+		self.pending.push(line.to_string());
+		if line.contains(EVENT_END) {
+			let xml = std::mem::take(&mut self.pending).join("\n");
+			return Some(match parse_event(&xml) {
+				Some(record) => Parsed::Event(record),
+				None => Parsed::Line(xml),
+			});
+		}
+		if self.pending.len() >= MAX_EVENT_LINES {
+			return Some(Parsed::Line(std::mem::take(&mut self.pending).join("\n")));
+		}
+		None
+	}
+
+	/// 输出结束时仍未闭合的内容
+	pub fn finish(&mut self) -> Option<Parsed> {
+		if self.pending.is_empty() {
+			return None;
+		}
+		Some(Parsed::Line(std::mem::take(&mut self.pending).join("\n")))
+	}
+}
+
+fn parse_event(xml: &str) -> Option<LogRecord> {
+	let open_end = xml.find('>')?;
+	let mut level = None;
+	let mut thread = String::new();
+	let mut logger = String::new();
+	let mut timestamp = 0;
+	for caps in EVENT_ATTR_RE.captures_iter(&xml[..open_end]) {
+		let value = unescape(&caps[2]);
+		match &caps[1] {
+			"level" => level = LogLevel::parse(&value),
+			"thread" => thread = value,
+			"logger" => logger = value,
+			"timestamp" => timestamp = value.parse().unwrap_or(0),
+			_ => {}
+		}
+	}
+
+	Some(LogRecord {
+		level: level?,
+		thread,
+		logger,
+		timestamp,
+		message: MESSAGE_RE
+			.captures(xml)
+			.map(|c| element_text(&c[1]))
+			.unwrap_or_default(),
+		throwable: THROWABLE_RE.captures(xml).map(|c| element_text(&c[1])),
+	})
+}
+
+/// CDATA 中的 `]]>` 会被拆成相邻的多段 CDATA
+fn element_text(raw: &str) -> String {
+	let raw = raw.trim();
+	match raw
+		.strip_prefix("<![CDATA[")
+		.and_then(|s| s.strip_suffix("]]>"))
+	{
+		Some(inner) => inner.replace("]]><![CDATA[", ""),
+		None => unescape(raw),
+	}
+}
+
+fn unescape(s: &str) -> String {
+	s.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&apos;", "'")
+		.replace("&amp;", "&")
+}
+
+/// 与官方启动器相同，日志配置放在 `assets/log_configs`。`id` 来自版本 JSON，
+/// 只接受单个文件名
+pub fn log_config_path(game_dir: &Path, file: &LoggingFile) -> anyhow::Result<PathBuf> {
+	let id = file.id.as_str();
+	if id.is_empty() || id == "." || id == ".." || id.contains(['/', '\\', ':']) {
+		anyhow::bail!("Invalid log config id: {}", id);
+	}
+	Ok(game_dir.join("assets").join("log_configs").join(id))
+}
+
+/// 版本指定的日志配置参数，配置文件不存在时跳过
+pub fn logging_jvm_arg(game_dir: &Path, profile: &VersionProfile) -> Option<String> {
+	let client = profile.logging.as_ref()?.client.as_ref()?;
+	let path = log_config_path(game_dir, &client.file)
+		.inspect_err(|e| tracing::warn!("{}", e))
+		.ok()?;
+	if !path.exists() {
+		tracing::warn!("Log config missing: {}", path.display());
+		return None;
+	}
+	Some(client.argument.replace("${path}", &path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_event_stream() {
+		let stdout = r#"Plain startup line
+<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1700000000123" level="INFO" thread="Render thread">
+  <log4j:Message><![CDATA[Setting user: Steve]]></log4j:Message>
+</log4j:Event>
+<log4j:Event logger="net.minecraft.server" timestamp="1700000000456" level="ERROR" thread="Server &quot;main&quot;">
+  <log4j:Message><![CDATA[Oops ]]]]><![CDATA[> done]]></log4j:Message>
+  <log4j:Throwable><![CDATA[java.lang.IllegalStateException: boom
+	at a.b.C.d(C.java:1)
+]]></log4j:Throwable>
+</log4j:Event>
+[12:00:00] [main/WARN]: legacy line"#;
+
+		let mut parser = Log4jParser::new();
+		let out: Vec<_> = stdout.lines().filter_map(|l| parser.feed(l)).collect();
+		assert_eq!(parser.finish(), None);
+		assert_eq!(out.len(), 4);
+		assert_eq!(out[0], Parsed::Line("Plain startup line".into()));
+
+		let Parsed::Event(info) = &out[1] else {
+			panic!("expected event");
+		};
+		assert_eq!(info.level, LogLevel::Info);
+		assert_eq!(info.thread, "Render thread");
+		assert_eq!(info.logger, "net.minecraft.client.Minecraft");
+		assert_eq!(info.timestamp, 1700000000123);
+		assert_eq!(info.message, "Setting user: Steve");
+		assert_eq!(info.throwable, None);
+
+		let Parsed::Event(error) = &out[2] else {
+			panic!("expected event");
+		};
+		assert_eq!(error.level, LogLevel::Error);
+		assert_eq!(error.thread, "Server \"main\"");
+		assert_eq!(error.message, "Oops ]]> done");
+		assert!(
+			error
+				.display_text()
+				.ends_with("boom\n\tat a.b.C.d(C.java:1)")
+		);
+
+		let Parsed::Line(legacy) = &out[3] else {
+			panic!("expected line");
+		};
+		assert_eq!(LogLevel::infer(legacy), Some(LogLevel::Warn));
+	}
+
+	#[test]
+	fn test_log_config_path_rejects_paths() {
+		let file = |id: &str| LoggingFile {
+			id: id.into(),
+			sha1: None,
+			size: None,
+			url: String::new(),
+		};
+		let game_dir = Path::new("game");
+		assert_eq!(
+			log_config_path(game_dir, &file("client-1.12.xml")).unwrap(),
+			game_dir.join("assets/log_configs/client-1.12.xml")
+		);
+		for id in [
+			"",
+			"..",
+			"../../evil.xml",
+			"a/b.xml",
+			"a\\b.xml",
+			"C:evil.xml",
+		] {
+			assert!(log_config_path(game_dir, &file(id)).is_err(), "{id}");
+		}
+	}
+}
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::core::paths::cache_dir;
+use crate::net::download::{CacheValidators, DownloadClient, Fetched};
+
+pub const VERSION_MANIFEST_URL: &str =
+	"https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+const MANIFEST_FILE: &str = "version_manifest_v2.json";
+const VALIDATORS_FILE: &str = "version_manifest_v2.etag.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionManifest {
+	pub latest: LatestVersions,
+	pub versions: Vec<ManifestVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersions {
+	pub release: String,
+	pub snapshot: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionType {
+	Release,
+	Snapshot,
+	OldBeta,
+	OldAlpha,
+	#[serde(other)]
+	Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestVersion {
+	pub id: String,
+	#[serde(rename = "type")]
+	pub version_type: VersionType,
+	pub url: String,
+	pub time: String,
+	pub release_time: String,
+	/// 版本 JSON 的 SHA-1，仅 v2 清单提供
+	#[serde(default)]
+	pub sha1: Option<String>,
+	#[serde(default)]
+	pub compliance_level: Option<u32>,
+}
+
+impl VersionManifest {
+	pub fn find(&self, id: &str) -> Option<&ManifestVersion> {
+		self.versions.iter().find(|v| v.id == id)
+	}
+}
+
+/// 版本清单的磁盘缓存，通过 ETag / Last-Modified 重新验证，网络不可用时回退到缓存
+pub struct ManifestCache {
+	dir: PathBuf,
+	url: String,
+}
+
+impl ManifestCache {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		Self {
+			dir: dir.into(),
+			url: VERSION_MANIFEST_URL.to_string(),
+		}
+	}
+
+	pub fn with_url(mut self, url: impl Into<String>) -> Self {
+		self.url = url.into();
+		self
+	}
+
+	pub fn open_default() -> Result<Self> {
+		Ok(Self::new(cache_dir()?))
+	}
+
+	pub async fn load(&self, client: &DownloadClient) -> Result<VersionManifest> {
+		let cached = self.load_cached();
+		let validators = match &cached {
+			Some(_) => self.load_validators(),
+			None => CacheValidators::default(),
+		};
+
+		match client.fetch_text_conditional(&self.url, &validators).await {
+			Ok(Fetched::NotModified) => {
+				cached.context("Version manifest not modified but cache is missing")
+			}
+			Ok(Fetched::Modified { body, validators }) => {
+				let manifest =
+					serde_json::from_str(&body).context("Failed to parse version manifest")?;
+				if let Err(e) = self.store(&body, &validators) {
+					warn!("Failed to cache version manifest: {}", e);
+				}
+				Ok(manifest)
+			}
+			Err(e) => match cached {
+				Some(manifest) => {
+					warn!("Fetch version manifest failed: {}, using cached copy", e);
+					Ok(manifest)
+				}
+				None => Err(e).context("Failed to fetch version manifest"),
+			},
+		}
+	}
+
+	/// 只读取磁盘缓存，不访问网络
+	pub fn load_cached(&self) -> Option<VersionManifest> {
+		let text = fs::read_to_string(self.manifest_path()).ok()?;
+		serde_json::from_str(&text).ok()
+	}
+
+	fn load_validators(&self) -> CacheValidators {
+		fs::read_to_string(self.dir.join(VALIDATORS_FILE))
+			.ok()
+			.and_then(|text| serde_json::from_str(&text).ok())
+			.unwrap_or_default()
+	}
+
+	fn store(&self, body: &str, validators: &CacheValidators) -> Result<()> {
+		fs::create_dir_all(&self.dir).context("Failed to create cache directory")?;
+		write_atomic(&self.manifest_path(), body.as_bytes())?;
+		write_atomic(
+			&self.dir.join(VALIDATORS_FILE),
+			serde_json::to_string(validators)?.as_bytes(),
+		)
+	}
+
+	fn manifest_path(&self) -> PathBuf {
+		self.dir.join(MANIFEST_FILE)
+	}
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+	let temp = path.with_extension("tmp");
+	fs::write(&temp, data).with_context(|| format!("Failed to write {}", temp.display()))?;
+	fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpListener;
+
+	const MANIFEST: &str = r#"{
+		"latest": {"release": "1.21.1", "snapshot": "24w33a"},
+		"versions": [
+			{"id": "24w33a", "type": "snapshot", "url": "https://example.com/24w33a.json", "time": "2024-08-15T12:00:00+00:00", "releaseTime": "2024-08-15T11:00:00+00:00", "sha1": "aa", "complianceLevel": 1},
+			{"id": "1.21.1", "type": "release", "url": "https://example.com/1.21.1.json", "time": "2024-08-08T12:00:00+00:00", "releaseTime": "2024-08-08T11:00:00+00:00", "sha1": "bb", "complianceLevel": 1},
+			{"id": "b1.7.3", "type": "old_beta", "url": "https://example.com/b1.7.3.json", "time": "2011-07-08T12:00:00+00:00", "releaseTime": "2011-07-08T11:00:00+00:00"}
+		]
+	}"#;
+
+	/// 带 ETag 的清单服务器，客户端携带匹配的 If-None-Match 时返回 304，
+	/// 同时返回 304 的次数
+	async fn start_manifest_server() -> (String, Arc<AtomicUsize>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}/manifest.json", listener.local_addr().unwrap());
+		let revalidated = Arc::new(AtomicUsize::new(0));
+		let counter = Arc::clone(&revalidated);
+
+		tokio::spawn(async move {
+			loop {
+				let (mut socket, _) = listener.accept().await.unwrap();
+				let counter = Arc::clone(&counter);
+				tokio::spawn(async move {
+					let mut buf = [0u8; 1024];
+					let n = socket.read(&mut buf).await.unwrap();
+					let req = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+
+					let response = if req.contains("if-none-match: \"v1\"") {
+						counter.fetch_add(1, Ordering::SeqCst);
+						"HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n".to_string()
+					} else {
+						format!(
+							"HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n{}",
+							MANIFEST.len(),
+							MANIFEST
+						)
+					};
+					socket.write_all(response.as_bytes()).await.unwrap();
+				});
+			}
+		});
+
+		(url, revalidated)
+	}
+
+	#[tokio::test]
+	async fn test_manifest_revalidate_and_offline_fallback() {
+		let dir = tempfile::tempdir().unwrap();
+		let (url, revalidated) = start_manifest_server().await;
+		let client = DownloadClient::new().unwrap();
+		let cache = ManifestCache::new(dir.path()).with_url(&url);
+
+		let manifest = cache.load(&client).await.unwrap();
+		assert_eq!(manifest.latest.release, "1.21.1");
+		assert_eq!(
+			manifest.find("b1.7.3").unwrap().version_type,
+			VersionType::OldBeta
+		);
+		assert_eq!(manifest.find("1.21.1").unwrap().sha1.as_deref(), Some("bb"));
+		assert_eq!(cache.load_validators().etag.as_deref(), Some("\"v1\""));
+		assert_eq!(revalidated.load(Ordering::SeqCst), 0);
+
+		// 第二次请求携带 ETag 命中 304，直接使用缓存
+		let manifest = cache.load(&client).await.unwrap();
+		assert_eq!(manifest.versions.len(), 3);
+		assert_eq!(revalidated.load(Ordering::SeqCst), 1);
+
+		// 服务器不可达时回退到缓存
+		let offline = ManifestCache::new(dir.path()).with_url("http://127.0.0.1:1/manifest.json");
+		let manifest = offline.load(&client).await.unwrap();
+		assert_eq!(manifest.latest.snapshot, "24w33a");
+
+		let empty = tempfile::tempdir().unwrap();
+		let offline = ManifestCache::new(empty.path()).with_url("http://127.0.0.1:1/manifest.json");
+		assert!(offline.load(&client).await.is_err());
+	}
+}
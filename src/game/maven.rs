@@ -0,0 +1,144 @@
+use anyhow::{Result, anyhow};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// 库没有给出下载地址或给出的地址失效时依次尝试的仓库
+pub const DEFAULT_REPOSITORIES: &[&str] = &[
+	"https://libraries.minecraft.net/",
+	"https://maven.fabricmc.net/",
+	"https://maven.quiltmc.org/repository/release/",
+	"https://maven.minecraftforge.net/",
+	"https://maven.neoforged.net/releases/",
+	"https://repo1.maven.org/maven2/",
+];
+
+/// `group:artifact:version[:classifier][@extension]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MavenCoord {
+	pub group: String,
+	pub artifact: String,
+	pub version: String,
+	pub classifier: Option<String>,
+	pub extension: String,
+}
+
+impl MavenCoord {
+	pub fn parse(coord: &str) -> Result<Self> {
+		let (coord, extension) = coord.split_once('@').unwrap_or((coord, "jar"));
+		let parts: Vec<&str> = coord.split(':').collect();
+		match parts.as_slice() {
+			[group, artifact, version, rest @ ..]
+				if rest.len() <= 1 && [group, artifact, version].iter().all(|p| !p.is_empty()) =>
+			{
+				Ok(Self {
+					group: group.to_string(),
+					artifact: artifact.to_string(),
+					version: version.to_string(),
+					classifier: rest.first().map(|c| c.to_string()),
+					extension: extension.to_string(),
+				})
+			}
+			_ => Err(anyhow!("Invalid maven coord: {coord}")),
+		}
+	}
+
+	pub fn with_classifier(mut self, classifier: impl Into<String>) -> Self {
+		self.classifier = Some(classifier.into());
+		self
+	}
+
+	/// 同一个库的不同版本拥有相同的 key：`group:artifact[:classifier]`
+	pub fn key(&self) -> String {
+		match &self.classifier {
+			Some(c) => format!("{}:{}:{}", self.group, self.artifact, c),
+			None => format!("{}:{}", self.group, self.artifact),
+		}
+	}
+
+	/// 仓库中的相对路径，如 `net/fabricmc/fabric-loader/0.16.0/fabric-loader-0.16.0.jar`
+	pub fn relative_path(&self) -> String {
+		let file_name = match &self.classifier {
+			Some(c) => format!(
+				"{}-{}-{}.{}",
+				self.artifact, self.version, c, self.extension
+			),
+			None => format!("{}-{}.{}", self.artifact, self.version, self.extension),
+		};
+		format!(
+			"{}/{}/{}/{}",
+			self.group.replace('.', "/"),
+			self.artifact,
+			self.version,
+			file_name
+		)
+	}
+
+	pub fn local_path(&self, libraries_dir: &Path) -> PathBuf {
+		libraries_dir.join(
+			self.relative_path()
+				.replace('/', std::path::MAIN_SEPARATOR_STR),
+		)
+	}
+
+	pub fn url(&self, repository: &str) -> String {
+		format!(
+			"{}/{}",
+			repository.trim_end_matches('/'),
+			self.relative_path()
+		)
+	}
+}
+
+impl fmt::Display for MavenCoord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+		if let Some(c) = &self.classifier {
+			write!(f, ":{c}")?;
+		}
+		if self.extension != "jar" {
+			write!(f, "@{}", self.extension)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_coords() {
+		let coord = MavenCoord::parse("net.fabricmc:fabric-loader:0.16.0").unwrap();
+		assert_eq!(coord.key(), "net.fabricmc:fabric-loader");
+		assert_eq!(
+			coord.url("https://maven.fabricmc.net/"),
+			"https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.16.0/fabric-loader-0.16.0.jar"
+		);
+
+		let coord =
+			MavenCoord::parse("net.minecraft:client:1.20.1-20230612.114412:mappings@txt").unwrap();
+		assert_eq!(coord.classifier.as_deref(), Some("mappings"));
+		assert_eq!(
+			coord.relative_path(),
+			"net/minecraft/client/1.20.1-20230612.114412/client-1.20.1-20230612.114412-mappings.txt"
+		);
+		assert_eq!(
+			coord.to_string(),
+			"net.minecraft:client:1.20.1-20230612.114412:mappings@txt"
+		);
+
+		let natives = MavenCoord::parse("org.lwjgl:lwjgl:3.3.3")
+			.unwrap()
+			.with_classifier("natives-linux");
+		assert_eq!(natives.key(), "org.lwjgl:lwjgl:natives-linux");
+		assert!(
+			natives
+				.relative_path()
+				.ends_with("lwjgl-3.3.3-natives-linux.jar")
+		);
+
+		assert!(MavenCoord::parse("broken").is_err());
+		assert!(MavenCoord::parse("a:b:c:d:e").is_err());
+		assert!(MavenCoord::parse("a::c").is_err());
+	}
+}
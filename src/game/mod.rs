@@ -1,6 +1,16 @@
 pub mod args;
 pub mod classpath;
+pub mod crash;
+pub mod forge;
 pub mod instance;
 pub mod java;
+pub mod loader;
+pub mod log4j;
+pub mod manifest;
+pub mod maven;
 pub mod natives;
+pub mod process;
 pub mod profile;
+pub mod readiness;
+pub mod runtime;
+pub mod script;
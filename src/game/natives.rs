@@ -57,7 +57,7 @@ pub fn extract_natives(
 			continue;
 		}
 
-		let natives_jar = match library_path(game_dir, lib, os_key, arch)? {
+		let natives_jar = match library_path(game_dir, lib, os_key)? {
 			Some(p) if p.exists() => p,
 			Some(p) => {
 				tracing::warn!("Natives jar missing: {}, skipping", p.display());
@@ -0,0 +1,405 @@
+use crate::game::crash::{self, CrashReport};
+use crate::game::log4j::{Log4jParser, LogLevel, LogRecord, Parsed};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Child;
+use tokio::sync::{Notify, broadcast, watch};
+
+/// 每个游戏保留的日志行数
+const LOG_CAPACITY: usize = 5000;
+const LOG_CHANNEL_CAPACITY: usize = 256;
+/// 进程退出后等待输出读完的时间
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogStream {
+	Stdout,
+	Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+	pub stream: LogStream,
+	pub text: String,
+	pub level: Option<LogLevel>,
+	/// 来自 log4j XML 事件时的结构化内容
+	pub record: Option<LogRecord>,
+}
+
+impl LogLine {
+	fn from_parsed(stream: LogStream, parsed: Parsed) -> Self {
+		match parsed {
+			Parsed::Line(text) => Self {
+				stream,
+				level: LogLevel::infer(&text),
+				text,
+				record: None,
+			},
+			Parsed::Event(record) => Self {
+				stream,
+				text: record.display_text(),
+				level: Some(record.level),
+				record: Some(record),
+			},
+		}
+	}
+}
+
+/// 固定容量的日志缓冲，写满后丢弃最旧的行
+#[derive(Debug)]
+pub struct LogBuffer {
+	lines: VecDeque<LogLine>,
+	capacity: usize,
+	dropped: usize,
+}
+
+impl LogBuffer {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			lines: VecDeque::with_capacity(capacity.min(1024)),
+			capacity,
+			dropped: 0,
+		}
+	}
+
+	pub fn push(&mut self, line: LogLine) {
+		if self.lines.len() == self.capacity {
+			self.lines.pop_front();
+			self.dropped += 1;
+		}
+		self.lines.push_back(line);
+	}
+
+	pub fn lines(&self) -> impl Iterator<Item = &LogLine> {
+		self.lines.iter()
+	}
+
+	/// 因超出容量被丢弃的行数
+	pub fn dropped(&self) -> usize {
+		self.dropped
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessStatus {
+	Running,
+	/// 被信号终止时没有退出码
+	Exited(Option<i32>),
+	Killed,
+}
+
+/// 启动器持有的游戏进程，后台持续读取输出并等待退出
+pub struct GameProcess {
+	pub id: u64,
+	pub instance: String,
+	pub game_dir: PathBuf,
+	pub pid: Option<u32>,
+	pub started_at: Instant,
+	/// 用于找出本次启动后生成的崩溃报告
+	started_wall: SystemTime,
+	ended_at: Mutex<Option<Instant>>,
+	crash: Mutex<Option<Arc<CrashReport>>>,
+	logs: Mutex<LogBuffer>,
+	lines: broadcast::Sender<LogLine>,
+	status: watch::Sender<ProcessStatus>,
+	kill: Notify,
+}
+
+impl GameProcess {
+	pub fn status(&self) -> ProcessStatus {
+		*self.status.borrow()
+	}
+
+	pub fn is_running(&self) -> bool {
+		self.status() == ProcessStatus::Running
+	}
+
+	pub fn uptime(&self) -> Duration {
+		let end = self.ended_at.lock().unwrap().unwrap_or_else(Instant::now);
+		end.duration_since(self.started_at)
+	}
+
+	/// 当前缓冲中的日志快照
+	pub fn logs(&self) -> Vec<LogLine> {
+		self.logs.lock().unwrap().lines().cloned().collect()
+	}
+
+	/// 异常退出时的崩溃分析，在状态变为已退出之前写入
+	pub fn crash(&self) -> Option<Arc<CrashReport>> {
+		self.crash.lock().unwrap().clone()
+	}
+
+	pub fn dropped_lines(&self) -> usize {
+		self.logs.lock().unwrap().dropped()
+	}
+
+	/// 订阅之后产生的日志行
+	pub fn subscribe(&self) -> broadcast::Receiver<LogLine> {
+		self.lines.subscribe()
+	}
+
+	pub async fn wait_exit(&self) -> ProcessStatus {
+		let mut rx = self.status.subscribe();
+		let status = rx
+			.wait_for(|s| *s != ProcessStatus::Running)
+			.await
+			.map(|s| *s);
+		status.unwrap_or(ProcessStatus::Exited(None))
+	}
+
+	pub fn kill(&self) {
+		if self.is_running() {
+			self.kill.notify_one();
+		}
+	}
+
+	fn push_line(&self, line: LogLine) {
+		self.logs.lock().unwrap().push(line.clone());
+		let _ = self.lines.send(line);
+	}
+
+	fn finish(&self, status: ProcessStatus) {
+		*self.ended_at.lock().unwrap() = Some(Instant::now());
+		self.status.send_replace(status);
+	}
+}
+
+/// 正在运行和已退出但尚未移除的游戏
+pub struct GameRegistry {
+	games: RwLock<Vec<Arc<GameProcess>>>,
+	next_id: AtomicU64,
+}
+
+impl Default for GameRegistry {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl GameRegistry {
+	pub fn new() -> Self {
+		Self {
+			games: RwLock::new(Vec::new()),
+			next_id: AtomicU64::new(1),
+		}
+	}
+
+	/// 接管子进程：持续读取 stdout/stderr 并在退出时记录状态，需在 tokio 运行时中调用
+	pub fn spawn(
+		&self,
+		instance: impl Into<String>,
+		game_dir: PathBuf,
+		mut child: Child,
+	) -> Arc<GameProcess> {
+		let (status, _) = watch::channel(ProcessStatus::Running);
+		let (lines, _) = broadcast::channel(LOG_CHANNEL_CAPACITY);
+		let game = Arc::new(GameProcess {
+			id: self.next_id.fetch_add(1, Ordering::Relaxed),
+			instance: instance.into(),
+			game_dir,
+			pid: child.id(),
+			started_at: Instant::now(),
+			started_wall: SystemTime::now(),
+			ended_at: Mutex::new(None),
+			crash: Mutex::new(None),
+			logs: Mutex::new(LogBuffer::new(LOG_CAPACITY)),
+			lines,
+			status,
+			kill: Notify::new(),
+		});
+
+		let mut readers = Vec::new();
+		if let Some(stdout) = child.stdout.take() {
+			readers.push(tokio::spawn(drain(
+				stdout,
+				LogStream::Stdout,
+				Arc::clone(&game),
+			)));
+		}
+		if let Some(stderr) = child.stderr.take() {
+			readers.push(tokio::spawn(drain(
+				stderr,
+				LogStream::Stderr,
+				Arc::clone(&game),
+			)));
+		}
+
+		let waiter = Arc::clone(&game);
+		tokio::spawn(async move {
+			let (result, killed) = tokio::select! {
+				result = child.wait() => (result, false),
+				_ = waiter.kill.notified() => {
+					let _ = child.start_kill();
+					(child.wait().await, true)
+				}
+			};
+			// 退出后把管道中剩余的输出读完。游戏启动的其他进程可能继承了管道，
+			// 超时后不再等待
+			for mut reader in readers {
+				if tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, &mut reader)
+					.await
+					.is_err()
+				{
+					tracing::debug!("Output of {} still open after exit", waiter.instance);
+					reader.abort();
+				}
+			}
+			let status = match result {
+				_ if killed => ProcessStatus::Killed,
+				Ok(status) => ProcessStatus::Exited(status.code()),
+				Err(e) => {
+					tracing::warn!("Failed to wait for {}: {}", waiter.instance, e);
+					ProcessStatus::Exited(None)
+				}
+			};
+			tracing::info!("Game {} finished: {:?}", waiter.instance, status);
+			if let ProcessStatus::Exited(code) = status
+				&& code != Some(0)
+			{
+				let game = Arc::clone(&waiter);
+				let report = tokio::task::spawn_blocking(move || {
+					let logs: Vec<String> = game.logs().into_iter().map(|l| l.text).collect();
+					crash::analyze(
+						&game.game_dir,
+						&game.instance,
+						code,
+						&logs,
+						game.started_wall,
+					)
+				})
+				.await;
+				if let Ok(report) = report {
+					*waiter.crash.lock().unwrap() = Some(Arc::new(report));
+				}
+			}
+			waiter.finish(status);
+		});
+
+		self.games.write().unwrap().push(Arc::clone(&game));
+		game
+	}
+
+	pub fn list(&self) -> Vec<Arc<GameProcess>> {
+		self.games.read().unwrap().clone()
+	}
+
+	pub fn has_running(&self) -> bool {
+		self.games.read().unwrap().iter().any(|g| g.is_running())
+	}
+
+	/// 移除已退出的游戏，运行中的不受影响
+	pub fn remove(&self, id: u64) {
+		self.games
+			.write()
+			.unwrap()
+			.retain(|g| g.id != id || g.is_running());
+	}
+}
+
+/// 按字节读取，游戏输出不一定是 UTF-8。使用 log4j XML 配置时事件会跨越多行
+async fn drain(reader: impl AsyncRead + Unpin, stream: LogStream, game: Arc<GameProcess>) {
+	let mut reader = BufReader::new(reader);
+	let mut parser = Log4jParser::new();
+	let mut buf = Vec::new();
+	loop {
+		buf.clear();
+		match reader.read_until(b'\n', &mut buf).await {
+			Ok(0) => break,
+			Ok(_) => {
+				let text = String::from_utf8_lossy(&buf);
+				if let Some(parsed) = parser.feed(text.trim_end_matches(['\r', '\n'])) {
+					game.push_line(LogLine::from_parsed(stream, parsed));
+				}
+			}
+			Err(e) => {
+				tracing::debug!("Stopped reading game output: {}", e);
+				break;
+			}
+		}
+	}
+	if let Some(parsed) = parser.finish() {
+		game.push_line(LogLine::from_parsed(stream, parsed));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn line(text: &str) -> LogLine {
+		LogLine {
+			stream: LogStream::Stdout,
+			text: text.into(),
+			level: None,
+			record: None,
+		}
+	}
+
+	#[test]
+	fn test_log_buffer_drops_oldest() {
+		let mut buf = LogBuffer::new(3);
+		for i in 0..5 {
+			buf.push(line(&i.to_string()));
+		}
+		let texts: Vec<_> = buf.lines().map(|l| l.text.as_str()).collect();
+		assert_eq!(texts, ["2", "3", "4"]);
+		assert_eq!(buf.dropped(), 2);
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_registry_collects_output_and_kills() {
+		use std::process::Stdio;
+		use tokio::process::Command;
+
+		let registry = GameRegistry::new();
+		let child = Command::new("sh")
+			.args(["-c", "echo hello; echo oops >&2; exit 3"])
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.unwrap();
+		let game = registry.spawn("test", std::env::temp_dir(), child);
+		assert_eq!(game.wait_exit().await, ProcessStatus::Exited(Some(3)));
+		assert!(game.crash().is_some());
+		let logs = game.logs();
+		assert!(
+			logs.iter()
+				.any(|l| l.stream == LogStream::Stdout && l.text == "hello")
+		);
+		assert!(
+			logs.iter()
+				.any(|l| l.stream == LogStream::Stderr && l.text == "oops")
+		);
+
+		let child = Command::new("sleep")
+			.arg("30")
+			.stdout(Stdio::piped())
+			.spawn()
+			.unwrap();
+		let sleeper = registry.spawn("sleeper", std::env::temp_dir(), child);
+		assert!(registry.has_running());
+		sleeper.kill();
+		assert_eq!(sleeper.wait_exit().await, ProcessStatus::Killed);
+
+		registry.remove(game.id);
+		assert_eq!(registry.list().len(), 1);
+
+		// 子进程退出后仍有后台进程占用管道，不能一直停留在运行中
+		let child = Command::new("sh")
+			.args(["-c", "sleep 5 & exit 1"])
+			.stdout(Stdio::piped())
+			.spawn()
+			.unwrap();
+		let orphaned = registry.spawn("orphaned", std::env::temp_dir(), child);
+		let status = tokio::time::timeout(Duration::from_secs(4), orphaned.wait_exit())
+			.await
+			.unwrap();
+		assert_eq!(status, ProcessStatus::Exited(Some(1)));
+		assert!(orphaned.crash().is_some());
+	}
+}
@@ -1,3 +1,4 @@
+use crate::game::maven::MavenCoord;
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -8,6 +9,8 @@ use std::path::Path;
 pub struct VersionProfile {
 	#[serde(default, rename = "inheritsFrom")]
 	pub inherits_from: Option<String>,
+	#[serde(default, rename = "type")]
+	pub version_type: Option<String>,
 	#[serde(default, rename = "mainClass")]
 	pub main_class: Option<String>,
 	#[serde(default)]
@@ -22,6 +25,44 @@ pub struct VersionProfile {
 	pub asset_index: Option<AssetIndexInfo>,
 	#[serde(default)]
 	pub downloads: Option<VersionDownloads>,
+	#[serde(default, rename = "javaVersion")]
+	pub java_version: Option<JavaVersion>,
+	/// 客户端 jar 所在的版本，继承的版本默认使用父版本的 jar
+	#[serde(default)]
+	pub jar: Option<String>,
+	#[serde(default)]
+	pub logging: Option<Logging>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct Logging {
+	#[serde(default)]
+	pub client: Option<LoggingConfig>,
+}
+
+/// log4j 配置文件及引用它的 JVM 参数，参数中的 `${path}` 替换为文件路径
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoggingConfig {
+	pub argument: String,
+	pub file: LoggingFile,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoggingFile {
+	pub id: String,
+	#[serde(default)]
+	pub sha1: Option<String>,
+	#[serde(default)]
+	pub size: Option<u64>,
+	pub url: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JavaVersion {
+	#[serde(default)]
+	pub component: Option<String>,
+	#[serde(rename = "majorVersion")]
+	pub major_version: u32,
 }
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -71,6 +112,13 @@ pub struct Library {
 	pub downloads: Option<LibraryDownloads>,
 	#[serde(default)]
 	pub extract: Option<Extract>,
+	/// 加载器的库只给出 Maven 仓库地址，没有 downloads
+	#[serde(default)]
+	pub url: Option<String>,
+	#[serde(default)]
+	pub sha1: Option<String>,
+	#[serde(default)]
+	pub size: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -160,13 +208,22 @@ pub fn load_version_profile(game_dir: &Path, version: &str) -> Result<VersionPro
 
 	if let Some(parent) = profile.inherits_from.take() {
 		let parent_profile = load_version_profile(game_dir, &parent)?;
+		let jar = profile
+			.jar
+			.take()
+			.or_else(|| parent_profile.jar.clone())
+			.unwrap_or(parent);
 		profile = merge_profile(parent_profile, profile);
+		profile.jar = Some(jar);
 	}
 
 	Ok(profile)
 }
 
 pub fn merge_profile(mut base: VersionProfile, child: VersionProfile) -> VersionProfile {
+	if child.version_type.is_some() {
+		base.version_type = child.version_type;
+	}
 	if let Some(mc) = child.main_class {
 		base.main_class = Some(mc);
 	}
@@ -181,7 +238,7 @@ pub fn merge_profile(mut base: VersionProfile, child: VersionProfile) -> Version
 	if let Some(ma) = child.minecraft_arguments {
 		base.minecraft_arguments = Some(ma);
 	}
-	base.libraries.extend(child.libraries);
+	base.libraries = merge_libraries(base.libraries, child.libraries);
 	if let Some(assets) = child.assets {
 		base.assets = Some(assets);
 	}
@@ -191,5 +248,167 @@ pub fn merge_profile(mut base: VersionProfile, child: VersionProfile) -> Version
 	if child.downloads.is_some() {
 		base.downloads = child.downloads;
 	}
+	if child.java_version.is_some() {
+		base.java_version = child.java_version;
+	}
+	if child.jar.is_some() {
+		base.jar = child.jar;
+	}
+	if child.logging.is_some() {
+		base.logging = child.logging;
+	}
 	base
 }
+
+/// 子版本中同一 `group:artifact[:classifier]` 的库取代父版本的库，
+/// 放在父版本中第一次出现的位置，其余库保持原有顺序
+fn merge_libraries(base: Vec<Library>, child: Vec<Library>) -> Vec<Library> {
+	let child_keys: Vec<Option<String>> = child.iter().map(library_key).collect();
+	let mut child_slots: Vec<Option<Library>> = child.into_iter().map(Some).collect();
+	let mut merged = Vec::with_capacity(child_slots.len() + base.len());
+
+	for lib in base {
+		match library_key(&lib) {
+			Some(key) if child_keys.contains(&Some(key.clone())) => {
+				for (k, slot) in child_keys.iter().zip(child_slots.iter_mut()) {
+					if k.as_deref() == Some(key.as_str())
+						&& let Some(replacement) = slot.take()
+					{
+						merged.push(replacement);
+					}
+				}
+			}
+			_ => merged.push(lib),
+		}
+	}
+	merged.extend(child_slots.into_iter().flatten());
+	merged
+}
+
+/// 旧版 natives 库与同名的普通库共用坐标，需要区分开
+fn library_key(lib: &Library) -> Option<String> {
+	let key = MavenCoord::parse(&lib.name).ok()?.key();
+	Some(match lib.natives {
+		Some(_) => format!("{key}:natives"),
+		None => key,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn write_version(game_dir: &Path, id: &str, json: &str) {
+		let dir = game_dir.join("versions").join(id);
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join(format!("{id}.json")), json).unwrap();
+	}
+
+	fn names(profile: &VersionProfile) -> Vec<&str> {
+		profile.libraries.iter().map(|l| l.name.as_str()).collect()
+	}
+
+	const VANILLA: &str = r#"{
+		"id": "1.20.1",
+		"mainClass": "net.minecraft.client.main.Main",
+		"libraries": [
+			{ "name": "com.google.guava:guava:31.1-jre" },
+			{ "name": "org.ow2.asm:asm:9.3" },
+			{ "name": "org.lwjgl:lwjgl:3.3.1" },
+			{ "name": "org.lwjgl:lwjgl:3.3.1:natives-linux", "rules": [{ "action": "allow", "os": { "name": "linux" } }] },
+			{ "name": "org.apache.logging.log4j:log4j-core:2.19.0" }
+		]
+	}"#;
+
+	#[test]
+	fn test_fabric_overrides_parent_libraries() {
+		let dir = tempfile::tempdir().unwrap();
+		write_version(dir.path(), "1.20.1", VANILLA);
+		write_version(
+			dir.path(),
+			"fabric-loader-0.16.0-1.20.1",
+			r#"{
+				"id": "fabric-loader-0.16.0-1.20.1",
+				"inheritsFrom": "1.20.1",
+				"mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+				"libraries": [
+					{ "name": "org.ow2.asm:asm:9.6", "url": "https://maven.fabricmc.net/" },
+					{ "name": "net.fabricmc:intermediary:1.20.1", "url": "https://maven.fabricmc.net/" },
+					{ "name": "net.fabricmc:fabric-loader:0.16.0", "url": "https://maven.fabricmc.net/" }
+				]
+			}"#,
+		);
+
+		let profile = load_version_profile(dir.path(), "fabric-loader-0.16.0-1.20.1").unwrap();
+		assert_eq!(
+			names(&profile),
+			[
+				"com.google.guava:guava:31.1-jre",
+				"org.ow2.asm:asm:9.6",
+				"org.lwjgl:lwjgl:3.3.1",
+				"org.lwjgl:lwjgl:3.3.1:natives-linux",
+				"org.apache.logging.log4j:log4j-core:2.19.0",
+				"net.fabricmc:intermediary:1.20.1",
+				"net.fabricmc:fabric-loader:0.16.0",
+			]
+		);
+		assert_eq!(profile.jar.as_deref(), Some("1.20.1"));
+		assert_eq!(
+			profile.main_class.as_deref(),
+			Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+		);
+	}
+
+	#[test]
+	fn test_forge_overrides_keep_classifiers_apart() {
+		let dir = tempfile::tempdir().unwrap();
+		write_version(dir.path(), "1.20.1", VANILLA);
+		write_version(
+			dir.path(),
+			"1.20.1-forge-47.2.0",
+			r#"{
+				"id": "1.20.1-forge-47.2.0",
+				"inheritsFrom": "1.20.1",
+				"libraries": [
+					{ "name": "net.minecraftforge:forge:1.20.1-47.2.0:universal" },
+					{ "name": "com.google.guava:guava:32.1.2-jre" },
+					{ "name": "org.lwjgl:lwjgl:3.3.2" },
+					{ "name": "net.minecraftforge:forge:1.20.1-47.2.0:client" }
+				]
+			}"#,
+		);
+
+		let profile = load_version_profile(dir.path(), "1.20.1-forge-47.2.0").unwrap();
+		assert_eq!(
+			names(&profile),
+			[
+				"com.google.guava:guava:32.1.2-jre",
+				"org.ow2.asm:asm:9.3",
+				"org.lwjgl:lwjgl:3.3.2",
+				"org.lwjgl:lwjgl:3.3.1:natives-linux",
+				"org.apache.logging.log4j:log4j-core:2.19.0",
+				"net.minecraftforge:forge:1.20.1-47.2.0:universal",
+				"net.minecraftforge:forge:1.20.1-47.2.0:client",
+			]
+		);
+	}
+
+	#[test]
+	fn test_legacy_natives_are_not_merged_with_plain_library() {
+		let parent: VersionProfile = serde_json::from_str(
+			r#"{ "libraries": [
+				{ "name": "org.lwjgl:lwjgl:3.2.2" },
+				{ "name": "org.lwjgl:lwjgl:3.2.2", "natives": { "linux": "natives-linux" } }
+			] }"#,
+		)
+		.unwrap();
+		let child: VersionProfile =
+			serde_json::from_str(r#"{ "libraries": [{ "name": "org.lwjgl:lwjgl:3.2.3" }] }"#)
+				.unwrap();
+
+		let merged = merge_profile(parent, child);
+		assert_eq!(merged.libraries.len(), 2);
+		assert_eq!(merged.libraries[0].name, "org.lwjgl:lwjgl:3.2.3");
+		assert!(merged.libraries[1].natives.is_some());
+	}
+}
@@ -0,0 +1,137 @@
+use crate::game::process::LogLine;
+use crate::game::profile::VersionProfile;
+use regex::Regex;
+
+/// 1.13 起使用 LWJGL 3 与新版参数格式，启动日志也随之变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionFamily {
+	Legacy,
+	Modern,
+}
+
+impl VersionFamily {
+	pub fn of(profile: &VersionProfile) -> Self {
+		if profile.arguments.is_some() {
+			VersionFamily::Modern
+		} else {
+			VersionFamily::Legacy
+		}
+	}
+
+	/// 只有窗口和 OpenGL 上下文创建之后才会出现的日志
+	fn patterns(self) -> &'static [&'static str] {
+		match self {
+			VersionFamily::Modern => &[
+				r"^Backend library: LWJGL version",
+				r"^Reloading ResourceManager",
+				r"^Created: \d+x\d+x\d+ minecraft:textures/atlas",
+				r"^Sound engine started",
+				r"OpenAL initialized",
+			],
+			VersionFamily::Legacy => &[
+				r"^LWJGL Version: ",
+				r"Reloading ResourceManager",
+				r"Created: \d+x\d+ textures",
+				r"SoundSystem Starting up|Sound engine started",
+			],
+		}
+	}
+}
+
+/// 根据游戏输出判断窗口是否已经创建
+pub struct ReadinessDetector {
+	patterns: Vec<Regex>,
+}
+
+impl ReadinessDetector {
+	/// `extra` 为配置中追加的正则，无效的会被忽略
+	pub fn new(family: VersionFamily, extra: &[String]) -> Self {
+		let builtin = family.patterns().iter().map(|p| Regex::new(p).unwrap());
+		let custom = extra.iter().filter_map(|p| {
+			Regex::new(p)
+				.inspect_err(|e| tracing::warn!("Invalid ready pattern '{}': {}", p, e))
+				.ok()
+		});
+		Self {
+			patterns: builtin.chain(custom).collect(),
+		}
+	}
+
+	/// 结构化日志只匹配消息正文，纯文本行去掉 `[时间] [线程/级别]:` 前缀后匹配
+	pub fn is_ready(&self, line: &LogLine) -> bool {
+		let text = match &line.record {
+			Some(record) => record.message.as_str(),
+			None => strip_plain_prefix(&line.text),
+		};
+		self.patterns.iter().any(|re| re.is_match(text))
+	}
+}
+
+fn strip_plain_prefix(text: &str) -> &str {
+	if text.starts_with('[')
+		&& let Some(i) = text.find("]: ")
+	{
+		return &text[i + 3..];
+	}
+	text
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::game::log4j::{LogLevel, LogRecord};
+	use crate::game::process::LogStream;
+
+	fn plain(text: &str) -> LogLine {
+		LogLine {
+			stream: LogStream::Stdout,
+			text: text.into(),
+			level: LogLevel::infer(text),
+			record: None,
+		}
+	}
+
+	fn event(thread: &str, message: &str) -> LogLine {
+		let record = LogRecord {
+			level: LogLevel::Info,
+			thread: thread.into(),
+			logger: "net.minecraft.client.Minecraft".into(),
+			timestamp: 0,
+			message: message.into(),
+			throwable: None,
+		};
+		LogLine {
+			stream: LogStream::Stdout,
+			text: record.display_text(),
+			level: Some(record.level),
+			record: Some(record),
+		}
+	}
+
+	#[test]
+	fn test_modern_structured_events() {
+		let detector = ReadinessDetector::new(VersionFamily::Modern, &[]);
+		assert!(!detector.is_ready(&event("Render thread", "Setting user: Steve")));
+		assert!(detector.is_ready(&event(
+			"Render thread",
+			"Backend library: LWJGL version 3.3.3-snapshot"
+		)));
+		// 线程名中出现关键字不算
+		assert!(!detector.is_ready(&event("Sound engine started", "hello")));
+	}
+
+	#[test]
+	fn test_legacy_plain_lines_and_custom_patterns() {
+		let detector = ReadinessDetector::new(
+			VersionFamily::Legacy,
+			&["^Custom window ready$".into(), "(".into()],
+		);
+		assert!(!detector.is_ready(&plain(
+			"[12:00:00] [Client thread/INFO]: Setting user: Steve"
+		)));
+		assert!(detector.is_ready(&plain(
+			"[12:00:01] [Client thread/INFO]: LWJGL Version: 2.9.4"
+		)));
+		assert!(detector.is_ready(&plain("[12:00:02] [main/INFO]: Custom window ready")));
+	}
+}
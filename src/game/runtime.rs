@@ -0,0 +1,164 @@
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::paths;
+
+pub const RUNTIME_INDEX_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// 平台 -> 组件 -> 可用版本
+pub type RuntimeIndex = HashMap<String, HashMap<String, Vec<RuntimeEntry>>>;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeEntry {
+	pub manifest: RuntimeDownload,
+	pub version: RuntimeVersion,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeVersion {
+	pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeDownload {
+	pub sha1: String,
+	pub size: u64,
+	pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeManifest {
+	pub files: BTreeMap<String, RuntimeFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeFile {
+	File {
+		#[serde(default)]
+		executable: bool,
+		downloads: RuntimeFileDownloads,
+	},
+	Directory,
+	Link {
+		target: String,
+	},
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeFileDownloads {
+	pub raw: RuntimeDownload,
+	#[serde(default)]
+	pub lzma: Option<RuntimeDownload>,
+}
+
+/// Mojang 运行时清单中的平台名
+pub fn platform_key() -> Option<&'static str> {
+	let key = match (std::env::consts::OS, std::env::consts::ARCH) {
+		("linux", "x86_64") => "linux",
+		("linux", "x86") => "linux-i386",
+		("macos", "x86_64") => "mac-os",
+		("macos", "aarch64") => "mac-os-arm64",
+		("windows", "x86_64") => "windows-x64",
+		("windows", "x86") => "windows-x86",
+		("windows", "aarch64") => "windows-arm64",
+		_ => return None,
+	};
+	Some(key)
+}
+
+pub fn find_runtime<'a>(
+	index: &'a RuntimeIndex,
+	platform: &str,
+	component: &str,
+) -> Option<&'a RuntimeEntry> {
+	index.get(platform)?.get(component)?.first()
+}
+
+/// 启动器管理的运行时根目录，Java 发现会扫描此处
+pub fn runtimes_dir() -> Result<PathBuf> {
+	Ok(paths::data_dir()?.join("runtime"))
+}
+
+pub fn runtime_dir(component: &str) -> Result<PathBuf> {
+	Ok(runtimes_dir()?.join(component))
+}
+
+/// 把清单中的相对路径拼到安装目录下，拒绝绝对路径与 `..`
+pub fn install_path(root: &Path, relative: &str) -> Result<PathBuf> {
+	let rel = Path::new(relative);
+	if rel
+		.components()
+		.any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+	{
+		return Err(anyhow!("Invalid runtime file path: {}", relative));
+	}
+	Ok(root.join(rel))
+}
+
+/// 符号链接的目标相对于链接所在目录解析，不能是绝对路径或指向运行时目录之外
+pub fn check_link_target(relative: &str, target: &str) -> Result<()> {
+	let invalid = || anyhow!("Invalid runtime link target: {} -> {}", relative, target);
+	let mut depth = Path::new(relative).components().count().saturating_sub(1);
+	for component in Path::new(target).components() {
+		match component {
+			Component::Normal(_) => depth += 1,
+			Component::CurDir => {}
+			Component::ParentDir => depth = depth.checked_sub(1).ok_or_else(invalid)?,
+			Component::RootDir | Component::Prefix(_) => return Err(invalid()),
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_runtime_manifest() {
+		let json = r#"{
+			"files": {
+				"bin": { "type": "directory" },
+				"bin/java": {
+					"type": "file",
+					"executable": true,
+					"downloads": {
+						"lzma": { "sha1": "a", "size": 1, "url": "https://x/lzma" },
+						"raw": { "sha1": "b", "size": 2, "url": "https://x/raw" }
+					}
+				},
+				"lib/libjli.so": { "type": "link", "target": "../bin/libjli.so" }
+			}
+		}"#;
+		let manifest: RuntimeManifest = serde_json::from_str(json).unwrap();
+
+		assert!(matches!(manifest.files["bin"], RuntimeFile::Directory));
+		match &manifest.files["bin/java"] {
+			RuntimeFile::File {
+				executable,
+				downloads,
+			} => {
+				assert!(executable);
+				assert_eq!(downloads.raw.size, 2);
+				assert_eq!(downloads.lzma.as_ref().unwrap().url, "https://x/lzma");
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+		assert!(
+			matches!(&manifest.files["lib/libjli.so"], RuntimeFile::Link { target } if target == "../bin/libjli.so")
+		);
+
+		let root = Path::new("/runtime/java-runtime-gamma");
+		assert!(install_path(root, "bin/java").is_ok());
+		assert!(install_path(root, "../escape").is_err());
+		assert!(install_path(root, "/etc/passwd").is_err());
+
+		assert!(check_link_target("lib/libjli.so", "../bin/libjli.so").is_ok());
+		assert!(check_link_target("lib/libjli.so", "../../escape").is_err());
+		assert!(check_link_target("bin/java", "/usr/bin/java").is_err());
+		assert!(check_link_target("java", "../java").is_err());
+	}
+}
@@ -0,0 +1,250 @@
+use std::path::Path;
+
+/// 脱敏后脚本从该环境变量读取令牌
+pub const TOKEN_ENV: &str = "HAKO_ACCESS_TOKEN";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptFormat {
+	Shell,
+	Batch,
+}
+
+impl ScriptFormat {
+	pub fn native() -> Self {
+		if cfg!(windows) {
+			ScriptFormat::Batch
+		} else {
+			ScriptFormat::Shell
+		}
+	}
+
+	pub fn extension(self) -> &'static str {
+		match self {
+			ScriptFormat::Shell => "sh",
+			ScriptFormat::Batch => "bat",
+		}
+	}
+}
+
+/// 一次启动的完整命令行
+#[derive(Debug, Clone)]
+pub struct LaunchCommand<'a> {
+	pub title: &'a str,
+	pub java: &'a Path,
+	pub jvm_args: &'a [String],
+	pub main_class: &'a str,
+	pub game_args: &'a [String],
+	pub working_dir: &'a Path,
+}
+
+impl LaunchCommand<'_> {
+	fn args(&self) -> impl Iterator<Item = &str> {
+		self.jvm_args
+			.iter()
+			.map(String::as_str)
+			.chain(std::iter::once(self.main_class))
+			.chain(self.game_args.iter().map(String::as_str))
+	}
+}
+
+/// 生成与启动器完全相同的启动脚本。`redact` 为需要隐藏的访问令牌，
+/// 脚本中改为引用 [`TOKEN_ENV`]
+pub fn render_script(cmd: &LaunchCommand, format: ScriptFormat, redact: Option<&str>) -> String {
+	// 离线账户的令牌是占位值，无需隐藏
+	let redact = redact.filter(|t| t.len() > 1);
+	match format {
+		ScriptFormat::Shell => render_shell(cmd, redact),
+		ScriptFormat::Batch => render_batch(cmd, redact),
+	}
+}
+
+fn render_shell(cmd: &LaunchCommand, redact: Option<&str>) -> String {
+	let mut out = String::from("#!/bin/sh\n");
+	out.push_str(&format!("# Hako 导出的启动脚本：{}\n", cmd.title));
+	if redact.is_some() {
+		out.push_str(&format!(
+			": \"${{{TOKEN_ENV}:?请先设置 {TOKEN_ENV} 为账户的访问令牌}}\"\n"
+		));
+	}
+	out.push_str(&format!(
+		"cd {} || exit 1\n",
+		shell_quote(&cmd.working_dir.to_string_lossy())
+	));
+	out.push_str(&format!(
+		"exec {}",
+		shell_quote(&cmd.java.to_string_lossy())
+	));
+	for arg in cmd.args() {
+		out.push_str(" \\\n\t");
+		out.push_str(&shell_arg(arg, redact));
+	}
+	out.push('\n');
+	out
+}
+
+fn render_batch(cmd: &LaunchCommand, redact: Option<&str>) -> String {
+	let mut lines = vec![
+		"@echo off".to_string(),
+		"chcp 65001 >nul".to_string(),
+		format!("rem Hako 导出的启动脚本：{}", cmd.title),
+	];
+	if redact.is_some() {
+		lines.push(format!(
+			"if not defined {TOKEN_ENV} (echo 请先设置 {TOKEN_ENV} 为账户的访问令牌 & exit /b 1)"
+		));
+	}
+	lines.push(format!(
+		"cd /d {}",
+		batch_quote(&cmd.working_dir.to_string_lossy())
+	));
+	let mut command = batch_quote(&cmd.java.to_string_lossy());
+	for arg in cmd.args() {
+		command.push_str(" ^\r\n\t");
+		command.push_str(&batch_arg(arg, redact));
+	}
+	lines.push(command);
+	lines.join("\r\n") + "\r\n"
+}
+
+/// 引用参数，参数中的令牌替换为环境变量引用
+fn shell_arg(arg: &str, token: Option<&str>) -> String {
+	let Some(token) = token.filter(|t| arg.contains(*t)) else {
+		return shell_quote(arg);
+	};
+	arg.split(token)
+		.map(|part| {
+			if part.is_empty() {
+				String::new()
+			} else {
+				shell_quote(part)
+			}
+		})
+		.collect::<Vec<_>>()
+		.join(&format!("\"${TOKEN_ENV}\""))
+}
+
+/// 整个参数放在同一对引号中，参数中的令牌替换为环境变量引用。
+/// 分段引用的 `"a""b"` 会被 Windows 解析为带引号的参数
+fn batch_arg(arg: &str, token: Option<&str>) -> String {
+	let mut inner = match token.filter(|t| arg.contains(*t)) {
+		Some(token) => arg
+			.split(token)
+			.map(batch_escape)
+			.collect::<Vec<_>>()
+			.join(&format!("%{TOKEN_ENV}%")),
+		None => batch_escape(arg),
+	};
+	// 结尾的反斜杠会转义右引号，需要加倍
+	let trailing = inner.len() - inner.trim_end_matches('\\').len();
+	inner.push_str(&"\\".repeat(trailing));
+	format!("\"{inner}\"")
+}
+
+fn shell_quote(s: &str) -> String {
+	format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn batch_quote(s: &str) -> String {
+	batch_arg(s, None)
+}
+
+/// 转义引号内的内容：`%` 写作 `%%`，引号写作 `""`，引号前的反斜杠加倍
+fn batch_escape(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut backslashes = 0;
+	for c in s.chars() {
+		match c {
+			'\\' => {
+				backslashes += 1;
+				out.push(c);
+				continue;
+			}
+			'"' => {
+				out.push_str(&"\\".repeat(backslashes));
+				out.push_str("\"\"");
+			}
+			'%' => out.push_str("%%"),
+			_ => out.push(c),
+		}
+		backslashes = 0;
+	}
+	out
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn command<'a>(jvm: &'a [String], game: &'a [String]) -> LaunchCommand<'a> {
+		LaunchCommand {
+			title: "1.20.1",
+			java: Path::new("/opt/java/bin/java"),
+			jvm_args: jvm,
+			main_class: "net.minecraft.client.main.Main",
+			game_args: game,
+			working_dir: Path::new("/games/it's mc"),
+		}
+	}
+
+	#[test]
+	fn test_shell_script_redacts_token() {
+		let jvm = vec!["-Xmx4096M".to_string()];
+		let game = vec![
+			"--accessToken".to_string(),
+			"secret-token".into(),
+			"--session=secret-token:x".into(),
+		];
+		let script = render_script(
+			&command(&jvm, &game),
+			ScriptFormat::Shell,
+			Some("secret-token"),
+		);
+
+		assert!(script.starts_with("#!/bin/sh\n"));
+		assert!(!script.contains("secret-token"));
+		assert!(script.contains(r"cd '/games/it'\''s mc' || exit 1"));
+		assert!(script.contains(
+			"exec '/opt/java/bin/java' \\\n\t'-Xmx4096M' \\\n\t'net.minecraft.client.main.Main'"
+		));
+		assert!(script.contains("\t\"$HAKO_ACCESS_TOKEN\" \\\n"));
+		assert!(script.contains("\t'--session='\"$HAKO_ACCESS_TOKEN\"':x'\n"));
+
+		let plain = render_script(&command(&jvm, &game), ScriptFormat::Shell, None);
+		assert!(plain.contains("'secret-token'"));
+		assert!(!plain.contains(TOKEN_ENV));
+	}
+
+	#[test]
+	fn test_batch_script() {
+		let jvm = vec![
+			"-Dpercent=100%".to_string(),
+			r#"-Dquote=say "hi""#.into(),
+			r"-Ddir=C:\games\".into(),
+		];
+		let game = vec![
+			"--accessToken".to_string(),
+			"secret-token".into(),
+			"--session=secret-token:x".into(),
+		];
+		let script = render_script(
+			&command(&jvm, &game),
+			ScriptFormat::Batch,
+			Some("secret-token"),
+		);
+
+		assert!(script.starts_with("@echo off\r\n"));
+		assert!(!script.contains("secret-token"));
+		assert!(script.contains("\"-Dpercent=100%%\""));
+		assert!(script.contains(r#""-Dquote=say ""hi""""#));
+		assert!(script.contains(r#""-Ddir=C:\games\\""#));
+		assert!(script.contains("\t\"%HAKO_ACCESS_TOKEN%\" ^\r\n"));
+		// 嵌入令牌的参数仍是一整个引号字符串
+		assert!(script.contains("\t\"--session=%HAKO_ACCESS_TOKEN%:x\"\r\n"));
+		assert!(script.contains("if not defined HAKO_ACCESS_TOKEN"));
+
+		// 离线账户的占位令牌不做处理
+		let offline = vec!["--accessToken".to_string(), "0".into()];
+		let script = render_script(&command(&jvm, &offline), ScriptFormat::Batch, Some("0"));
+		assert!(!script.contains(TOKEN_ENV));
+	}
+}
@@ -1,33 +1,52 @@
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::Duration;
 
-use futures_util::TryStreamExt;
-use reqwest::header::RANGE;
+use futures_util::{StreamExt, TryStreamExt};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RANGE};
 use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
 use thiserror::Error;
 use tokio::fs::{self, OpenOptions};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
-use tokio::sync::watch;
+use tokio::sync::{Semaphore, watch};
 use tokio::time::Instant;
 use tracing::{debug, warn};
 
+use crate::net::mirror::MirrorList;
+
 const DEFAULT_RETRY: usize = 3;
+const DEFAULT_CONCURRENCY: usize = 8;
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
 const PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Clone, Debug)]
 pub enum Checksum {
+	Sha1(String),
 	Sha256(String),
 }
 
+impl Checksum {
+	pub fn matches(&self, data: &[u8]) -> bool {
+		let (digest, expected) = match self {
+			Checksum::Sha1(expected) => (hex::encode(Sha1::digest(data)), expected),
+			Checksum::Sha256(expected) => (hex::encode(Sha256::digest(data)), expected),
+		};
+		digest.eq_ignore_ascii_case(expected)
+	}
+}
+
 #[derive(Clone, Debug)]
 pub struct DownloadRequest {
 	pub url: String,
+	/// 主地址的所有下载源都失败后依次尝试，例如同一构件所在的其他 Maven 仓库
+	pub fallback_urls: Vec<String>,
 	pub dest: PathBuf,
 	pub checksum: Option<Checksum>,
+	pub size: Option<u64>,
 	pub retry: usize,
 	pub timeout: Duration,
 }
@@ -36,8 +55,10 @@ impl DownloadRequest {
 	pub fn new(url: impl Into<String>, dest: impl Into<PathBuf>) -> Self {
 		Self {
 			url: url.into(),
+			fallback_urls: Vec::new(),
 			dest: dest.into(),
 			checksum: None,
+			size: None,
 			retry: DEFAULT_RETRY,
 			timeout: DEFAULT_TIMEOUT,
 		}
@@ -47,6 +68,28 @@ impl DownloadRequest {
 		self.checksum = Some(checksum);
 		self
 	}
+
+	pub fn with_sha1(self, sha1: Option<&str>) -> Self {
+		match sha1 {
+			Some(h) => self.with_checksum(Checksum::Sha1(h.to_string())),
+			None => self,
+		}
+	}
+
+	pub fn with_fallback_urls(mut self, urls: Vec<String>) -> Self {
+		self.fallback_urls = urls;
+		self
+	}
+
+	pub fn with_size(mut self, size: Option<u64>) -> Self {
+		self.size = size;
+		self
+	}
+
+	/// 是否带有可用于校验本地文件的信息
+	pub fn is_verifiable(&self) -> bool {
+		self.checksum.is_some() || self.size.is_some()
+	}
 }
 
 #[derive(Clone, Debug)]
@@ -56,6 +99,29 @@ pub struct DownloadProgress {
 	pub speed_bps: f64,
 }
 
+#[derive(Clone, Debug)]
+pub struct BatchProgress {
+	pub downloaded: u64,
+	pub completed: usize,
+	pub total: usize,
+}
+
+/// HTTP 缓存校验信息，用于条件请求
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CacheValidators {
+	pub etag: Option<String>,
+	pub last_modified: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Fetched {
+	NotModified,
+	Modified {
+		body: String,
+		validators: CacheValidators,
+	},
+}
+
 #[derive(Error, Debug)]
 pub enum DownloadError {
 	#[error("http error: {0}")]
@@ -66,6 +132,8 @@ pub enum DownloadError {
 	UnexpectedStatus(StatusCode),
 	#[error("checksum mismatch")]
 	ChecksumMismatch,
+	#[error("size mismatch: expected {expected}, got {actual}")]
+	SizeMismatch { expected: u64, actual: u64 },
 	#[error("download cancelled")]
 	Cancelled,
 	#[error("retry exhausted after {0} attempts")]
@@ -74,15 +142,151 @@ pub enum DownloadError {
 
 pub struct DownloadClient {
 	client: Client,
+	concurrency: usize,
+	permits: Arc<Semaphore>,
+	mirrors: MirrorList,
 }
 
 impl DownloadClient {
 	pub fn new() -> Result<Self, DownloadError> {
+		Self::with_concurrency(DEFAULT_CONCURRENCY)
+	}
+
+	/// 同一个客户端上的所有下载共享 `concurrency` 个并发名额
+	pub fn with_concurrency(concurrency: usize) -> Result<Self, DownloadError> {
 		let client = Client::builder()
 			.read_timeout(Duration::from_secs(600)) // 我不信还能timeout
 			.build()?;
+		let concurrency = concurrency.max(1);
+
+		Ok(Self {
+			client,
+			concurrency,
+			permits: Arc::new(Semaphore::new(concurrency)),
+			mirrors: MirrorList::default(),
+		})
+	}
+
+	pub fn with_mirrors(mut self, mirrors: MirrorList) -> Self {
+		self.mirrors = mirrors;
+		self
+	}
+
+	/// 读取版本清单等小型文本资源，同样按下载源顺序回退
+	pub async fn fetch_text(&self, url: &str) -> Result<String, DownloadError> {
+		match self
+			.fetch_text_conditional(url, &CacheValidators::default())
+			.await?
+		{
+			Fetched::Modified { body, .. } => Ok(body),
+			Fetched::NotModified => Err(DownloadError::UnexpectedStatus(StatusCode::NOT_MODIFIED)),
+		}
+	}
+
+	/// 带 ETag / Last-Modified 的条件请求，未变化时返回 `Fetched::NotModified`
+	pub async fn fetch_text_conditional(
+		&self,
+		url: &str,
+		validators: &CacheValidators,
+	) -> Result<Fetched, DownloadError> {
+		let mut last_error = None;
+		for candidate in self.mirrors.candidates(url) {
+			match self.fetch_text_from(&candidate, validators).await {
+				Ok(fetched) => return Ok(fetched),
+				Err(e) => {
+					warn!("fetch {} failed: {}, trying next source", candidate, e);
+					last_error = Some(e);
+				}
+			}
+		}
+		Err(last_error.unwrap_or(DownloadError::RetryExhausted(0)))
+	}
+
+	async fn fetch_text_from(
+		&self,
+		url: &str,
+		validators: &CacheValidators,
+	) -> Result<Fetched, DownloadError> {
+		let mut req = self.client.get(url).timeout(DEFAULT_TIMEOUT);
+		if let Some(etag) = &validators.etag {
+			req = req.header(IF_NONE_MATCH, etag);
+		}
+		if let Some(last_modified) = &validators.last_modified {
+			req = req.header(IF_MODIFIED_SINCE, last_modified);
+		}
+
+		let resp = req.send().await?;
+		let status = resp.status();
+		if status == StatusCode::NOT_MODIFIED {
+			return Ok(Fetched::NotModified);
+		}
+		if !status.is_success() {
+			return Err(DownloadError::UnexpectedStatus(status));
+		}
 
-		Ok(Self { client })
+		let header = |name| {
+			resp.headers()
+				.get(name)
+				.and_then(|v| v.to_str().ok())
+				.map(String::from)
+		};
+		let validators = CacheValidators {
+			etag: header(ETAG),
+			last_modified: header(LAST_MODIFIED),
+		};
+		Ok(Fetched::Modified {
+			body: resp.text().await?,
+			validators,
+		})
+	}
+
+	/// 并发下载一批文件，任意一个失败即中止整批
+	pub async fn download_many<F>(
+		&self,
+		requests: Vec<DownloadRequest>,
+		on_progress: F,
+		cancel: Option<watch::Receiver<bool>>,
+	) -> Result<(), DownloadError>
+	where
+		F: Fn(BatchProgress) + Sync,
+	{
+		let total = requests.len();
+		let downloaded = AtomicU64::new(0);
+		let completed = AtomicUsize::new(0);
+		let (downloaded, completed, on_progress) = (&downloaded, &completed, &on_progress);
+
+		let mut results = futures_util::stream::iter(requests.into_iter().map(|request| {
+			let cancel = cancel.clone();
+			async move {
+				let mut last = 0u64;
+				self.download(
+					request,
+					|p| {
+						let delta = p.downloaded.saturating_sub(last);
+						last = last.max(p.downloaded);
+						on_progress(BatchProgress {
+							downloaded: downloaded.fetch_add(delta, Ordering::Relaxed) + delta,
+							completed: completed.load(Ordering::Relaxed),
+							total,
+						});
+					},
+					cancel,
+				)
+				.await?;
+				on_progress(BatchProgress {
+					downloaded: downloaded.load(Ordering::Relaxed),
+					completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+					total,
+				});
+				Ok::<_, DownloadError>(())
+			}
+		}))
+		.buffer_unordered(self.concurrency);
+
+		while let Some(result) = results.next().await {
+			result?;
+		}
+		Ok(())
 	}
 
 	pub async fn download<F>(
@@ -94,38 +298,81 @@ impl DownloadClient {
 	where
 		F: FnMut(DownloadProgress),
 	{
+		let _permit = self
+			.permits
+			.acquire()
+			.await
+			.map_err(|_| DownloadError::Cancelled)?;
+
 		if let Some(parent) = request.dest.parent() {
 			fs::create_dir_all(parent).await?;
 		}
 
 		let temp_path = request.dest.with_extension("hako.part");
 
-		debug!(
-			"download start url={} dest={} resume_from={}",
-			request.url,
-			request.dest.display(),
-			fs::metadata(&temp_path).await.map(|m| m.len()).unwrap_or(0)
-		);
-
-		if let Some(checksum) = &request.checksum {
-			if file_matches_checksum(&request.dest, checksum)
+		if request.is_verifiable()
+			&& verify_file(&request.dest, request.checksum.as_ref(), request.size)
 				.await
 				.unwrap_or(false)
+		{
+			let size = fs::metadata(&request.dest)
+				.await
+				.map(|m| m.len())
+				.unwrap_or(0);
+			on_progress(DownloadProgress {
+				downloaded: size,
+				total: Some(size),
+				speed_bps: 0.0,
+			});
+			return Ok(());
+		}
+
+		let mut last_error = None;
+		let urls = std::iter::once(&request.url)
+			.chain(&request.fallback_urls)
+			.flat_map(|url| self.mirrors.candidates(url));
+		for url in urls {
+			let attempt = DownloadRequest {
+				url,
+				..request.clone()
+			};
+			match self
+				.download_from(&attempt, &temp_path, &mut on_progress, cancel.clone())
+				.await
 			{
-				let size = fs::metadata(&request.dest)
-					.await
-					.map(|m| m.len())
-					.unwrap_or(0);
-				on_progress(DownloadProgress {
-					downloaded: size,
-					total: Some(size),
-					speed_bps: 0.0,
-				});
-				return Ok(());
+				Ok(()) => return Ok(()),
+				Err(DownloadError::Cancelled) => return Err(DownloadError::Cancelled),
+				Err(e) => {
+					warn!("download {} failed: {}, trying next source", attempt.url, e);
+					last_error = Some(e);
+				}
 			}
 		}
+		Err(last_error.unwrap_or(DownloadError::RetryExhausted(request.retry)))
+	}
 
-		let mut start_from = fs::metadata(&temp_path).await.map(|m| m.len()).unwrap_or(0);
+	async fn download_from<F>(
+		&self,
+		request: &DownloadRequest,
+		temp_path: &Path,
+		on_progress: &mut F,
+		cancel: Option<watch::Receiver<bool>>,
+	) -> Result<(), DownloadError>
+	where
+		F: FnMut(DownloadProgress),
+	{
+		let mut start_from = fs::metadata(temp_path).await.map(|m| m.len()).unwrap_or(0);
+		debug!(
+			"download start url={} dest={} resume_from={}",
+			request.url,
+			request.dest.display(),
+			start_from
+		);
+		if request.size.is_some_and(|size| start_from > size) {
+			// 残留的临时文件比目标还大，不可能续传
+			fs::remove_file(temp_path).await?;
+			start_from = 0;
+		}
 
 		let downloaded = Arc::new(AtomicU64::new(start_from));
 		let mut last_instant = Instant::now();
@@ -133,14 +380,14 @@ impl DownloadClient {
 
 		let download_result = self
 			.download_single(
-				&request,
+				request,
 				&mut start_from,
-				&temp_path,
+				temp_path,
 				&downloaded,
 				&mut last_instant,
 				&mut last_downloaded,
-				&mut on_progress,
-				cancel.clone(),
+				on_progress,
+				cancel,
 			)
 			.await;
 
@@ -153,16 +400,23 @@ impl DownloadClient {
 
 		match download_result {
 			Ok(_) => {
-				if let Some(checksum) = &request.checksum {
-					if !file_matches_checksum(&temp_path, checksum)
+				if let Some(expected) = request.size {
+					let actual = fs::metadata(temp_path).await?.len();
+					if actual != expected {
+						let _ = fs::remove_file(temp_path).await;
+						return Err(DownloadError::SizeMismatch { expected, actual });
+					}
+				}
+				if request.checksum.is_some()
+					&& !verify_file(temp_path, request.checksum.as_ref(), None)
 						.await
 						.unwrap_or(false)
-					{
-						return Err(DownloadError::ChecksumMismatch);
-					}
+				{
+					let _ = fs::remove_file(temp_path).await;
+					return Err(DownloadError::ChecksumMismatch);
 				}
 
-				fs::rename(&temp_path, &request.dest).await?;
+				fs::rename(temp_path, &request.dest).await?;
 				Ok(())
 			}
 			Err(e) => Err(e),
@@ -315,30 +569,34 @@ impl DownloadClient {
 	}
 }
 
-async fn file_matches_checksum(path: &Path, checksum: &Checksum) -> Result<bool, std::io::Error> {
-	if !path.exists() {
+pub async fn verify_file(
+	path: &Path,
+	checksum: Option<&Checksum>,
+	size: Option<u64>,
+) -> Result<bool, std::io::Error> {
+	let Ok(metadata) = fs::metadata(path).await else {
+		return Ok(false);
+	};
+	if size.is_some_and(|size| size != metadata.len()) {
 		return Ok(false);
 	}
 
-	match checksum {
-		Checksum::Sha256(expected) => {
-			let path = path.to_owned();
-			let expected = expected.clone();
-			let digest = tokio::task::spawn_blocking(move || -> Result<String, std::io::Error> {
-				let data = std::fs::read(&path)?;
-				let hash = hex::encode(Sha256::digest(data));
-				Ok(hash)
-			})
-			.await
-			.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))??;
-			Ok(digest.eq_ignore_ascii_case(&expected))
-		}
-	}
+	let Some(checksum) = checksum.cloned() else {
+		return Ok(true);
+	};
+	let path = path.to_owned();
+	tokio::task::spawn_blocking(move || -> Result<bool, std::io::Error> {
+		let data = std::fs::read(&path)?;
+		Ok(checksum.matches(&data))
+	})
+	.await
+	.map_err(std::io::Error::other)?
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::net::mirror::{DownloadSource, RewriteRule};
 	use tokio::io::{AsyncReadExt, AsyncWriteExt};
 	use tokio::net::TcpListener;
 
@@ -463,4 +721,131 @@ mod tests {
 
 		server_handle.abort();
 	}
+
+	#[tokio::test]
+	async fn test_sha1_mismatch_discards_file() {
+		let data = b"corrupted payload".to_vec();
+		let (url, server_handle) = start_test_server(data).await;
+
+		let client = DownloadClient::new().unwrap();
+		let dir = tempfile::tempdir().unwrap();
+		let dest = dir.path().join("bad.jar");
+
+		let err = client
+			.download(
+				DownloadRequest::new(format!("{}/bad.jar", url), &dest)
+					.with_sha1(Some("da39a3ee5e6b4b0d3255bfef95601890afd80709")),
+				|_p| {},
+				None,
+			)
+			.await
+			.unwrap_err();
+
+		assert!(matches!(err, DownloadError::ChecksumMismatch));
+		assert!(!dest.exists());
+		assert!(!dest.with_extension("hako.part").exists());
+
+		server_handle.abort();
+	}
+
+	#[tokio::test]
+	async fn test_verified_file_is_skipped_and_truncated_is_refetched() {
+		let data = b"minecraft client jar".to_vec();
+		let sha1 = hex::encode(Sha1::digest(&data));
+
+		let dir = tempfile::tempdir().unwrap();
+		let dest = dir.path().join("client.jar");
+		tokio::fs::write(&dest, &data).await.unwrap();
+
+		// 本地文件已通过校验，不应访问网络
+		let client = DownloadClient::new().unwrap();
+		client
+			.download(
+				DownloadRequest::new("http://127.0.0.1:1/client.jar", &dest)
+					.with_sha1(Some(&sha1))
+					.with_size(Some(data.len() as u64)),
+				|_p| {},
+				None,
+			)
+			.await
+			.unwrap();
+
+		tokio::fs::write(&dest, &data[..5]).await.unwrap();
+		let (url, server_handle) = start_test_server(data.clone()).await;
+		client
+			.download(
+				DownloadRequest::new(format!("{}/client.jar", url), &dest)
+					.with_sha1(Some(&sha1))
+					.with_size(Some(data.len() as u64)),
+				|_p| {},
+				None,
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(tokio::fs::read(&dest).await.unwrap(), data);
+
+		server_handle.abort();
+	}
+
+	#[tokio::test]
+	async fn test_download_many_reports_batch_progress() {
+		let data = b"asset object".to_vec();
+		let (url, server_handle) = start_test_server(data.clone()).await;
+
+		let client = DownloadClient::with_concurrency(2).unwrap();
+		let dir = tempfile::tempdir().unwrap();
+		let requests: Vec<_> = (0..6)
+			.map(|i| {
+				DownloadRequest::new(format!("{}/{}", url, i), dir.path().join(i.to_string()))
+					.with_size(Some(data.len() as u64))
+			})
+			.collect();
+
+		let last = std::sync::Mutex::new(None);
+		client
+			.download_many(requests, |p| *last.lock().unwrap() = Some(p), None)
+			.await
+			.unwrap();
+
+		let last = last.into_inner().unwrap().unwrap();
+		assert_eq!(last.completed, 6);
+		assert_eq!(last.total, 6);
+		assert_eq!(last.downloaded, 6 * data.len() as u64);
+		for i in 0..6 {
+			assert_eq!(
+				tokio::fs::read(dir.path().join(i.to_string()))
+					.await
+					.unwrap(),
+				data
+			);
+		}
+
+		server_handle.abort();
+	}
+
+	#[tokio::test]
+	async fn test_falls_back_to_next_source() {
+		let data = b"served by official".to_vec();
+		let (url, server_handle) = start_test_server(data.clone()).await;
+
+		// 首选镜像不可达，应回退到官方地址
+		let mirror = DownloadSource::Custom {
+			base_url: "http://127.0.0.1:1".into(),
+			rules: vec![RewriteRule::new(url.clone(), "http://127.0.0.1:1")],
+		};
+		let client = DownloadClient::new()
+			.unwrap()
+			.with_mirrors(MirrorList::new(mirror, true));
+		let dir = tempfile::tempdir().unwrap();
+		let dest = dir.path().join("fallback.bin");
+
+		let mut request = DownloadRequest::new(format!("{}/fallback.bin", url), &dest);
+		request.retry = 0;
+		client.download(request, |_p| {}, None).await.unwrap();
+
+		assert_eq!(tokio::fs::read(&dest).await.unwrap(), data);
+
+		server_handle.abort();
+	}
 }
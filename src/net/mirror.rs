@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+const BMCLAPI_BASE: &str = "https://bmclapi2.bangbang93.com";
+
+/// BMCLAPI 的目录结构：官方地址前缀 -> 镜像站下的路径
+const BMCLAPI_LAYOUT: &[(&str, &str)] = &[
+	("https://piston-meta.mojang.com", ""),
+	("https://launchermeta.mojang.com", ""),
+	("https://launcher.mojang.com", ""),
+	("https://piston-data.mojang.com", ""),
+	("https://resources.download.minecraft.net", "/assets"),
+	("https://libraries.minecraft.net", "/maven"),
+	("https://maven.minecraftforge.net", "/maven"),
+	("https://maven.neoforged.net/releases", "/maven"),
+	("https://maven.fabricmc.net", "/maven"),
+	("https://meta.fabricmc.net", "/fabric-meta"),
+	(
+		"https://authlib-injector.yushi.moe",
+		"/mirrors/authlib-injector",
+	),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewriteRule {
+	pub from: String,
+	pub to: String,
+}
+
+impl RewriteRule {
+	pub fn new(from: impl Into<String>, to: impl Into<String>) -> Self {
+		Self {
+			from: from.into(),
+			to: to.into(),
+		}
+	}
+
+	fn apply(&self, url: &str) -> Option<String> {
+		let rest = url.strip_prefix(self.from.as_str())?;
+		// 只在路径边界上匹配，避免 example.com 命中 example.com.cn
+		if !(rest.is_empty() || rest.starts_with('/') || self.from.ends_with('/')) {
+			return None;
+		}
+		Some(format!("{}{}", self.to, rest))
+	}
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DownloadSource {
+	#[default]
+	Official,
+	Bmclapi,
+	/// 与 BMCLAPI 目录结构相同的自建镜像，`rules` 优先于默认结构
+	Custom {
+		base_url: String,
+		#[serde(default)]
+		rules: Vec<RewriteRule>,
+	},
+}
+
+impl DownloadSource {
+	pub fn name(&self) -> String {
+		match self {
+			Self::Official => "官方".into(),
+			Self::Bmclapi => "BMCLAPI".into(),
+			Self::Custom { base_url, .. } => base_url.clone(),
+		}
+	}
+
+	pub fn rules(&self) -> Vec<RewriteRule> {
+		match self {
+			Self::Official => Vec::new(),
+			Self::Bmclapi => bmclapi_layout(BMCLAPI_BASE),
+			Self::Custom { base_url, rules } => {
+				let mut all = rules.clone();
+				all.extend(bmclapi_layout(base_url.trim_end_matches('/')));
+				all
+			}
+		}
+	}
+
+	/// 官方源原样返回；镜像源没有对应规则时返回 None
+	pub fn rewrite(&self, url: &str) -> Option<String> {
+		if *self == Self::Official {
+			return Some(url.to_string());
+		}
+		self.rules().iter().find_map(|r| r.apply(url))
+	}
+}
+
+fn bmclapi_layout(base: &str) -> Vec<RewriteRule> {
+	BMCLAPI_LAYOUT
+		.iter()
+		.map(|(from, path)| RewriteRule::new(*from, format!("{base}{path}")))
+		.collect()
+}
+
+/// 按优先级排列的下载源，请求失败时依次尝试下一个
+#[derive(Debug, Clone)]
+pub struct MirrorList {
+	sources: Vec<DownloadSource>,
+}
+
+impl MirrorList {
+	pub fn new(preferred: DownloadSource, fallback: bool) -> Self {
+		let mut sources = vec![preferred];
+		if fallback {
+			for builtin in [DownloadSource::Official, DownloadSource::Bmclapi] {
+				if !sources.contains(&builtin) {
+					sources.push(builtin);
+				}
+			}
+		}
+		Self { sources }
+	}
+
+	pub fn candidates(&self, url: &str) -> Vec<String> {
+		let mut out: Vec<String> = Vec::new();
+		for candidate in self.sources.iter().filter_map(|s| s.rewrite(url)) {
+			if !out.contains(&candidate) {
+				out.push(candidate);
+			}
+		}
+		// 所有源都无法改写（例如第三方地址）时仍使用原地址
+		if out.is_empty() {
+			out.push(url.to_string());
+		}
+		out
+	}
+}
+
+impl Default for MirrorList {
+	fn default() -> Self {
+		Self::new(DownloadSource::Official, false)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_bmclapi_rewrite() {
+		let source = DownloadSource::Bmclapi;
+		assert_eq!(
+			source
+				.rewrite("https://resources.download.minecraft.net/ab/abcdef")
+				.unwrap(),
+			"https://bmclapi2.bangbang93.com/assets/ab/abcdef"
+		);
+		assert_eq!(
+			source
+				.rewrite(
+					"https://libraries.minecraft.net/com/mojang/brigadier/1.0.18/brigadier-1.0.18.jar"
+				)
+				.unwrap(),
+			"https://bmclapi2.bangbang93.com/maven/com/mojang/brigadier/1.0.18/brigadier-1.0.18.jar"
+		);
+		assert!(source.rewrite("https://example.com/file.jar").is_none());
+		assert!(
+			source
+				.rewrite("https://libraries.minecraft.net.evil/x.jar")
+				.is_none()
+		);
+	}
+
+	#[test]
+	fn test_custom_source_and_fallback_order() {
+		let custom = DownloadSource::Custom {
+			base_url: "https://mirror.example.com/".into(),
+			rules: vec![RewriteRule::new(
+				"https://piston-meta.mojang.com",
+				"https://meta.example.com",
+			)],
+		};
+		let mirrors = MirrorList::new(custom, true);
+		let url = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+		assert_eq!(
+			mirrors.candidates(url),
+			vec![
+				"https://meta.example.com/mc/game/version_manifest_v2.json".to_string(),
+				url.to_string(),
+				"https://bmclapi2.bangbang93.com/mc/game/version_manifest_v2.json".to_string(),
+			]
+		);
+		assert_eq!(
+			mirrors.candidates("https://example.com/a.jar"),
+			vec!["https://example.com/a.jar".to_string()]
+		);
+	}
+}
@@ -1 +1,2 @@
 pub mod download;
+pub mod mirror;
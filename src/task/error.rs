@@ -14,6 +14,9 @@ pub enum TaskError {
 	#[error("Lock conflict: {0}")]
 	LockConflict(String),
 
+	#[error("Java {0} is required but no matching installation was found")]
+	JavaNotFound(u32),
+
 	#[error("Invalid task state")]
 	InvalidState,
 }
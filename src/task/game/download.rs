@@ -1,70 +1,175 @@
+use crate::core::state::AppState;
 use crate::game::args::{Features, current_arch, current_os_key, rule_allows};
-use crate::game::profile::{Library, VersionProfile, load_version_profile};
-use crate::net::download::{DownloadClient, DownloadRequest};
+use crate::game::classpath::native_classifier;
+use crate::game::log4j::log_config_path;
+use crate::game::manifest::{ManifestCache, ManifestVersion};
+use crate::game::maven::MavenCoord;
+use crate::game::profile::{Artifact, Library, VersionProfile, load_version_profile};
+use crate::net::download::{BatchProgress, DownloadClient, DownloadRequest};
+use crate::net::mirror::MirrorList;
 use crate::task::error::{TaskError, TaskResult};
 use crate::task::lock::LockKey;
 use crate::task::main_task::{ConcurrentTask, TaskContext, TaskType};
 use crate::task::sub_task::{SubTask, SubTaskChain, SubTaskContext};
 use anyhow::Context;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::sync::{OnceCell, RwLock, watch};
 
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug, Default)]
+pub struct PhaseProgress {
+	pub label: String,
+	pub completed: usize,
+	pub total: usize,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct DownloadProgressState {
 	pub message: String,
 	pub downloaded: u64,
 	pub total: Option<u64>,
 	pub speed_bps: f64,
+	pub files_completed: usize,
+	pub files_total: usize,
+	pub phases: Vec<PhaseProgress>,
 	pub finished: bool,
 }
 
 pub type ProgressRef = Arc<RwLock<DownloadProgressState>>;
 
+#[derive(Clone, Copy, Debug)]
+enum Phase {
+	ClientJar,
+	Libraries,
+	Assets,
+}
+
+impl Phase {
+	const ALL: [Phase; 3] = [Phase::ClientJar, Phase::Libraries, Phase::Assets];
+
+	fn label(self) -> &'static str {
+		match self {
+			Phase::ClientJar => "客户端",
+			Phase::Libraries => "依赖库",
+			Phase::Assets => "资源文件",
+		}
+	}
+}
+
+/// 单个阶段的计数，由各阶段的下载回调并发更新
+#[derive(Default)]
+struct PhaseCounter {
+	downloaded: AtomicU64,
+	completed: AtomicUsize,
+	total_bytes: AtomicU64,
+	total_files: AtomicUsize,
+}
+
+#[derive(Default)]
+struct DownloadPlan {
+	client_jar: Vec<DownloadRequest>,
+	libraries: Vec<DownloadRequest>,
+	assets: Vec<DownloadRequest>,
+}
+
+impl DownloadPlan {
+	fn requests(&self, phase: Phase) -> &[DownloadRequest] {
+		match phase {
+			Phase::ClientJar => &self.client_jar,
+			Phase::Libraries => &self.libraries,
+			Phase::Assets => &self.assets,
+		}
+	}
+}
+
 struct DownloadContext {
 	client: DownloadClient,
 	game_dir: PathBuf,
 	version_id: String,
+	/// versions 下的实例目录名，未指定时与版本号相同
+	instance: String,
 	progress: Option<ProgressRef>,
 	profile: OnceCell<VersionProfile>,
+	plan: OnceCell<DownloadPlan>,
+	counters: [PhaseCounter; 3],
 }
 
 impl DownloadContext {
 	fn new(
 		game_dir: PathBuf,
 		version_id: String,
+		instance: String,
 		progress: Option<ProgressRef>,
 	) -> TaskResult<Self> {
 		Ok(Self {
-			client: DownloadClient::new().map_err(|e| TaskError::Failed(e.to_string()))?,
+			client: configured_client()?,
 			game_dir,
 			version_id,
+			instance,
 			progress,
 			profile: OnceCell::new(),
+			plan: OnceCell::new(),
+			counters: Default::default(),
 		})
 	}
 
-	async fn set_progress(
-		&self,
-		message: &str,
-		downloaded: u64,
-		total: Option<u64>,
-		speed: f64,
-		finished: bool,
-	) {
+	async fn set_message(&self, message: &str) {
 		if let Some(p) = &self.progress {
-			let mut guard = p.write().await;
-			guard.message = message.to_string();
-			guard.downloaded = downloaded;
-			guard.total = total;
-			guard.speed_bps = speed;
-			guard.finished = finished;
+			p.write().await.message = message.to_string();
 		}
 	}
 
+	fn counter(&self, phase: Phase) -> &PhaseCounter {
+		&self.counters[phase as usize]
+	}
+
+	fn downloaded(&self) -> u64 {
+		self.counters
+			.iter()
+			.map(|c| c.downloaded.load(Ordering::Relaxed))
+			.sum()
+	}
+
+	/// 汇总各阶段计数写入共享进度
+	async fn publish_progress(&self, speed_bps: f64, finished: bool) {
+		let Some(p) = &self.progress else {
+			return;
+		};
+
+		let phases: Vec<_> = Phase::ALL
+			.iter()
+			.map(|&phase| {
+				let c = self.counter(phase);
+				PhaseProgress {
+					label: phase.label().to_string(),
+					completed: c.completed.load(Ordering::Relaxed),
+					total: c.total_files.load(Ordering::Relaxed),
+				}
+			})
+			.collect();
+		let total: u64 = self
+			.counters
+			.iter()
+			.map(|c| c.total_bytes.load(Ordering::Relaxed))
+			.sum();
+
+		let mut guard = p.write().await;
+		guard.downloaded = self.downloaded();
+		guard.total = (total > 0).then_some(total);
+		guard.speed_bps = speed_bps;
+		guard.files_completed = phases.iter().map(|p| p.completed).sum();
+		guard.files_total = phases.iter().map(|p| p.total).sum();
+		guard.phases = phases;
+		guard.finished = finished;
+	}
+
 	fn profile(&self) -> Option<&VersionProfile> {
 		self.profile.get()
 	}
@@ -73,9 +178,17 @@ impl DownloadContext {
 pub struct DownloadGameTask {
 	pub cluster_path: PathBuf,
 	pub version: String,
+	/// 自定义实例名，允许同一版本安装多份
+	pub name: Option<String>,
 	pub progress: Option<ProgressRef>,
 }
 
+impl DownloadGameTask {
+	fn instance_name(&self) -> &str {
+		self.name.as_deref().unwrap_or(&self.version)
+	}
+}
+
 impl TaskType for DownloadGameTask {
 	const TYPE_NAME: &'static str = "download_game";
 }
@@ -85,7 +198,7 @@ impl ConcurrentTask for DownloadGameTask {
 	type Output = ();
 
 	fn locks(&self) -> Vec<LockKey> {
-		vec![LockKey::resource("download_game", &self.version)]
+		vec![LockKey::resource("download_game", self.instance_name())]
 	}
 
 	fn max_concurrent(&self) -> Option<usize> {
@@ -96,36 +209,48 @@ impl ConcurrentTask for DownloadGameTask {
 		let shared = Arc::new(DownloadContext::new(
 			self.cluster_path.clone(),
 			self.version.clone(),
+			self.instance_name().to_string(),
 			self.progress.clone(),
 		)?);
 
 		let mut chain = SubTaskChain::new();
 		chain.add(EnsureProfileTask(Arc::clone(&shared)));
+		chain.add(PlanFilesTask(Arc::clone(&shared)));
 		chain.add_parallel(
-			vec![
-				Arc::new(ClientJarTask(Arc::clone(&shared))) as Arc<dyn SubTask>,
-				Arc::new(CoreLibTask(Arc::clone(&shared))) as Arc<dyn SubTask>,
-				Arc::new(AssetsTask(Arc::clone(&shared))) as Arc<dyn SubTask>,
-			],
+			Phase::ALL.map(|phase| {
+				Arc::new(DownloadPhaseTask(Arc::clone(&shared), phase)) as Arc<dyn SubTask>
+			}),
 			None,
 		);
 
+		let reporter = tokio::spawn(report_progress(Arc::clone(&shared)));
 		let sub_ctx = SubTaskContext::new(ctx.cancelled_receiver());
-		chain.execute(&sub_ctx).await?;
+		let result = chain.execute(&sub_ctx).await;
+		reporter.abort();
+		result?;
 
 		shared
-			.set_progress(
-				&format!("{} 下载完成", shared.version_id),
-				0,
-				None,
-				0.0,
-				true,
-			)
+			.set_message(&format!("{} 下载完成", shared.instance))
 			.await;
+		shared.publish_progress(0.0, true).await;
 		Ok(())
 	}
 }
 
+async fn report_progress(shared: Arc<DownloadContext>) {
+	let mut last_instant = Instant::now();
+	let mut last_downloaded = 0;
+	loop {
+		tokio::time::sleep(PROGRESS_INTERVAL).await;
+		let downloaded = shared.downloaded();
+		let elapsed = last_instant.elapsed().as_secs_f64();
+		let speed = downloaded.saturating_sub(last_downloaded) as f64 / elapsed;
+		shared.publish_progress(speed, false).await;
+		last_instant = Instant::now();
+		last_downloaded = downloaded;
+	}
+}
+
 struct EnsureProfileTask(Arc<DownloadContext>);
 
 #[async_trait::async_trait]
@@ -139,18 +264,12 @@ impl SubTask for EnsureProfileTask {
 		let version_json = s
 			.game_dir
 			.join("versions")
-			.join(&s.version_id)
-			.join(format!("{}.json", s.version_id));
+			.join(&s.instance)
+			.join(format!("{}.json", s.instance));
 
 		if !version_json.exists() {
-			s.set_progress(
-				&format!("下载版本元数据 {}", s.version_id),
-				0,
-				None,
-				0.0,
-				false,
-			)
-			.await;
+			s.set_message(&format!("下载版本元数据 {}", s.version_id))
+				.await;
 
 			if let Some(dir) = version_json.parent() {
 				fs::create_dir_all(dir)
@@ -158,168 +277,231 @@ impl SubTask for EnsureProfileTask {
 					.map_err(|e| TaskError::Failed(e.to_string()))?;
 			}
 
-			let meta_url = resolve_version_url(&s.version_id).await?;
+			let version = resolve_version(&s.client, &s.version_id).await?;
 			s.client
 				.download(
-					DownloadRequest::new(meta_url, &version_json),
+					DownloadRequest::new(version.url, &version_json)
+						.with_sha1(version.sha1.as_deref()),
 					|_| {},
 					Some(ctx.cancelled.clone()),
 				)
 				.await
 				.map_err(|e| TaskError::Failed(e.to_string()))?;
+
+			if s.instance != s.version_id {
+				rename_version_json(&version_json, &s.instance)
+					.await
+					.map_err(|e| TaskError::Failed(e.to_string()))?;
+			}
 		}
 
-		let profile = load_version_profile(&s.game_dir, &s.version_id)
+		let profile = load_version_profile(&s.game_dir, &s.instance)
 			.map_err(|e| TaskError::Failed(e.to_string()))?;
 		let _ = s.profile.set(profile);
 		Ok(())
 	}
 }
 
-struct ClientJarTask(Arc<DownloadContext>);
+/// 在开始下载前确定所有待下载文件，以便预先得到文件数与总字节数
+struct PlanFilesTask(Arc<DownloadContext>);
 
 #[async_trait::async_trait]
-impl SubTask for ClientJarTask {
+impl SubTask for PlanFilesTask {
 	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
 		let s = &self.0;
-		let profile = s
-			.profile()
-			.ok_or_else(|| TaskError::Failed("profile missing".into()))?;
-
-		let Some(client_dl) = profile.downloads.as_ref().and_then(|d| d.client.as_ref()) else {
-			return Ok(());
-		};
-		let Some(url) = &client_dl.url else {
-			return Ok(());
-		};
-
-		let dest = s
-			.game_dir
-			.join("versions")
-			.join(&s.version_id)
-			.join(format!("{}.jar", s.version_id));
-
-		if dest.exists() {
+		if s.plan.get().is_some() {
 			return Ok(());
 		}
-
-		check_cancel(&ctx.cancelled)?;
-		// let version_id = s.version_id.clone();
-		s.client
-			.download(
-				DownloadRequest::new(url.clone(), dest),
-				|_| { /* 进度回调 */ },
-				Some(ctx.cancelled.clone()),
-			)
-			.await
-			.map_err(|e| TaskError::Failed(e.to_string()))
-	}
-}
-
-struct CoreLibTask(Arc<DownloadContext>);
-
-#[async_trait::async_trait]
-impl SubTask for CoreLibTask {
-	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
-		let s = &self.0;
 		let profile = s
 			.profile()
 			.ok_or_else(|| TaskError::Failed("profile missing".into()))?;
 
-		let features = Features::default();
-		let os_key = current_os_key();
-		let arch = current_arch();
+		s.set_message(&format!("下载 {}", s.instance)).await;
 
-		let requests: Vec<_> = profile
-			.libraries
-			.iter()
-			.filter(|lib| rule_allows(lib.rules.as_ref(), os_key, arch, &features))
-			.filter_map(|lib| library_request(&s.game_dir, lib, os_key))
-			.filter(|req| !req.dest.exists())
-			.collect();
+		let mut plan = DownloadPlan {
+			client_jar: client_jar_request(&s.game_dir, &s.instance, profile)
+				.into_iter()
+				.chain(log_config_request(&s.game_dir, profile))
+				.filter(needs_download)
+				.collect(),
+			libraries: library_requests(&s.game_dir, profile, &configured_repositories()),
+			assets: Vec::new(),
+		};
 
-		for req in requests {
+		let assets_id = profile.assets.as_deref().unwrap_or(&s.version_id);
+		let assets_dir = s.game_dir.join("assets");
+		let index_path = assets_dir.join("indexes").join(format!("{assets_id}.json"));
+
+		if let Some(request) = asset_index_request(profile, &index_path).filter(needs_download) {
 			check_cancel(&ctx.cancelled)?;
 			s.client
-				.download(req, |_| {}, Some(ctx.cancelled.clone()))
+				.download(request, |_| {}, Some(ctx.cancelled.clone()))
 				.await
 				.map_err(|e| TaskError::Failed(e.to_string()))?;
 		}
+
+		let mut assets_total = None;
+		if index_path.exists() {
+			let index: AssetIndex = {
+				let content = fs::read_to_string(&index_path)
+					.await
+					.context("read asset index")
+					.map_err(|e| TaskError::Failed(e.to_string()))?;
+				serde_json::from_str(&content)
+					.context("parse asset index")
+					.map_err(|e| TaskError::Failed(e.to_string()))?
+			};
+			plan.assets = asset_requests(&assets_dir, &index);
+			assets_total = profile.asset_index.as_ref().and_then(|i| i.total_size);
+		}
+
+		for phase in Phase::ALL {
+			let requests = plan.requests(phase);
+			let bytes = match (phase, total_size(requests)) {
+				(Phase::Assets, 0) => assets_total.unwrap_or(0),
+				(_, bytes) => bytes,
+			};
+			let counter = s.counter(phase);
+			counter.total_files.store(requests.len(), Ordering::Relaxed);
+			counter.total_bytes.store(bytes, Ordering::Relaxed);
+		}
+
+		let _ = s.plan.set(plan);
 		Ok(())
 	}
 }
 
-struct AssetsTask(Arc<DownloadContext>);
+struct DownloadPhaseTask(Arc<DownloadContext>, Phase);
 
 #[async_trait::async_trait]
-impl SubTask for AssetsTask {
+impl SubTask for DownloadPhaseTask {
 	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
-		let s = &self.0;
-		let profile = s
-			.profile()
-			.ok_or_else(|| TaskError::Failed("profile missing".into()))?;
+		let (s, phase) = (&self.0, self.1);
+		let requests = s
+			.plan
+			.get()
+			.ok_or_else(|| TaskError::Failed("download plan missing".into()))?
+			.requests(phase)
+			.to_vec();
+
+		let counter = s.counter(phase);
+		check_cancel(&ctx.cancelled)?;
+		s.client
+			.download_many(
+				requests,
+				|p: BatchProgress| {
+					counter.downloaded.store(p.downloaded, Ordering::Relaxed);
+					counter.completed.store(p.completed, Ordering::Relaxed);
+				},
+				Some(ctx.cancelled.clone()),
+			)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))
+	}
+}
 
-		let assets_id = profile.assets.as_deref().unwrap_or(&s.version_id);
-		let assets_dir = s.game_dir.join("assets");
-		let index_path = assets_dir.join("indexes").join(format!("{assets_id}.json"));
+fn client_jar_request(
+	game_dir: &Path,
+	version_id: &str,
+	profile: &VersionProfile,
+) -> Option<DownloadRequest> {
+	let client_dl = profile.downloads.as_ref()?.client.as_ref()?;
+	let url = client_dl.url.as_ref()?;
+	let jar = profile.jar.as_deref().unwrap_or(version_id);
+	let dest = game_dir
+		.join("versions")
+		.join(jar)
+		.join(format!("{jar}.jar"));
+
+	Some(
+		DownloadRequest::new(url.clone(), dest)
+			.with_sha1(client_dl.sha1.as_deref())
+			.with_size(client_dl.size),
+	)
+}
 
-		if !index_path.exists() {
-			if let Some(url) = profile.asset_index.as_ref().and_then(|i| i.url.as_ref()) {
-				check_cancel(&ctx.cancelled)?;
-				s.client
-					.download(
-						DownloadRequest::new(url.clone(), index_path.clone()),
-						|_| {},
-						Some(ctx.cancelled.clone()),
-					)
-					.await
-					.map_err(|e| TaskError::Failed(e.to_string()))?;
-			} else {
-				return Ok(());
-			}
-		}
+pub(crate) fn log_config_request(
+	game_dir: &Path,
+	profile: &VersionProfile,
+) -> Option<DownloadRequest> {
+	let file = &profile.logging.as_ref()?.client.as_ref()?.file;
+	let dest = log_config_path(game_dir, file)
+		.inspect_err(|e| tracing::warn!("{}", e))
+		.ok()?;
+	Some(
+		DownloadRequest::new(file.url.clone(), dest)
+			.with_sha1(file.sha1.as_deref())
+			.with_size(file.size),
+	)
+}
 
-		if !index_path.exists() {
-			return Ok(());
-		}
+fn asset_index_request(profile: &VersionProfile, index_path: &Path) -> Option<DownloadRequest> {
+	let info = profile.asset_index.as_ref()?;
+	let url = info.url.as_ref()?;
+	Some(
+		DownloadRequest::new(url.clone(), index_path)
+			.with_sha1(info.sha1.as_deref())
+			.with_size(info.size),
+	)
+}
 
-		let index: AssetIndex = {
-			let content = fs::read_to_string(&index_path)
-				.await
-				.context("read asset index")
-				.map_err(|e| TaskError::Failed(e.to_string()))?;
-			serde_json::from_str(&content)
-				.context("parse asset index")
-				.map_err(|e| TaskError::Failed(e.to_string()))?
-		};
+pub(crate) fn library_requests(
+	game_dir: &Path,
+	profile: &VersionProfile,
+	repositories: &[String],
+) -> Vec<DownloadRequest> {
+	let features = Features::default();
+	let os_key = current_os_key();
+	let arch = current_arch();
+	let mut seen = HashSet::new();
+
+	profile
+		.libraries
+		.iter()
+		.filter(|lib| rule_allows(lib.rules.as_ref(), os_key, arch, &features))
+		.filter_map(|lib| library_request(game_dir, lib, os_key, repositories))
+		.filter(needs_download)
+		.filter(|req| seen.insert(req.dest.clone()))
+		.collect()
+}
 
-		let requests: Vec<_> = index
-			.objects
-			.values()
-			.filter(|a| a.hash.len() >= 2)
-			.filter_map(|a| {
-				let subdir = &a.hash[..2];
-				let dest = assets_dir.join("objects").join(subdir).join(&a.hash);
-				if dest.exists() {
-					return None;
-				}
-				let url = format!(
-					"https://resources.download.minecraft.net/{}/{}",
-					subdir, a.hash
-				);
-				Some(DownloadRequest::new(url, dest))
-			})
-			.collect();
+fn asset_requests(assets_dir: &Path, index: &AssetIndex) -> Vec<DownloadRequest> {
+	// 不同资源名可能指向同一个对象，并发写同一文件会互相破坏
+	let mut seen = HashSet::new();
+	index
+		.objects
+		.values()
+		.filter(|a| a.hash.len() >= 2 && seen.insert(a.hash.as_str()))
+		.map(|a| {
+			let subdir = &a.hash[..2];
+			let dest = assets_dir.join("objects").join(subdir).join(&a.hash);
+			let url = format!(
+				"https://resources.download.minecraft.net/{}/{}",
+				subdir, a.hash
+			);
+			DownloadRequest::new(url, dest)
+				.with_sha1(Some(&a.hash))
+				.with_size(a.size)
+		})
+		.collect()
+}
 
-		for req in requests {
-			check_cancel(&ctx.cancelled)?;
-			s.client
-				.download(req, |_| {}, Some(ctx.cancelled.clone()))
-				.await
-				.map_err(|e| TaskError::Failed(e.to_string()))?;
-		}
-		Ok(())
-	}
+fn total_size(requests: &[DownloadRequest]) -> u64 {
+	requests.iter().filter_map(|r| r.size).sum()
+}
+
+/// 按启动器设置（并发数、下载源）创建下载客户端
+pub(crate) fn configured_client() -> TaskResult<DownloadClient> {
+	let config = AppState::get().config.get();
+	let mirrors = MirrorList::new(config.download_source, config.download_fallback);
+	DownloadClient::with_concurrency(config.download_concurrency as usize)
+		.map(|c| c.with_mirrors(mirrors))
+		.map_err(|e| TaskError::Failed(e.to_string()))
+}
+
+/// 按库查找时使用的 Maven 仓库列表
+pub(crate) fn configured_repositories() -> Vec<String> {
+	AppState::get().config.get().maven_repositories
 }
 
 fn check_cancel(cancel: &watch::Receiver<bool>) -> TaskResult<()> {
@@ -330,55 +512,98 @@ fn check_cancel(cancel: &watch::Receiver<bool>) -> TaskResult<()> {
 	}
 }
 
-fn library_request(game_dir: &Path, lib: &Library, os_key: &str) -> Option<DownloadRequest> {
-	let downloads = lib.downloads.as_ref()?;
-
-	if let Some(natives) = &lib.natives {
-		if natives.contains_key(os_key) {
-			if let Some(classifiers) = &downloads.classifiers {
-				let key = format!("natives-{os_key}");
-				if let Some(artifact) = classifiers.get(&key) {
-					if let (Some(path), Some(url)) = (&artifact.path, &artifact.url) {
-						let dest = game_dir
-							.join("libraries")
-							.join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
-						return Some(DownloadRequest::new(url.clone(), dest));
-					}
-				}
-			}
-		}
+/// 有校验信息的请求交给下载器校验，否则只看文件是否存在
+fn needs_download(request: &DownloadRequest) -> bool {
+	request.is_verifiable() || !request.dest.exists()
+}
+
+fn library_request(
+	game_dir: &Path,
+	lib: &Library,
+	os_key: &str,
+	repositories: &[String],
+) -> Option<DownloadRequest> {
+	let classifier = match &lib.natives {
+		Some(_) => Some(native_classifier(lib, os_key)?),
+		None => None,
+	};
+	let artifact = match (&classifier, &lib.downloads) {
+		(Some(c), Some(dl)) => dl.classifiers.as_ref().and_then(|m| m.get(c)),
+		(None, Some(dl)) => dl.artifact.as_ref(),
+		_ => None,
+	};
+
+	if let Some(artifact) = artifact
+		&& let Some(path) = &artifact.path
+	{
+		// 地址为空的库由安装器自带或由处理器生成
+		let url = artifact.url.as_deref().filter(|u| !u.is_empty())?;
+		let dest = game_dir
+			.join("libraries")
+			.join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
+		return Some(artifact_request(url, dest, artifact));
 	}
 
-	if let Some(artifact) = &downloads.artifact {
-		if let (Some(path), Some(url)) = (&artifact.path, &artifact.url) {
-			let dest = game_dir
-				.join("libraries")
-				.join(path.replace('/', std::path::MAIN_SEPARATOR_STR));
-			return Some(DownloadRequest::new(url.clone(), dest));
+	maven_library_request(game_dir, lib, classifier, repositories)
+}
+
+/// 没有 downloads 的库（加载器、旧版 Forge 等），按坐标在库自带的仓库和配置的仓库中查找
+fn maven_library_request(
+	game_dir: &Path,
+	lib: &Library,
+	classifier: Option<String>,
+	repositories: &[String],
+) -> Option<DownloadRequest> {
+	let mut coord = MavenCoord::parse(&lib.name).ok()?;
+	let is_main_artifact = classifier.is_none() && coord.classifier.is_none();
+	if let Some(c) = classifier {
+		coord = coord.with_classifier(c);
+	}
+
+	let mut urls: Vec<String> = Vec::new();
+	for repo in lib.url.iter().chain(repositories) {
+		let url = coord.url(repo);
+		if !urls.contains(&url) {
+			urls.push(url);
 		}
 	}
+	let mut urls = urls.into_iter();
+	let request = DownloadRequest::new(urls.next()?, coord.local_path(&game_dir.join("libraries")))
+		.with_fallback_urls(urls.collect());
+
+	// 库上的校验信息只描述主构件
+	Some(if is_main_artifact {
+		request.with_sha1(lib.sha1.as_deref()).with_size(lib.size)
+	} else {
+		request
+	})
+}
 
-	None
+fn artifact_request(url: &str, dest: PathBuf, artifact: &Artifact) -> DownloadRequest {
+	DownloadRequest::new(url, dest)
+		.with_sha1(artifact.sha1.as_deref())
+		.with_size(artifact.size)
 }
 
-async fn resolve_version_url(version_id: &str) -> TaskResult<String> {
-	const MANIFEST: &str = "https://piston-meta.mojang.com/mc/game/version_manifest.json";
+/// 自定义实例名时把版本 JSON 的 id 改为实例名，与目录名保持一致
+async fn rename_version_json(path: &Path, instance: &str) -> anyhow::Result<()> {
+	let text = fs::read_to_string(path).await?;
+	let mut json: serde_json::Value = serde_json::from_str(&text)?;
+	json["id"] = serde_json::Value::String(instance.to_string());
+	fs::write(path, serde_json::to_vec_pretty(&json)?).await?;
+	Ok(())
+}
 
-	let resp = reqwest::get(MANIFEST)
+async fn resolve_version(client: &DownloadClient, version_id: &str) -> TaskResult<ManifestVersion> {
+	let manifest = ManifestCache::open_default()
+		.map_err(|e| TaskError::Failed(e.to_string()))?
+		.load(client)
 		.await
-		.map_err(|e| TaskError::Failed(format!("Fetch manifest: {e}")))?;
-	let text = resp
-		.text()
-		.await
-		.map_err(|e| TaskError::Failed(format!("Read manifest: {e}")))?;
-	let manifest: VersionManifest = serde_json::from_str(&text)
-		.map_err(|e| TaskError::Failed(format!("Parse manifest: {e}")))?;
+		.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
 
 	manifest
-		.versions
-		.into_iter()
-		.find(|v| v.id == version_id)
-		.map(|v| v.url)
+		.find(version_id)
+		.cloned()
 		.ok_or_else(|| TaskError::Failed(format!("Version {} not found", version_id)))
 }
 
@@ -390,15 +615,76 @@ struct AssetIndex {
 #[derive(Deserialize)]
 struct AssetObject {
 	hash: String,
+	#[serde(default)]
+	size: Option<u64>,
 }
 
-#[derive(Deserialize)]
-struct VersionManifest {
-	versions: Vec<VersionRef>,
-}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::net::download::Checksum;
 
-#[derive(Deserialize)]
-struct VersionRef {
-	id: String,
-	url: String,
+	fn library(json: &str) -> Library {
+		serde_json::from_str(json).unwrap()
+	}
+
+	#[test]
+	fn test_maven_library_requests() {
+		let game_dir = Path::new("/game");
+		let repositories = vec![
+			"https://libraries.minecraft.net/".to_string(),
+			"https://maven.fabricmc.net".to_string(),
+		];
+		let request =
+			|json: &str| library_request(game_dir, &library(json), "linux", &repositories).unwrap();
+
+		// 只有 url 的库：自带仓库优先，其余仓库去重后依次回退
+		let fabric = request(
+			r#"{ "name": "net.fabricmc:sponge-mixin:0.15.3+mixin.0.8.7",
+				 "url": "https://maven.fabricmc.net/",
+				 "sha1": "51ee0a44ab05f6fddd66b09e66b3a16904f9c55d", "size": 1451874 }"#,
+		);
+		assert_eq!(
+			fabric.url,
+			"https://maven.fabricmc.net/net/fabricmc/sponge-mixin/0.15.3+mixin.0.8.7/sponge-mixin-0.15.3+mixin.0.8.7.jar"
+		);
+		assert_eq!(
+			fabric.fallback_urls,
+			[
+				"https://libraries.minecraft.net/net/fabricmc/sponge-mixin/0.15.3+mixin.0.8.7/sponge-mixin-0.15.3+mixin.0.8.7.jar"
+			]
+		);
+		assert_eq!(
+			fabric.dest,
+			game_dir.join("libraries").join(
+				"net/fabricmc/sponge-mixin/0.15.3+mixin.0.8.7/sponge-mixin-0.15.3+mixin.0.8.7.jar"
+			)
+		);
+		assert!(matches!(fabric.checksum, Some(Checksum::Sha1(ref h)) if h.starts_with("51ee0a")));
+		assert_eq!(fabric.size, Some(1451874));
+
+		// 没有 url 时按配置的仓库顺序尝试，扩展名与分类器写入文件名
+		let zipped = request(r#"{ "name": "com.example:data:1.0:client@zip" }"#);
+		assert_eq!(
+			zipped.url,
+			"https://libraries.minecraft.net/com/example/data/1.0/data-1.0-client.zip"
+		);
+		assert_eq!(
+			zipped.fallback_urls,
+			["https://maven.fabricmc.net/com/example/data/1.0/data-1.0-client.zip"]
+		);
+		assert!(zipped.checksum.is_none());
+
+		// 旧版 natives 的校验信息属于主构件，不用于分类器文件
+		let natives = request(
+			r#"{ "name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4",
+				 "natives": { "linux": "natives-linux" }, "sha1": "abc" }"#,
+		);
+		assert!(
+			natives
+				.url
+				.ends_with("lwjgl-platform-2.9.4-natives-linux.jar")
+		);
+		assert!(natives.checksum.is_none());
+	}
 }
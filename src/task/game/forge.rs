@@ -0,0 +1,357 @@
+use crate::config::manager::ConfigManager;
+use crate::core::paths;
+use crate::core::state::AppState;
+use crate::game::forge::{
+	ForgeFlavor, ForgeInstaller, InstallProfile, ProcessorEnv, fetch_installer_sha1,
+};
+use crate::game::loader::{LoaderKind, prepare_profile};
+use crate::game::profile::{VersionProfile, load_version_profile};
+use crate::net::download::{DownloadClient, DownloadRequest};
+use crate::task::error::{TaskError, TaskResult};
+use crate::task::game::download::{
+	DownloadGameTask, DownloadProgressState, ProgressRef, configured_client,
+	configured_repositories, library_requests,
+};
+use crate::task::game::start::launch_java;
+use crate::task::lock::LockKey;
+use crate::task::main_task::{ConcurrentTask, TaskContext, TaskType};
+use crate::task::sub_task::{SubTask, SubTaskChain, SubTaskContext};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+/// 错误信息中保留的处理器输出行数
+const PROCESSOR_LOG_TAIL: usize = 20;
+
+/// 下载 Forge/NeoForge 安装器，写入版本 JSON 并运行 install_profile 中的处理器
+pub struct InstallForgeTask {
+	pub cluster_path: PathBuf,
+	pub game_version: String,
+	pub flavor: ForgeFlavor,
+	pub version: String,
+	pub name: Option<String>,
+	pub progress: Option<ProgressRef>,
+}
+
+impl InstallForgeTask {
+	fn kind(&self) -> LoaderKind {
+		match self.flavor {
+			ForgeFlavor::Forge => LoaderKind::Forge,
+			ForgeFlavor::NeoForge => LoaderKind::NeoForge,
+		}
+	}
+
+	fn instance_name(&self) -> String {
+		self.name.clone().unwrap_or_else(|| {
+			self.kind()
+				.default_instance_name(&self.game_version, &self.version)
+		})
+	}
+}
+
+impl TaskType for InstallForgeTask {
+	const TYPE_NAME: &'static str = "install_forge";
+}
+
+#[async_trait::async_trait]
+impl ConcurrentTask for InstallForgeTask {
+	type Output = ();
+
+	fn locks(&self) -> Vec<LockKey> {
+		vec![
+			LockKey::resource("download_game", self.instance_name()),
+			LockKey::resource("download_game", &self.game_version),
+		]
+	}
+
+	fn max_concurrent(&self) -> Option<usize> {
+		Some(1)
+	}
+
+	async fn execute(&mut self, ctx: &TaskContext) -> TaskResult<Self::Output> {
+		// 处理器需要原版客户端 jar
+		let mut vanilla = DownloadGameTask {
+			cluster_path: self.cluster_path.clone(),
+			version: self.game_version.clone(),
+			name: None,
+			progress: self.progress.clone(),
+		};
+		vanilla.execute(ctx).await?;
+
+		let instance = self.instance_name();
+		let work_dir = paths::cache_dir()
+			.map_err(|e| TaskError::Failed(e.to_string()))?
+			.join("forge-installer")
+			.join(&instance);
+		let shared = Arc::new(ForgeContext {
+			client: configured_client()?,
+			game_dir: self.cluster_path.clone(),
+			game_version: self.game_version.clone(),
+			flavor: self.flavor,
+			kind: self.kind(),
+			version: self.version.clone(),
+			instance,
+			installer: work_dir.join("installer.jar"),
+			work_dir,
+			progress: self.progress.clone(),
+			profile: OnceCell::new(),
+			env: OnceCell::new(),
+		});
+
+		let mut chain = SubTaskChain::new();
+		chain.add(DownloadInstallerTask(Arc::clone(&shared)));
+		chain.add(ExtractInstallerTask(Arc::clone(&shared)));
+		chain.add(DownloadForgeLibrariesTask(Arc::clone(&shared)));
+		chain.add(RunProcessorsTask(Arc::clone(&shared)));
+
+		let sub_ctx = SubTaskContext::new(ctx.cancelled_receiver());
+		let result = chain.execute(&sub_ctx).await;
+		if let Err(e) = tokio::fs::remove_dir_all(&shared.work_dir).await {
+			tracing::debug!("Failed to clean {}: {}", shared.work_dir.display(), e);
+		}
+		result?;
+
+		shared
+			.update(|p| {
+				p.message = format!("{} 安装完成", shared.instance);
+				p.finished = true;
+			})
+			.await;
+		Ok(())
+	}
+}
+
+struct ForgeContext {
+	client: DownloadClient,
+	game_dir: PathBuf,
+	game_version: String,
+	flavor: ForgeFlavor,
+	kind: LoaderKind,
+	version: String,
+	instance: String,
+	work_dir: PathBuf,
+	installer: PathBuf,
+	progress: Option<ProgressRef>,
+	profile: OnceCell<InstallProfile>,
+	env: OnceCell<ProcessorEnv>,
+}
+
+impl ForgeContext {
+	async fn update(&self, f: impl FnOnce(&mut DownloadProgressState)) {
+		if let Some(p) = &self.progress {
+			f(&mut *p.write().await);
+		}
+	}
+
+	fn profile(&self) -> TaskResult<&InstallProfile> {
+		self.profile
+			.get()
+			.ok_or_else(|| TaskError::Failed("install profile missing".into()))
+	}
+}
+
+struct DownloadInstallerTask(Arc<ForgeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for DownloadInstallerTask {
+	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		s.update(|p| {
+			p.message = format!("下载 {} {} 安装器", s.kind.name(), s.version);
+			p.finished = false;
+		})
+		.await;
+
+		let url = s.flavor.installer_url(&s.game_version, &s.version);
+		let sha1 = fetch_installer_sha1(&s.client, &url)
+			.await
+			.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+		s.client
+			.download(
+				DownloadRequest::new(url, &s.installer).with_sha1(Some(&sha1)),
+				|_| {},
+				Some(ctx.cancelled.clone()),
+			)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))
+	}
+}
+
+/// 读取 install_profile.json，写入版本 JSON 并解出安装器内置的库与数据文件
+struct ExtractInstallerTask(Arc<ForgeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for ExtractInstallerTask {
+	async fn execute(&self, _ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = Arc::clone(&self.0);
+		let (profile, env) = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+			let mut installer = ForgeInstaller::open(&s.installer)?;
+			let profile = installer.install_profile()?;
+			if profile.minecraft != s.game_version {
+				return Err(anyhow::anyhow!(
+					"Installer targets Minecraft {}, expected {}",
+					profile.minecraft,
+					s.game_version
+				));
+			}
+
+			let json = installer.read_text(&profile.json)?;
+			let version_json = prepare_profile(&json, &s.instance, &s.game_version)?;
+			let dir = s.game_dir.join("versions").join(&s.instance);
+			std::fs::create_dir_all(&dir)?;
+			std::fs::write(dir.join(format!("{}.json", s.instance)), version_json)?;
+
+			installer.extract_maven(&s.game_dir.join("libraries"))?;
+			let env = ProcessorEnv::new(&profile, &mut installer, &s.game_dir, &s.work_dir)?;
+			Ok((profile, env))
+		})
+		.await
+		.map_err(|e| TaskError::Failed(e.to_string()))?
+		.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+
+		let _ = self.0.profile.set(profile);
+		let _ = self.0.env.set(env);
+		Ok(())
+	}
+}
+
+struct DownloadForgeLibrariesTask(Arc<ForgeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for DownloadForgeLibrariesTask {
+	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		let version = load_version_profile(&s.game_dir, &s.instance)
+			.map_err(|e| TaskError::Failed(e.to_string()))?;
+		// 安装器与版本 JSON 有大量相同的库，合并后统一去重，避免并发写同一文件
+		let libraries = VersionProfile {
+			libraries: s
+				.profile()?
+				.libraries
+				.iter()
+				.cloned()
+				.chain(version.libraries)
+				.collect(),
+			..Default::default()
+		};
+		let requests = library_requests(&s.game_dir, &libraries, &configured_repositories());
+
+		s.update(|p| {
+			p.message = format!("下载 {} 依赖库", s.kind.name());
+			p.files_completed = 0;
+			p.files_total = requests.len();
+		})
+		.await;
+		s.client
+			.download_many(
+				requests,
+				|b| {
+					if let Some(Ok(mut p)) = s.progress.as_ref().map(|p| p.try_write()) {
+						p.files_completed = b.completed;
+					}
+				},
+				Some(ctx.cancelled.clone()),
+			)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))
+	}
+}
+
+struct RunProcessorsTask(Arc<ForgeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for RunProcessorsTask {
+	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		let env = s
+			.env
+			.get()
+			.ok_or_else(|| TaskError::Failed("processor data missing".into()))?;
+		let processors: Vec<_> = s
+			.profile()?
+			.processors
+			.iter()
+			.filter(|p| p.runs_on_client())
+			.collect();
+		if processors.is_empty() {
+			return Ok(());
+		}
+		let java = processor_java(s, ctx.cancelled.clone()).await?;
+		let sep = if cfg!(windows) { ";" } else { ":" };
+
+		for (i, processor) in processors.iter().enumerate() {
+			if ctx.is_cancelled() {
+				return Err(TaskError::Cancelled);
+			}
+			s.update(|p| {
+				p.message = format!("执行安装处理器 {}/{}", i + 1, processors.len());
+				p.files_completed = i;
+				p.files_total = processors.len();
+			})
+			.await;
+			if env
+				.outputs_valid(processor)
+				.map_err(|e| TaskError::Failed(e.to_string()))?
+			{
+				continue;
+			}
+
+			let command = env
+				.command(processor)
+				.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+			let classpath = command
+				.classpath
+				.iter()
+				.map(|p| p.to_string_lossy())
+				.collect::<Vec<_>>()
+				.join(sep);
+			tracing::info!("Running processor {}", processor.jar);
+			let output = Command::new(&java)
+				.arg("-cp")
+				.arg(classpath)
+				.arg(&command.main_class)
+				.args(&command.args)
+				.current_dir(&s.game_dir)
+				.output()
+				.await
+				.map_err(|e| TaskError::Failed(format!("Failed to run processor: {e}")))?;
+
+			if !output.status.success() {
+				let log = String::from_utf8_lossy(&output.stderr).into_owned()
+					+ &String::from_utf8_lossy(&output.stdout);
+				let tail: Vec<_> = log.lines().rev().take(PROCESSOR_LOG_TAIL).collect();
+				return Err(TaskError::Failed(format!(
+					"Processor {} exited with {}:\n{}",
+					processor.jar,
+					output.status,
+					tail.into_iter().rev().collect::<Vec<_>>().join("\n")
+				)));
+			}
+			if !processor.outputs.is_empty()
+				&& !env
+					.outputs_valid(processor)
+					.map_err(|e| TaskError::Failed(e.to_string()))?
+			{
+				return Err(TaskError::Failed(format!(
+					"Processor {} produced files with unexpected checksums",
+					processor.jar
+				)));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// 与启动游戏时使用同一个 Java：实例指定的优先，否则按版本要求选择
+async fn processor_java(
+	s: &ForgeContext,
+	cancelled: tokio::sync::watch::Receiver<bool>,
+) -> TaskResult<PathBuf> {
+	let profile = load_version_profile(&s.game_dir, &s.instance)
+		.map_err(|e| TaskError::Failed(e.to_string()))?;
+	let java_path = ConfigManager::load_game_config(&s.game_dir, &s.instance)
+		.resolve(&AppState::get().config.get().game)
+		.java_path;
+	launch_java(java_path, &profile, cancelled).await
+}
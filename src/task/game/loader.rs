@@ -0,0 +1,174 @@
+use crate::game::loader::{LoaderKind, prepare_profile};
+use crate::game::profile::load_version_profile;
+use crate::net::download::{BatchProgress, DownloadClient};
+use crate::task::error::{TaskError, TaskResult};
+use crate::task::game::download::{
+	DownloadGameTask, DownloadProgressState, ProgressRef, configured_client,
+	configured_repositories, library_requests,
+};
+use crate::task::lock::LockKey;
+use crate::task::main_task::{ConcurrentTask, TaskContext, TaskType};
+use crate::task::sub_task::{SubTask, SubTaskChain, SubTaskContext};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs;
+
+/// 安装原版后写入加载器的版本 JSON 并下载加载器依赖库
+pub struct InstallLoaderTask {
+	pub cluster_path: PathBuf,
+	pub game_version: String,
+	pub loader: LoaderKind,
+	pub loader_version: String,
+	/// 自定义实例名，默认与官方安装器一致
+	pub name: Option<String>,
+	pub progress: Option<ProgressRef>,
+}
+
+impl InstallLoaderTask {
+	fn instance_name(&self) -> String {
+		self.name.clone().unwrap_or_else(|| {
+			self.loader
+				.default_instance_name(&self.game_version, &self.loader_version)
+		})
+	}
+}
+
+impl TaskType for InstallLoaderTask {
+	const TYPE_NAME: &'static str = "install_loader";
+}
+
+#[async_trait::async_trait]
+impl ConcurrentTask for InstallLoaderTask {
+	type Output = ();
+
+	fn locks(&self) -> Vec<LockKey> {
+		vec![
+			LockKey::resource("download_game", self.instance_name()),
+			LockKey::resource("download_game", &self.game_version),
+		]
+	}
+
+	fn max_concurrent(&self) -> Option<usize> {
+		Some(2)
+	}
+
+	async fn execute(&mut self, ctx: &TaskContext) -> TaskResult<Self::Output> {
+		// 加载器版本继承原版，先保证原版完整
+		let mut vanilla = DownloadGameTask {
+			cluster_path: self.cluster_path.clone(),
+			version: self.game_version.clone(),
+			name: None,
+			progress: self.progress.clone(),
+		};
+		vanilla.execute(ctx).await?;
+
+		let shared = Arc::new(LoaderContext {
+			client: configured_client()?,
+			game_dir: self.cluster_path.clone(),
+			game_version: self.game_version.clone(),
+			loader: self.loader,
+			loader_version: self.loader_version.clone(),
+			instance: self.instance_name(),
+			progress: self.progress.clone(),
+		});
+
+		let mut chain = SubTaskChain::new();
+		chain.add(WriteLoaderProfileTask(Arc::clone(&shared)));
+		chain.add(DownloadLoaderLibrariesTask(Arc::clone(&shared)));
+
+		let sub_ctx = SubTaskContext::new(ctx.cancelled_receiver());
+		chain.execute(&sub_ctx).await?;
+
+		shared
+			.update(|p| {
+				p.message = format!("{} 安装完成", shared.instance);
+				p.finished = true;
+			})
+			.await;
+		Ok(())
+	}
+}
+
+struct LoaderContext {
+	client: DownloadClient,
+	game_dir: PathBuf,
+	game_version: String,
+	loader: LoaderKind,
+	loader_version: String,
+	instance: String,
+	progress: Option<ProgressRef>,
+}
+
+impl LoaderContext {
+	async fn update(&self, f: impl FnOnce(&mut DownloadProgressState)) {
+		if let Some(p) = &self.progress {
+			f(&mut *p.write().await);
+		}
+	}
+}
+
+struct WriteLoaderProfileTask(Arc<LoaderContext>);
+
+#[async_trait::async_trait]
+impl SubTask for WriteLoaderProfileTask {
+	async fn execute(&self, _ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		s.update(|p| {
+			p.message = format!("获取 {} {} 版本信息", s.loader.name(), s.loader_version);
+			p.finished = false;
+		})
+		.await;
+
+		let url = s
+			.loader
+			.profile_url(&s.game_version, &s.loader_version)
+			.ok_or_else(|| TaskError::Failed(format!("{} needs an installer", s.loader.name())))?;
+		let json = s
+			.client
+			.fetch_text(&url)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))?;
+		let profile = prepare_profile(&json, &s.instance, &s.game_version)
+			.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+
+		let dir = s.game_dir.join("versions").join(&s.instance);
+		fs::create_dir_all(&dir)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))?;
+		fs::write(dir.join(format!("{}.json", s.instance)), profile)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))
+	}
+}
+
+struct DownloadLoaderLibrariesTask(Arc<LoaderContext>);
+
+#[async_trait::async_trait]
+impl SubTask for DownloadLoaderLibrariesTask {
+	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		let profile = load_version_profile(&s.game_dir, &s.instance)
+			.map_err(|e| TaskError::Failed(e.to_string()))?;
+		let requests = library_requests(&s.game_dir, &profile, &configured_repositories());
+
+		s.update(|p| {
+			p.message = format!("下载 {} 依赖库", s.loader.name());
+			p.files_completed = 0;
+			p.files_total = requests.len();
+		})
+		.await;
+		s.client
+			.download_many(
+				requests,
+				|b: BatchProgress| {
+					// 只有少量文件，拿不到锁时跳过本次更新即可
+					if let Some(Ok(mut p)) = s.progress.as_ref().map(|p| p.try_write()) {
+						p.files_completed = b.completed;
+					}
+				},
+				Some(ctx.cancelled.clone()),
+			)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))
+	}
+}
@@ -1,2 +1,5 @@
 pub mod download;
+pub mod forge;
+pub mod loader;
+pub mod runtime;
 pub mod start;
@@ -0,0 +1,385 @@
+use crate::core::state::AppState;
+use crate::game::runtime::{
+	RUNTIME_INDEX_URL, RuntimeFile, RuntimeIndex, RuntimeManifest, check_link_target, find_runtime,
+	install_path, platform_key, runtime_dir,
+};
+use crate::net::download::{BatchProgress, Checksum, DownloadClient, DownloadRequest, verify_file};
+use crate::task::error::{TaskError, TaskResult};
+use crate::task::game::download::{ProgressRef, configured_client};
+use crate::task::lock::LockKey;
+use crate::task::main_task::{ConcurrentTask, TaskContext, TaskType};
+use crate::task::sub_task::{SubTask, SubTaskChain, SubTaskContext};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio::fs;
+use tokio::sync::{Mutex, OnceCell, watch};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 下载 Mojang 提供的 Java 运行时，安装到启动器数据目录，返回安装目录
+pub struct DownloadJavaTask {
+	/// 版本 JSON 中的 javaVersion.component，如 java-runtime-gamma
+	pub component: String,
+	pub progress: Option<ProgressRef>,
+}
+
+impl TaskType for DownloadJavaTask {
+	const TYPE_NAME: &'static str = "download_java";
+}
+
+#[async_trait::async_trait]
+impl ConcurrentTask for DownloadJavaTask {
+	type Output = PathBuf;
+
+	fn locks(&self) -> Vec<LockKey> {
+		vec![LockKey::resource("java_runtime", &self.component)]
+	}
+
+	fn max_concurrent(&self) -> Option<usize> {
+		Some(1)
+	}
+
+	async fn execute(&mut self, ctx: &TaskContext) -> TaskResult<Self::Output> {
+		install_runtime(
+			&self.component,
+			self.progress.clone(),
+			ctx.cancelled_receiver(),
+		)
+		.await
+	}
+}
+
+/// 安装运行时并重新扫描 Java，返回安装目录
+async fn install_runtime(
+	component: &str,
+	progress: Option<ProgressRef>,
+	cancelled: watch::Receiver<bool>,
+) -> TaskResult<PathBuf> {
+	let shared = Arc::new(RuntimeContext::new(component, progress)?);
+
+	let mut chain = SubTaskChain::new();
+	chain.add(FetchRuntimeManifestTask(Arc::clone(&shared)));
+	chain.add(DownloadRuntimeFilesTask(Arc::clone(&shared)));
+	chain.add(LinkRuntimeFilesTask(Arc::clone(&shared)));
+
+	let reporter = tokio::spawn(report_progress(Arc::clone(&shared)));
+	let result = chain.execute(&SubTaskContext::new(cancelled)).await;
+	reporter.abort();
+	result?;
+
+	tokio::task::spawn_blocking(|| AppState::get().scan_java())
+		.await
+		.map_err(|e| TaskError::Failed(e.to_string()))?;
+
+	shared
+		.set_message(&format!("Java 运行时 {} 安装完成", shared.component))
+		.await;
+	shared.publish_progress(0.0, true).await;
+	Ok(shared.dir.clone())
+}
+
+struct RuntimeContext {
+	client: DownloadClient,
+	component: String,
+	dir: PathBuf,
+	progress: Option<ProgressRef>,
+	manifest: OnceCell<RuntimeManifest>,
+	/// 下载的是 LZMA 压缩版本的文件：(压缩文件, 目标文件, 解压后的 sha1)
+	compressed: Mutex<Vec<(PathBuf, PathBuf, String)>>,
+	downloaded: AtomicU64,
+	completed: AtomicUsize,
+	total_bytes: AtomicU64,
+	total_files: AtomicUsize,
+}
+
+impl RuntimeContext {
+	fn new(component: &str, progress: Option<ProgressRef>) -> TaskResult<Self> {
+		Ok(Self {
+			client: configured_client()?,
+			component: component.to_string(),
+			dir: runtime_dir(component).map_err(|e| TaskError::Failed(e.to_string()))?,
+			progress,
+			manifest: OnceCell::new(),
+			compressed: Mutex::new(Vec::new()),
+			downloaded: AtomicU64::new(0),
+			completed: AtomicUsize::new(0),
+			total_bytes: AtomicU64::new(0),
+			total_files: AtomicUsize::new(0),
+		})
+	}
+
+	async fn set_message(&self, message: &str) {
+		if let Some(p) = &self.progress {
+			p.write().await.message = message.to_string();
+		}
+	}
+
+	async fn publish_progress(&self, speed_bps: f64, finished: bool) {
+		let Some(p) = &self.progress else {
+			return;
+		};
+		let total = self.total_bytes.load(Ordering::Relaxed);
+
+		let mut guard = p.write().await;
+		guard.downloaded = self.downloaded.load(Ordering::Relaxed);
+		guard.total = (total > 0).then_some(total);
+		guard.speed_bps = speed_bps;
+		guard.files_completed = self.completed.load(Ordering::Relaxed);
+		guard.files_total = self.total_files.load(Ordering::Relaxed);
+		guard.finished = finished;
+	}
+
+	fn manifest(&self) -> TaskResult<&RuntimeManifest> {
+		self.manifest
+			.get()
+			.ok_or_else(|| TaskError::Failed("runtime manifest missing".into()))
+	}
+}
+
+async fn report_progress(shared: Arc<RuntimeContext>) {
+	let mut last_instant = Instant::now();
+	let mut last_downloaded = 0;
+	loop {
+		tokio::time::sleep(PROGRESS_INTERVAL).await;
+		let downloaded = shared.downloaded.load(Ordering::Relaxed);
+		let elapsed = last_instant.elapsed().as_secs_f64();
+		let speed = downloaded.saturating_sub(last_downloaded) as f64 / elapsed;
+		shared.publish_progress(speed, false).await;
+		last_instant = Instant::now();
+		last_downloaded = downloaded;
+	}
+}
+
+struct FetchRuntimeManifestTask(Arc<RuntimeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for FetchRuntimeManifestTask {
+	async fn execute(&self, _ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		if s.manifest.get().is_some() {
+			return Ok(());
+		}
+		let platform = platform_key().ok_or_else(|| {
+			TaskError::Failed("Java runtime is not provided for this platform".into())
+		})?;
+
+		s.set_message("获取 Java 运行时列表").await;
+		let index: RuntimeIndex = fetch_json(&s.client, RUNTIME_INDEX_URL).await?;
+		let entry = find_runtime(&index, platform, &s.component).ok_or_else(|| {
+			TaskError::Failed(format!(
+				"Java runtime {} is not available for {}",
+				s.component, platform
+			))
+		})?;
+
+		s.set_message(&format!(
+			"下载 Java 运行时 {} ({})",
+			s.component, entry.version.name
+		))
+		.await;
+		let text = s
+			.client
+			.fetch_text(&entry.manifest.url)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))?;
+		if !Checksum::Sha1(entry.manifest.sha1.clone()).matches(text.as_bytes()) {
+			return Err(TaskError::Failed(format!(
+				"Checksum mismatch for runtime manifest {}",
+				s.component
+			)));
+		}
+		let manifest = serde_json::from_str(&text).map_err(|e| TaskError::Failed(e.to_string()))?;
+		let _ = s.manifest.set(manifest);
+		Ok(())
+	}
+}
+
+struct DownloadRuntimeFilesTask(Arc<RuntimeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for DownloadRuntimeFilesTask {
+	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		let mut requests = Vec::new();
+		let mut compressed = Vec::new();
+
+		for (name, file) in &s.manifest()?.files {
+			let dest = install_path(&s.dir, name).map_err(|e| TaskError::Failed(e.to_string()))?;
+			match file {
+				RuntimeFile::Directory => fs::create_dir_all(&dest)
+					.await
+					.map_err(|e| TaskError::Failed(e.to_string()))?,
+				RuntimeFile::File { downloads, .. } => {
+					let raw = Checksum::Sha1(downloads.raw.sha1.clone());
+					if verify_file(&dest, Some(&raw), Some(downloads.raw.size))
+						.await
+						.unwrap_or(false)
+					{
+						continue;
+					}
+					let request = match &downloads.lzma {
+						Some(lzma) => {
+							let archive = lzma_path(&dest);
+							compressed.push((archive.clone(), dest, downloads.raw.sha1.clone()));
+							DownloadRequest::new(&lzma.url, archive)
+								.with_sha1(Some(&lzma.sha1))
+								.with_size(Some(lzma.size))
+						}
+						None => DownloadRequest::new(&downloads.raw.url, dest)
+							.with_sha1(Some(&downloads.raw.sha1))
+							.with_size(Some(downloads.raw.size)),
+					};
+					requests.push(request);
+				}
+				RuntimeFile::Link { .. } => {}
+			}
+		}
+
+		s.total_files.store(requests.len(), Ordering::Relaxed);
+		s.total_bytes.store(
+			requests.iter().filter_map(|r| r.size).sum(),
+			Ordering::Relaxed,
+		);
+		s.client
+			.download_many(
+				requests,
+				|p: BatchProgress| {
+					s.downloaded.store(p.downloaded, Ordering::Relaxed);
+					s.completed.store(p.completed, Ordering::Relaxed);
+				},
+				Some(ctx.cancelled.clone()),
+			)
+			.await
+			.map_err(|e| TaskError::Failed(e.to_string()))?;
+
+		*s.compressed.lock().await = compressed;
+		Ok(())
+	}
+}
+
+/// 解压 LZMA 文件、创建符号链接并设置可执行权限
+struct LinkRuntimeFilesTask(Arc<RuntimeContext>);
+
+#[async_trait::async_trait]
+impl SubTask for LinkRuntimeFilesTask {
+	async fn execute(&self, _ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let s = &self.0;
+		let compressed = std::mem::take(&mut *s.compressed.lock().await);
+		if !compressed.is_empty() {
+			s.set_message(&format!("解压 Java 运行时 {}", s.component))
+				.await;
+		}
+		for (archive, dest, sha1) in compressed {
+			tokio::task::spawn_blocking(move || decompress_lzma(&archive, &dest, &sha1))
+				.await
+				.map_err(|e| TaskError::Failed(e.to_string()))?
+				.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+		}
+
+		for (name, file) in &s.manifest()?.files {
+			let path = install_path(&s.dir, name).map_err(|e| TaskError::Failed(e.to_string()))?;
+			match file {
+				RuntimeFile::File {
+					executable: true, ..
+				} => set_executable(&path)
+					.await
+					.map_err(|e| TaskError::Failed(e.to_string()))?,
+				RuntimeFile::Link { target } => create_link(name, &path, target)
+					.await
+					.map_err(|e| TaskError::Failed(e.to_string()))?,
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(
+	client: &DownloadClient,
+	url: &str,
+) -> TaskResult<T> {
+	let text = client
+		.fetch_text(url)
+		.await
+		.map_err(|e| TaskError::Failed(e.to_string()))?;
+	serde_json::from_str(&text).map_err(|e| TaskError::Failed(e.to_string()))
+}
+
+fn lzma_path(dest: &Path) -> PathBuf {
+	let mut path = dest.as_os_str().to_owned();
+	path.push(".lzma");
+	PathBuf::from(path)
+}
+
+fn decompress_lzma(archive: &Path, dest: &Path, sha1: &str) -> anyhow::Result<()> {
+	let mut input = std::io::BufReader::new(std::fs::File::open(archive)?);
+	let mut data = Vec::new();
+	lzma_rs::lzma_decompress(&mut input, &mut data)
+		.map_err(|e| anyhow::anyhow!("Failed to decompress {}: {}", archive.display(), e))?;
+	if !Checksum::Sha1(sha1.to_string()).matches(&data) {
+		return Err(anyhow::anyhow!("Checksum mismatch for {}", dest.display()));
+	}
+	std::fs::write(dest, data)?;
+	std::fs::remove_file(archive)?;
+	Ok(())
+}
+
+#[cfg(unix)]
+async fn set_executable(path: &Path) -> std::io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+	fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).await
+}
+
+#[cfg(not(unix))]
+async fn set_executable(_path: &Path) -> std::io::Result<()> {
+	Ok(())
+}
+
+#[cfg(unix)]
+async fn create_link(name: &str, path: &Path, target: &str) -> std::io::Result<()> {
+	check_link_target(name, target)
+		.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+	if fs::symlink_metadata(path).await.is_ok() {
+		fs::remove_file(path).await?;
+	}
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent).await?;
+	}
+	fs::symlink(target, path).await
+}
+
+/// Windows 的运行时清单中没有链接，无需处理
+#[cfg(not(unix))]
+async fn create_link(_name: &str, _path: &Path, _target: &str) -> std::io::Result<()> {
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_decompress_lzma_verifies_content() {
+		let dir = tempfile::tempdir().unwrap();
+		let archive = dir.path().join("java.lzma");
+		let dest = dir.path().join("java");
+		let content = b"#!/bin/sh\necho java\n";
+
+		let mut packed = Vec::new();
+		lzma_rs::lzma_compress(&mut &content[..], &mut packed).unwrap();
+		std::fs::write(&archive, &packed).unwrap();
+
+		assert!(decompress_lzma(&archive, &dest, "0000").is_err());
+		assert!(!dest.exists());
+
+		let sha1 = {
+			use sha1::{Digest, Sha1};
+			hex::encode(Sha1::digest(content))
+		};
+		decompress_lzma(&archive, &dest, &sha1).unwrap();
+		assert_eq!(std::fs::read(&dest).unwrap(), content);
+		assert!(!archive.exists());
+	}
+}
@@ -1,24 +1,47 @@
+use crate::account::Account;
+use crate::account::yggdrasil::{AuthlibInjector, ensure_authlib_injector, prefetch_metadata};
 use crate::config::manager::ConfigManager;
+use crate::core::paths;
 use crate::core::state::AppState;
-use crate::game::args::{Features, collect_game_args, collect_jvm_args};
+use crate::game::args::{
+	ExpandedArgs, LaunchAuth, LaunchOptions, collect_game_args, collect_jvm_args,
+};
 use crate::game::classpath::build_classpath;
 use crate::game::instance::GameInstance;
-use crate::game::java::find_java;
+use crate::game::java::{find_java, select_java};
 use crate::game::natives::{extract_natives, get_natives_directory};
+use crate::game::process::{GameProcess, ProcessStatus};
 use crate::game::profile::{VersionProfile, load_version_profile};
+use crate::game::readiness::{ReadinessDetector, VersionFamily};
+use crate::game::script::{LaunchCommand, ScriptFormat, render_script};
 use crate::task::error::{TaskError, TaskResult};
+use crate::task::game::download::{configured_client, log_config_request};
+use crate::task::game::runtime::DownloadJavaTask;
 use crate::task::lock::LockKey;
 use crate::task::main_task::{BlockingTask, TaskContext, TaskType};
 use crate::task::sub_task::{SubTask, SubTaskChain, SubTaskContext};
 use anyhow::Context;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::io::AsyncBufReadExt;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use tokio::sync::RwLock;
-
-const MAX_WAIT_TIME: Duration = Duration::from_secs(30);
+use tokio::sync::broadcast::error::RecvError;
+
+/// 启动任务的结果。游戏在此之后继续由 `AppState::games` 管理
+#[derive(Debug, Clone)]
+pub enum LaunchOutcome {
+	/// 检测到窗口已创建
+	Started,
+	/// 超时仍未检测到窗口，游戏仍在运行
+	StillStarting,
+	/// 启动过程中退出，崩溃时附带诊断
+	ExitedDuringStartup {
+		status: ProcessStatus,
+		reason: Option<String>,
+	},
+}
 
 struct StartContext {
 	game_dir: PathBuf,
@@ -27,6 +50,9 @@ struct StartContext {
 	max_memory_mb: u32,
 	extra_jvm_args: Vec<String>,
 	extra_game_args: Vec<String>,
+	options: LaunchOptions,
+	startup_timeout: Duration,
+	ready_patterns: Vec<String>,
 
 	profile: Option<VersionProfile>,
 	natives_dir: Option<PathBuf>,
@@ -34,28 +60,27 @@ struct StartContext {
 	classpath: Option<String>,
 	jvm_args: Vec<String>,
 	game_args: Vec<String>,
-	username: String,
-	uuid: String,
+	auth: LaunchAuth,
+	yggdrasil_server: Option<String>,
+	outcome: Option<LaunchOutcome>,
 }
 
 impl StartContext {
-	fn from_instance(instance: &GameInstance) -> Self {
+	fn from_instance(instance: &GameInstance, account: Option<Account>) -> Self {
 		let state = AppState::get();
 		let launcher_config = state.config.get();
 		let game_config =
 			ConfigManager::load_game_config(&instance.cluster_path, &instance.version);
 		let resolved = game_config.resolve(&launcher_config.game);
 
-		let (username, uuid) = state
-			.accounts
-			.current()
-			.map(|a| (a.username().to_string(), a.uuid().to_string()))
-			.unwrap_or_else(|| {
-				(
-					"Player".into(),
-					crate::account::offline_uuid("Player").to_string(),
-				)
-			});
+		let yggdrasil_server = account
+			.as_ref()
+			.and_then(|a| a.yggdrasil_server())
+			.map(String::from);
+		let auth = account
+			.as_ref()
+			.map(LaunchAuth::from_account)
+			.unwrap_or_else(|| LaunchAuth::offline("Player"));
 
 		let jvm_args: Vec<String> = resolved
 			.jvm_args
@@ -75,14 +100,24 @@ impl StartContext {
 			max_memory_mb: resolved.max_memory_mb,
 			extra_jvm_args: jvm_args,
 			extra_game_args: game_args,
+			options: LaunchOptions {
+				width: resolved.window_width,
+				height: resolved.window_height,
+				fullscreen: resolved.fullscreen,
+				demo: resolved.demo,
+				quick_play: resolved.quick_play,
+			},
 			profile: None,
 			natives_dir: None,
 			java_bin: None,
 			classpath: None,
 			jvm_args: Vec::new(),
 			game_args: Vec::new(),
-			username,
-			uuid,
+			startup_timeout: Duration::from_secs(resolved.startup_timeout_secs),
+			ready_patterns: resolved.ready_patterns,
+			auth,
+			yggdrasil_server,
+			outcome: None,
 		}
 	}
 }
@@ -97,7 +132,7 @@ impl TaskType for StartGameTask {
 
 #[async_trait::async_trait]
 impl BlockingTask for StartGameTask {
-	type Output = ();
+	type Output = LaunchOutcome;
 
 	fn locks(&self) -> Vec<LockKey> {
 		vec![LockKey::global("start_game")]
@@ -108,14 +143,148 @@ impl BlockingTask for StartGameTask {
 	}
 
 	async fn execute(&mut self, ctx: &TaskContext) -> TaskResult<Self::Output> {
-		let shared = Arc::new(RwLock::new(StartContext::from_instance(&self.instance)));
+		let shared = prepare_context(&self.instance).await?;
 
-		let mut chain = SubTaskChain::new();
-		chain.add(PrepareEnvTask(Arc::clone(&shared)));
-		chain.add(LaunchTask(shared));
+		let mut chain = prepare_chain(&shared);
+		chain.add(LaunchTask(Arc::clone(&shared)));
 
 		let sub_ctx = SubTaskContext::new(ctx.cancelled_receiver());
-		chain.execute(&sub_ctx).await
+		chain.execute(&sub_ctx).await?;
+		shared
+			.write()
+			.await
+			.outcome
+			.take()
+			.ok_or_else(|| TaskError::Failed("launch outcome missing".into()))
+	}
+}
+
+/// 按启动流程解析出完整命令行，写成可脱离启动器运行的脚本
+pub struct ExportLaunchScriptTask {
+	pub instance: GameInstance,
+	pub format: ScriptFormat,
+	/// 用环境变量代替脚本中的访问令牌
+	pub redact_token: bool,
+	pub dest: PathBuf,
+}
+
+impl TaskType for ExportLaunchScriptTask {
+	const TYPE_NAME: &'static str = "export_launch_script";
+}
+
+#[async_trait::async_trait]
+impl BlockingTask for ExportLaunchScriptTask {
+	type Output = PathBuf;
+
+	fn locks(&self) -> Vec<LockKey> {
+		vec![LockKey::resource(
+			"export_launch_script",
+			self.instance.version.clone(),
+		)]
+	}
+
+	async fn execute(&mut self, ctx: &TaskContext) -> TaskResult<Self::Output> {
+		let shared = prepare_context(&self.instance).await?;
+		let sub_ctx = SubTaskContext::new(ctx.cancelled_receiver());
+		prepare_chain(&shared).execute(&sub_ctx).await?;
+
+		let s = shared.read().await;
+		let java = s
+			.java_bin
+			.as_ref()
+			.ok_or_else(|| TaskError::Failed("java missing".into()))?;
+		let main_class = s
+			.profile
+			.as_ref()
+			.and_then(|p| p.main_class.as_deref())
+			.ok_or_else(|| TaskError::Failed("mainClass missing".into()))?;
+		let cmd = LaunchCommand {
+			title: &s.version_id,
+			java,
+			jvm_args: &s.jvm_args,
+			main_class,
+			game_args: &s.game_args,
+			working_dir: &s.game_dir,
+		};
+		let token = self.redact_token.then_some(s.auth.access_token.as_str());
+		let script = render_script(&cmd, self.format, token);
+		drop(s);
+
+		write_script(&self.dest, &script, !self.redact_token)
+			.await
+			.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+		tracing::info!("Launch script exported to {}", self.dest.display());
+		Ok(self.dest.clone())
+	}
+}
+
+/// 含有访问令牌的脚本只允许所有者读取，创建时即设置权限
+async fn write_script(dest: &Path, script: &str, private: bool) -> anyhow::Result<()> {
+	if let Some(parent) = dest.parent() {
+		tokio::fs::create_dir_all(parent).await?;
+	}
+	// 已有文件的权限不会被创建选项覆盖
+	let _ = tokio::fs::remove_file(dest).await;
+	let mut options = tokio::fs::OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	options.mode(if private { 0o700 } else { 0o755 });
+	#[cfg(not(unix))]
+	let _ = private;
+	let mut file = options
+		.open(dest)
+		.await
+		.with_context(|| format!("write {}", dest.display()))?;
+	file.write_all(script.as_bytes()).await?;
+	file.flush().await?;
+	Ok(())
+}
+
+/// 刷新当前账户并读取实例配置
+async fn prepare_context(instance: &GameInstance) -> TaskResult<Arc<RwLock<StartContext>>> {
+	let account = AppState::get()
+		.accounts
+		.refresh_current()
+		.await
+		.map_err(|e| TaskError::Failed(format!("refresh account: {e}")))?;
+	Ok(Arc::new(RwLock::new(StartContext::from_instance(
+		instance, account,
+	))))
+}
+
+/// 启动前的准备步骤，完成后上下文中已有完整的启动参数
+fn prepare_chain(shared: &Arc<RwLock<StartContext>>) -> SubTaskChain {
+	let mut chain = SubTaskChain::new();
+	chain.add(PrepareAuthTask(Arc::clone(shared)));
+	chain.add(PrepareEnvTask(Arc::clone(shared)));
+	chain
+}
+
+/// 第三方认证账户：准备 authlib-injector 并预取服务器元数据
+struct PrepareAuthTask(Arc<RwLock<StartContext>>);
+
+#[async_trait::async_trait]
+impl SubTask for PrepareAuthTask {
+	async fn execute(&self, _ctx: &SubTaskContext) -> Result<(), TaskError> {
+		let Some(server) = self.0.read().await.yggdrasil_server.clone() else {
+			return Ok(());
+		};
+
+		let dir = paths::data_dir()
+			.map_err(|e| TaskError::Failed(e.to_string()))?
+			.join("authlib-injector");
+		let client = configured_client()?;
+		let jar = ensure_authlib_injector(&client, &dir)
+			.await
+			.map_err(|e| TaskError::Failed(format!("{e:#}")))?;
+		let prefetched = prefetch_metadata(&server).await;
+
+		self.0.write().await.auth.authlib = Some(AuthlibInjector {
+			jar,
+			server,
+			prefetched,
+		});
+		Ok(())
 	}
 }
 
@@ -123,21 +292,32 @@ struct PrepareEnvTask(Arc<RwLock<StartContext>>);
 
 #[async_trait::async_trait]
 impl SubTask for PrepareEnvTask {
-	async fn execute(&self, _ctx: &SubTaskContext) -> Result<(), TaskError> {
+	async fn execute(&self, ctx: &SubTaskContext) -> Result<(), TaskError> {
 		let mut s = self.0.write().await;
 
 		let profile = load_version_profile(&s.game_dir, &s.version_id)
 			.map_err(|e| TaskError::Failed(format!("load profile: {e}")))?;
 
+		// 较早安装的实例没有下载日志配置，缺失时补上，失败则按纯文本日志启动
+		if let Some(request) =
+			log_config_request(&s.game_dir, &profile).filter(|r| !r.dest.exists())
+		{
+			let result = configured_client()?
+				.download(request, |_| {}, Some(ctx.cancelled.clone()))
+				.await;
+			if let Err(e) = result {
+				tracing::warn!("Failed to download log config: {}", e);
+			}
+		}
+
 		let natives_dir = get_natives_directory(&s.game_dir, &s.version_id)
 			.map_err(|e| TaskError::Failed(e.to_string()))?;
 
-		let features = Features::default();
+		let features = s.options.features();
 		extract_natives(&s.game_dir, &profile, &natives_dir, &features)
 			.map_err(|e| TaskError::Failed(e.to_string()))?;
 
-		let java_bin =
-			find_java(s.java_path.take()).map_err(|e| TaskError::Failed(e.to_string()))?;
+		let java_bin = launch_java(s.java_path.take(), &profile, ctx.cancelled.clone()).await?;
 
 		let cp = build_classpath(&s.game_dir, &s.version_id, &profile, &features)
 			.map_err(|e| TaskError::Failed(e.to_string()))?;
@@ -148,29 +328,32 @@ impl SubTask for PrepareEnvTask {
 			.unwrap_or(&s.version_id)
 			.to_string();
 
-		let mut jvm_args = collect_jvm_args(
+		let jvm = collect_jvm_args(
 			&profile,
 			&s.game_dir,
 			&s.version_id,
 			&cp,
 			&assets_index,
-			&s.username,
-			&s.uuid,
+			&s.auth,
 			&natives_dir,
+			&s.options,
 			&features,
 		);
-
-		jvm_args.insert(0, format!("-Xmx{}M", s.max_memory_mb));
-
-		let game_args = collect_game_args(
+		let game = collect_game_args(
 			&s.game_dir,
 			&s.version_id,
 			&profile,
-			&s.username,
-			&s.uuid,
+			&s.auth,
 			&assets_index,
+			&s.options,
 			&features,
 		);
+		report_unresolved(&jvm);
+		report_unresolved(&game);
+
+		let mut jvm_args = jvm.args;
+		jvm_args.insert(0, format!("-Xmx{}M", s.max_memory_mb));
+		let game_args = game.args;
 
 		s.profile = Some(profile);
 		s.natives_dir = Some(natives_dir);
@@ -187,6 +370,77 @@ impl SubTask for PrepareEnvTask {
 	}
 }
 
+fn report_unresolved(args: &ExpandedArgs) {
+	for u in &args.unresolved {
+		tracing::warn!(
+			"Unresolved placeholder {} in launch argument '{}'",
+			u.placeholder,
+			u.argument
+		);
+	}
+}
+
+/// 实例指定了 Java 时直接使用，否则按版本 JSON 的 `javaVersion` 选择
+pub(crate) async fn launch_java(
+	java_path: Option<PathBuf>,
+	profile: &VersionProfile,
+	cancelled: tokio::sync::watch::Receiver<bool>,
+) -> TaskResult<PathBuf> {
+	match (java_path, &profile.java_version) {
+		(None, Some(required)) => {
+			resolve_java(
+				required.major_version,
+				required.component.as_deref(),
+				cancelled,
+			)
+			.await
+		}
+		(prefer, _) => find_java(prefer).map_err(|e| TaskError::Failed(e.to_string())),
+	}
+}
+
+/// 从已发现的 Java 中挑选符合版本要求的。找不到时先重新扫描，
+/// 仍然没有则下载版本指定的 Mojang 运行时
+async fn resolve_java(
+	major: u32,
+	component: Option<&str>,
+	mut cancelled: tokio::sync::watch::Receiver<bool>,
+) -> TaskResult<PathBuf> {
+	let state = AppState::get();
+	let select = || select_java(&state.java_installations(), major).map(|j| j.path.clone());
+	if let Some(java) = select() {
+		return Ok(java);
+	}
+
+	tokio::task::spawn_blocking(|| AppState::get().scan_java())
+		.await
+		.map_err(|e| TaskError::Failed(e.to_string()))?;
+	if let Some(java) = select() {
+		return Ok(java);
+	}
+
+	let Some(component) = component else {
+		return Err(TaskError::JavaNotFound(major));
+	};
+	tracing::info!("No Java {} found, installing runtime {}", major, component);
+	let task = DownloadJavaTask {
+		component: component.to_string(),
+		progress: None,
+	};
+	let mut h = state.task_manager.submit_concurrent(task).await?;
+	let cancel = h.cancel_token();
+	tokio::select! {
+		result = h.result() => {
+			result?;
+		}
+		_ = cancelled.changed() => {
+			let _ = cancel.send(true);
+			return Err(TaskError::Cancelled);
+		}
+	}
+	select().ok_or(TaskError::JavaNotFound(major))
+}
+
 struct LaunchTask(Arc<RwLock<StartContext>>);
 
 #[async_trait::async_trait]
@@ -223,67 +477,73 @@ impl SubTask for LaunchTask {
 			.stdout(std::process::Stdio::piped())
 			.stderr(std::process::Stdio::piped());
 
-		let mut child = cmd
+		let child = cmd
 			.spawn()
 			.context("spawn game process")
 			.map_err(|e| TaskError::Failed(e.to_string()))?;
-
+		let detector = ReadinessDetector::new(VersionFamily::of(profile), &s.ready_patterns);
+		let timeout = s.startup_timeout;
+		let game = AppState::get()
+			.games
+			.spawn(s.version_id.clone(), s.game_dir.clone(), child);
 		drop(s);
 
-		let stdout = child
-			.stdout
-			.take()
-			.ok_or_else(|| TaskError::Failed("no stdout".into()))?;
-		let stderr = child
-			.stderr
-			.take()
-			.ok_or_else(|| TaskError::Failed("no stderr".into()))?;
-
-		let mut stdout_lines = tokio::io::BufReader::new(stdout).lines();
-		let mut stderr_lines = tokio::io::BufReader::new(stderr).lines();
-		let mut cancelled = ctx.cancelled.clone();
-		let start = Instant::now();
-
-		loop {
-			tokio::select! {
-				line = stdout_lines.next_line() => {
-					if let Ok(Some(log)) = line {
-						if is_game_initialized(&log) {
-							tracing::info!("game initialized");
-							return Ok(());
-						}
-					}
-				}
-				line = stderr_lines.next_line() => {
-					if let Ok(Some(log)) = line {
-						if is_game_initialized(&log) {
-							tracing::info!("game initialized");
-							return Ok(());
-						}
-					}
-				}
-				_ = tokio::time::sleep(Duration::from_millis(100)) => {
-					if let Ok(Some(status)) = child.try_wait() {
-						if !status.success() {
-							return Err(TaskError::Failed(format!("Game exited: {:?}", status.code())));
-						}
-						return Ok(());
-					}
-					if start.elapsed() > MAX_WAIT_TIME {
-						tracing::warn!("timeout, assuming game started");
-						return Ok(());
-					}
-				}
-				_ = cancelled.changed() => {
-					let _ = child.kill().await;
-					return Err(TaskError::Cancelled);
-				}
+		let outcome = wait_ready(&game, &detector, timeout, ctx.cancelled.clone()).await?;
+		match &outcome {
+			LaunchOutcome::Started => tracing::info!("game window created"),
+			LaunchOutcome::StillStarting => {
+				tracing::warn!(
+					"game not ready after {:?}, still waiting in background",
+					timeout
+				)
+			}
+			LaunchOutcome::ExitedDuringStartup { status, .. } => {
+				tracing::warn!("game exited during startup: {:?}", status)
 			}
 		}
+		self.0.write().await.outcome = Some(outcome);
+		Ok(())
 	}
 }
 
-fn is_game_initialized(log: &str) -> bool {
-	let l = log.to_lowercase();
-	l.contains("lwjgl version") || l.contains("openal initialized") || l.contains("setting user:")
+/// 游戏进程交给注册表管理，这里只等待窗口创建、退出或超时
+async fn wait_ready(
+	game: &GameProcess,
+	detector: &ReadinessDetector,
+	timeout: Duration,
+	mut cancelled: tokio::sync::watch::Receiver<bool>,
+) -> TaskResult<LaunchOutcome> {
+	let mut lines = game.subscribe();
+	if game.logs().iter().any(|l| detector.is_ready(l)) {
+		return Ok(LaunchOutcome::Started);
+	}
+	let deadline = tokio::time::sleep(timeout);
+	tokio::pin!(deadline);
+
+	loop {
+		tokio::select! {
+			line = lines.recv() => match line {
+				Ok(line) if detector.is_ready(&line) => return Ok(LaunchOutcome::Started),
+				Ok(_) => {}
+				// 订阅落后时丢失的行仍在缓冲中
+				Err(RecvError::Lagged(_)) => {
+					if game.logs().iter().any(|l| detector.is_ready(l)) {
+						return Ok(LaunchOutcome::Started);
+					}
+				}
+				Err(RecvError::Closed) => {}
+			},
+			status = game.wait_exit() => {
+				return Ok(LaunchOutcome::ExitedDuringStartup {
+					status,
+					reason: game.crash().map(|report| report.headline()),
+				});
+			}
+			_ = &mut deadline => return Ok(LaunchOutcome::StillStarting),
+			_ = cancelled.changed() => {
+				game.kill();
+				return Err(TaskError::Cancelled);
+			}
+		}
+	}
 }
@@ -1,30 +1,65 @@
 use crate::core::state::AppState;
 use crate::ui::components::{navbar::Navbar, topbar::Topbar};
 use crate::ui::views::{
-	download::DownloadView, home::HomeView, instances::InstancesView, settings::SettingsView,
-	tasks::TasksView,
+	accounts::AccountsView, console::ConsoleView, download::DownloadView, home::HomeView,
+	instances::InstancesView, settings::SettingsView, tasks::TasksView,
 };
 use gpui::{Context, Entity, Render, Window, div, prelude::*, rgb};
 use gpui_router::{Route, Routes};
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
 
 pub struct HakoApp {
 	topbar: Entity<Topbar>,
 	navbar: Entity<Navbar>,
+	download: Entity<DownloadView>,
+	console: Entity<ConsoleView>,
+	instances: Entity<InstancesView>,
+	accounts: Entity<AccountsView>,
 }
 
 impl HakoApp {
 	pub fn new(ctx: &mut Context<Self>) -> Self {
 		AppState::init();
 
+		// 后台任务的进度和游戏日志不会主动通知界面，有任务或游戏运行时定时重绘
+		ctx.spawn(async move |this, cx| {
+			loop {
+				cx.background_executor().timer(REFRESH_INTERVAL).await;
+				let state = AppState::get();
+				let has_tasks =
+					!state.task_progress.lock().unwrap().is_empty() || state.games.has_running();
+				let alive = this.update(cx, |_, cx| {
+					if has_tasks {
+						cx.notify();
+					}
+				});
+				if alive.is_err() {
+					break;
+				}
+			}
+		})
+		.detach();
+
 		Self {
 			topbar: ctx.new(|_| Topbar::new()),
 			navbar: ctx.new(|cx| Navbar::new(cx)),
+			download: ctx.new(|cx| DownloadView::new(cx)),
+			console: ctx.new(|cx| ConsoleView::new(cx)),
+			instances: ctx.new(InstancesView::new),
+			accounts: ctx.new(AccountsView::new),
 		}
 	}
 }
 
 impl Render for HakoApp {
 	fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+		let download = self.download.clone();
+		let console = self.console.clone();
+		let instances = self.instances.clone();
+		let accounts = self.accounts.clone();
+
 		div()
 			.flex()
 			.flex_col()
@@ -44,18 +79,28 @@ impl Render for HakoApp {
 							.child(
 								Route::new()
 									.path("download")
-									.element(|_, _| DownloadView::render()),
+									.element(move |_, _| download.clone()),
 							)
 							.child(
 								Route::new()
 									.path("instances")
-									.element(|_, _| InstancesView::render()),
+									.element(move |_, _| instances.clone()),
+							)
+							.child(
+								Route::new()
+									.path("accounts")
+									.element(move |_, _| accounts.clone()),
 							)
 							.child(
 								Route::new()
 									.path("tasks")
 									.element(|_, _| TasksView::render()),
 							)
+							.child(
+								Route::new()
+									.path("console")
+									.element(move |_, _| console.clone()),
+							)
 							.child(
 								Route::new()
 									.path("settings")
@@ -1,34 +1,69 @@
 use crate::core::state::AppState;
-use crate::task::game::start::StartGameTask;
+use crate::game::process::ProcessStatus;
+use crate::task::error::TaskError;
+use crate::task::game::start::{LaunchOutcome, StartGameTask};
 use gpui::{Context, Render, Window, div, prelude::*, px, rgb, white};
 use gpui_router::NavLink;
 
-pub struct Navbar;
+pub struct Navbar {
+	/// 最近一次启动的状态
+	status: Option<String>,
+}
 
 impl Navbar {
 	pub fn new(_cx: &mut Context<Self>) -> Self {
-		Self
+		Self { status: None }
 	}
 
-	fn launch_current(&self) {
+	fn launch_current(&mut self, cx: &mut Context<Self>) {
 		let state = AppState::get();
 		let Some(inst) = state.current_instance() else {
 			return;
 		};
 		let tm = state.task_manager.clone();
 		let ver = inst.version.clone();
-		tokio::runtime::Handle::current().spawn(async move {
-			let task = StartGameTask { instance: inst };
-			match tm.submit_blocking(task).await {
-				Ok(mut h) => {
+		self.status = Some(format!("正在启动 {ver}"));
+		cx.notify();
+
+		let rt = tokio::runtime::Handle::current();
+		cx.spawn(async move |this, cx| {
+			let result = rt
+				.spawn(async move {
+					let task = StartGameTask { instance: inst };
+					let mut h = tm.submit_blocking(task).await?;
 					tracing::info!("启动: {} ({})", ver, h.id);
-					tokio::spawn(async move {
-						let _ = h.result().await;
-					});
-				}
-				Err(e) => tracing::error!("启动失败: {}", e),
-			}
-		});
+					h.result().await.map(|outcome| (ver, outcome))
+				})
+				.await;
+			let _ = this.update(cx, |nav, cx| {
+				nav.status = Some(match result {
+					Ok(Ok((ver, outcome))) => outcome_text(&ver, &outcome),
+					Ok(Err(TaskError::Cancelled)) => "已取消启动".into(),
+					Ok(Err(e)) => {
+						tracing::error!("启动失败: {}", e);
+						format!("启动失败: {e}")
+					}
+					Err(e) => format!("启动失败: {e}"),
+				});
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+}
+
+fn outcome_text(ver: &str, outcome: &LaunchOutcome) -> String {
+	match outcome {
+		LaunchOutcome::Started => format!("{ver} 已启动"),
+		LaunchOutcome::StillStarting => format!("{ver} 仍在启动，可在控制台查看日志"),
+		LaunchOutcome::ExitedDuringStartup {
+			reason: Some(reason),
+			..
+		} => format!("{ver} 启动失败：{reason}"),
+		LaunchOutcome::ExitedDuringStartup { status, .. } => match status {
+			ProcessStatus::Exited(Some(0)) => format!("{ver} 在启动过程中退出"),
+			_ => format!("{ver} 启动失败，详见控制台"),
+		},
 	}
 }
 
@@ -46,7 +81,12 @@ impl Render for Navbar {
 			.bg(rgb(0x141414))
 			.border_t_1()
 			.border_color(rgb(0x252525))
-			.child(div())
+			.child(
+				div()
+					.text_sm()
+					.text_color(rgb(0x888888))
+					.children(self.status.clone()),
+			)
 			.child(
 				div()
 					.flex()
@@ -55,6 +95,8 @@ impl Render for Navbar {
 					.child(NavLink::new().to("/").child(nav_label("首页")))
 					.child(NavLink::new().to("/download").child(nav_label("下载")))
 					.child(NavLink::new().to("/instances").child(nav_label("实例")))
+					.child(NavLink::new().to("/accounts").child(nav_label("账户")))
+					.child(NavLink::new().to("/console").child(nav_label("控制台")))
 					.child(NavLink::new().to("/settings").child(nav_label("设置")))
 					.child(
 						div()
@@ -76,7 +118,7 @@ impl Render for Navbar {
 							.when(has_instance, |d| {
 								d.on_mouse_down(
 									gpui::MouseButton::Left,
-									cx.listener(|this, _, _, _| this.launch_current()),
+									cx.listener(|this, _, _, cx| this.launch_current(cx)),
 								)
 							}),
 					),
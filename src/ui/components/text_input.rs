@@ -0,0 +1,104 @@
+use gpui::{
+	App, Context, FocusHandle, Focusable, KeyDownEvent, Render, SharedString, Window, div,
+	prelude::*, px, rgb,
+};
+
+/// 单行文本输入框，仅处理直接输入的字符与退格
+pub struct TextInput {
+	focus_handle: FocusHandle,
+	text: String,
+	placeholder: SharedString,
+	/// 密码框只显示圆点
+	masked: bool,
+}
+
+impl TextInput {
+	pub fn new(placeholder: impl Into<SharedString>, cx: &mut Context<Self>) -> Self {
+		Self {
+			focus_handle: cx.focus_handle(),
+			text: String::new(),
+			placeholder: placeholder.into(),
+			masked: false,
+		}
+	}
+
+	pub fn masked(mut self) -> Self {
+		self.masked = true;
+		self
+	}
+
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	pub fn set_text(&mut self, text: impl Into<String>, cx: &mut Context<Self>) {
+		self.text = text.into();
+		cx.notify();
+	}
+
+	fn on_key_down(&mut self, event: &KeyDownEvent, _: &mut Window, cx: &mut Context<Self>) {
+		let keystroke = &event.keystroke;
+		if keystroke.modifiers.control || keystroke.modifiers.platform {
+			return;
+		}
+
+		match keystroke.key.as_str() {
+			"backspace" => {
+				self.text.pop();
+			}
+			"enter" | "escape" | "tab" => return,
+			_ => match &keystroke.key_char {
+				Some(ch) => self.text.push_str(ch),
+				None => return,
+			},
+		}
+		cx.stop_propagation();
+		cx.notify();
+	}
+}
+
+impl Focusable for TextInput {
+	fn focus_handle(&self, _cx: &App) -> FocusHandle {
+		self.focus_handle.clone()
+	}
+}
+
+impl Render for TextInput {
+	fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+		let focused = self.focus_handle.is_focused(window);
+		let empty = self.text.is_empty();
+
+		div()
+			.track_focus(&self.focus_handle)
+			.on_key_down(cx.listener(Self::on_key_down))
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(|this, _, window, _| window.focus(&this.focus_handle)),
+			)
+			.flex()
+			.items_center()
+			.h(px(32.))
+			.px_3()
+			.rounded_md()
+			.bg(rgb(0x1a1a1a))
+			.border_1()
+			.border_color(if focused {
+				rgb(0x3b82f6)
+			} else {
+				rgb(0x333333)
+			})
+			.cursor_text()
+			.text_sm()
+			.text_color(rgb(if empty { 0x666666 } else { 0xffffff }))
+			.child(if empty {
+				self.placeholder.clone()
+			} else if self.masked {
+				SharedString::from("•".repeat(self.text.chars().count()))
+			} else {
+				SharedString::from(self.text.clone())
+			})
+			.when(focused, |d| {
+				d.child(div().w(px(1.)).h(px(16.)).ml(px(1.)).bg(rgb(0xffffff)))
+			})
+	}
+}
@@ -3,6 +3,7 @@ use gpui::{App, TitlebarOptions, WindowBounds, WindowOptions, px};
 pub mod app;
 pub mod components {
 	pub mod navbar;
+	pub mod text_input;
 	pub mod topbar;
 }
 pub mod views;
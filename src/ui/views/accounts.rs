@@ -0,0 +1,448 @@
+use crate::account::Account;
+use crate::account::error::AuthError;
+use crate::account::microsoft::DeviceCode;
+use crate::core::state::AppState;
+use crate::ui::components::text_input::TextInput;
+use gpui::{Context, Entity, Render, Window, div, prelude::*, rgb};
+
+pub struct AccountsView {
+	offline_name: Entity<TextInput>,
+	client_id: Entity<TextInput>,
+	server: Entity<TextInput>,
+	username: Entity<TextInput>,
+	password: Entity<TextInput>,
+	/// 账户有多个角色时填写要使用的角色名
+	profile: Entity<TextInput>,
+	/// 等待用户在浏览器中输入的设备码
+	device_code: Option<DeviceCode>,
+	/// 正在登录时不再接受新的登录
+	busy: bool,
+	notice: Option<String>,
+}
+
+impl AccountsView {
+	pub fn new(cx: &mut Context<Self>) -> Self {
+		let offline_name = cx.new(|cx| TextInput::new("离线用户名", cx));
+		let client_id = cx.new(|cx| {
+			let mut input = TextInput::new("Azure 应用客户端 ID", cx);
+			input.set_text(AppState::get().config.get().microsoft_client_id, cx);
+			input
+		});
+		let server = cx.new(|cx| TextInput::new("认证服务器地址", cx));
+		let username = cx.new(|cx| TextInput::new("邮箱或用户名", cx));
+		let password = cx.new(|cx| TextInput::new("密码", cx).masked());
+		let profile = cx.new(|cx| TextInput::new("角色名（可选）", cx));
+		for input in [
+			&offline_name,
+			&client_id,
+			&server,
+			&username,
+			&password,
+			&profile,
+		] {
+			cx.observe(input, |_, _, cx| cx.notify()).detach();
+		}
+
+		Self {
+			offline_name,
+			client_id,
+			server,
+			username,
+			password,
+			profile,
+			device_code: None,
+			busy: false,
+			notice: None,
+		}
+	}
+
+	fn add_offline(&mut self, cx: &mut Context<Self>) {
+		let name = self.offline_name.read(cx).text().trim().to_string();
+		if name.is_empty() {
+			return;
+		}
+		AppState::get().accounts.add_offline(name);
+		self.offline_name
+			.update(cx, |input, cx| input.set_text("", cx));
+		cx.notify();
+	}
+
+	/// 设备码登录，拿到设备码后先展示给用户，再等待授权完成
+	fn login_microsoft(&mut self, cx: &mut Context<Self>) {
+		if self.busy {
+			return;
+		}
+		let client_id = self.client_id.read(cx).text().trim().to_string();
+		if client_id.is_empty() {
+			self.notice = Some("请先填写 Azure 应用的客户端 ID".into());
+			cx.notify();
+			return;
+		}
+		let saved = client_id.clone();
+		if let Err(e) = AppState::get()
+			.config
+			.update(|c| c.microsoft_client_id = saved)
+		{
+			tracing::warn!("Failed to save client id: {:#}", e);
+		}
+		self.busy = true;
+		self.device_code = None;
+		self.notice = Some("正在获取设备码...".into());
+		cx.notify();
+
+		let (code_tx, code_rx) = tokio::sync::oneshot::channel();
+		let rt = tokio::runtime::Handle::current();
+		cx.spawn(async move |this, cx| {
+			let login = rt.spawn(async move {
+				AppState::get()
+					.accounts
+					.login_microsoft(&client_id, move |code| {
+						let _ = code_tx.send(code.clone());
+					})
+					.await
+			});
+			if let Ok(code) = code_rx.await {
+				let _ = this.update(cx, |view, cx| {
+					view.device_code = Some(code);
+					view.notice = None;
+					cx.notify();
+				});
+			}
+			let result = login.await;
+			let _ = this.update(cx, |view, cx| {
+				view.busy = false;
+				view.device_code = None;
+				view.notice = Some(match result {
+					Ok(Ok(_)) => "已添加微软账户".into(),
+					Ok(Err(e)) => {
+						tracing::error!("微软登录失败: {}", e);
+						format!("登录失败: {e}")
+					}
+					Err(e) => format!("登录失败: {e}"),
+				});
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+
+	/// 登录第三方认证服务器，账户有多个角色且未填写角色名时提示可选的角色
+	fn login_yggdrasil(&mut self, cx: &mut Context<Self>) {
+		if self.busy {
+			return;
+		}
+		let text = |input: &Entity<TextInput>, cx: &Context<Self>| {
+			input.read(cx).text().trim().to_string()
+		};
+		let server = text(&self.server, cx);
+		let username = text(&self.username, cx);
+		let password = self.password.read(cx).text().to_string();
+		let profile = Some(text(&self.profile, cx)).filter(|p| !p.is_empty());
+		if server.is_empty() || username.is_empty() || password.is_empty() {
+			self.notice = Some("请填写认证服务器、用户名和密码".into());
+			cx.notify();
+			return;
+		}
+		self.busy = true;
+		self.notice = Some(format!("正在登录 {server}..."));
+		cx.notify();
+
+		let rt = tokio::runtime::Handle::current();
+		cx.spawn(async move |this, cx| {
+			let result = rt
+				.spawn(async move {
+					AppState::get()
+						.accounts
+						.login_yggdrasil(&server, &username, &password, profile.as_deref())
+						.await
+				})
+				.await;
+			let _ = this.update(cx, |view, cx| {
+				view.busy = false;
+				view.notice = Some(match result {
+					Ok(Ok(_)) => {
+						view.password.update(cx, |input, cx| input.set_text("", cx));
+						"已添加外置账户".into()
+					}
+					Ok(Err(AuthError::MultipleProfiles(names))) => {
+						format!("该账户有多个角色，请填写角色名：{}", names.join("、"))
+					}
+					Ok(Err(e)) => {
+						tracing::error!("外置登录失败: {}", e);
+						format!("登录失败: {e}")
+					}
+					Err(e) => format!("登录失败: {e}"),
+				});
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+
+	fn render_device_code(code: &DeviceCode) -> impl IntoElement + use<> {
+		let uri = code.verification_uri.clone();
+		div()
+			.flex()
+			.flex_col()
+			.gap_2()
+			.px_3()
+			.py_3()
+			.rounded_md()
+			.bg(rgb(0x1a1a1a))
+			.border_1()
+			.border_color(rgb(0x3b82f6))
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.gap_3()
+					.child(div().text_sm().text_color(rgb(0x888888)).child("验证码"))
+					.child(
+						div()
+							.text_xl()
+							.text_color(rgb(0xffffff))
+							.child(code.user_code.clone()),
+					),
+			)
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.gap_3()
+					.child(div().text_sm().text_color(rgb(0x888888)).child("验证地址"))
+					.child(
+						div()
+							.text_sm()
+							.text_color(rgb(0x3b82f6))
+							.cursor_pointer()
+							.child(uri.clone())
+							.on_mouse_down(gpui::MouseButton::Left, move |_, _, cx| {
+								cx.open_url(&uri)
+							}),
+					),
+			)
+			.children(
+				code.message
+					.clone()
+					.map(|message| div().text_xs().text_color(rgb(0x666666)).child(message)),
+			)
+			.child(
+				div()
+					.text_xs()
+					.text_color(rgb(0x888888))
+					.child("在浏览器中打开验证地址并输入验证码，完成后会自动添加账户"),
+			)
+	}
+
+	fn render_account(
+		idx: usize,
+		account: &Account,
+		current: bool,
+		cx: &mut Context<Self>,
+	) -> impl IntoElement + use<> {
+		let kind = match account {
+			Account::Offline { .. } => "离线".to_string(),
+			Account::Microsoft { .. } => "微软".to_string(),
+			Account::Yggdrasil { server, .. } => format!("外置 · {server}"),
+		};
+
+		div()
+			.flex()
+			.items_center()
+			.justify_between()
+			.px_3()
+			.py_2()
+			.rounded_md()
+			.bg(if current {
+				rgb(0x1e3a5f)
+			} else {
+				rgb(0x1a1a1a)
+			})
+			.border_1()
+			.border_color(if current {
+				rgb(0x3b82f6)
+			} else {
+				rgb(0x333333)
+			})
+			.hover(|s| s.bg(rgb(0x252525)))
+			.cursor_pointer()
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(move |_, _, _, cx| {
+					AppState::get().accounts.select(Some(idx));
+					cx.notify();
+				}),
+			)
+			.child(
+				div()
+					.flex()
+					.flex_col()
+					.gap_1()
+					.child(
+						div()
+							.flex()
+							.items_center()
+							.gap_2()
+							.child(
+								div()
+									.text_color(rgb(0xffffff))
+									.child(account.username().to_string()),
+							)
+							.when(current, |d| {
+								d.child(
+									div()
+										.px_2()
+										.py_1()
+										.rounded_sm()
+										.bg(rgb(0x3b82f6))
+										.text_color(rgb(0xffffff))
+										.text_xs()
+										.child("当前"),
+								)
+							}),
+					)
+					.child(div().text_sm().text_color(rgb(0x666666)).child(kind)),
+			)
+			.child(
+				div()
+					.px_2()
+					.py_1()
+					.rounded_sm()
+					.bg(rgb(0x333333))
+					.hover(|s| s.bg(rgb(0x444444)))
+					.text_color(rgb(0xffffff))
+					.text_xs()
+					.child("移除")
+					.on_mouse_down(
+						gpui::MouseButton::Left,
+						cx.listener(move |_, _, _, cx| {
+							cx.stop_propagation();
+							AppState::get().accounts.remove(idx);
+							cx.notify();
+						}),
+					),
+			)
+	}
+}
+
+fn section(title: &str) -> gpui::Div {
+	div()
+		.flex()
+		.flex_col()
+		.gap_3()
+		.p_4()
+		.rounded_lg()
+		.bg(rgb(0x141414))
+		.child(
+			div()
+				.text_lg()
+				.text_color(rgb(0xffffff))
+				.child(title.to_string()),
+		)
+}
+
+fn button(label: &'static str, enabled: bool) -> gpui::Div {
+	div()
+		.px_4()
+		.py_2()
+		.rounded_md()
+		.bg(if enabled {
+			rgb(0x3b82f6)
+		} else {
+			rgb(0x333333)
+		})
+		.when(enabled, |d| {
+			d.hover(|s| s.bg(rgb(0x2563eb))).cursor_pointer()
+		})
+		.text_color(rgb(if enabled { 0xffffff } else { 0x666666 }))
+		.text_sm()
+		.child(label)
+}
+
+impl Render for AccountsView {
+	fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+		let accounts = AppState::get().accounts.list();
+		let current = AppState::get().accounts.current_index();
+		let idle = !self.busy;
+
+		div()
+			.flex()
+			.flex_col()
+			.p_4()
+			.gap_4()
+			.child(div().text_xl().text_color(rgb(0xffffff)).child("账户"))
+			.child(
+				section("账户列表")
+					.when(accounts.is_empty(), |d| {
+						d.child(
+							div()
+								.text_sm()
+								.text_color(rgb(0x666666))
+								.child("暂无账户，未选择账户时以离线账户 Player 启动"),
+						)
+					})
+					.children(accounts.iter().enumerate().map(|(idx, account)| {
+						Self::render_account(idx, account, current == Some(idx), cx)
+					})),
+			)
+			.child(
+				section("离线账户").child(
+					div()
+						.flex()
+						.items_center()
+						.gap_3()
+						.child(div().flex_grow().child(self.offline_name.clone()))
+						.child(button("添加", true).on_mouse_down(
+							gpui::MouseButton::Left,
+							cx.listener(|this, _, _, cx| this.add_offline(cx)),
+						)),
+				),
+			)
+			.child(
+				section("微软账户")
+					.child(
+						div()
+							.flex()
+							.items_center()
+							.gap_3()
+							.child(div().flex_grow().child(self.client_id.clone()))
+							.child(button("登录", idle).when(idle, |d| {
+								d.on_mouse_down(
+									gpui::MouseButton::Left,
+									cx.listener(|this, _, _, cx| this.login_microsoft(cx)),
+								)
+							})),
+					)
+					.when_some(self.device_code.clone(), |d, code| {
+						d.child(Self::render_device_code(&code))
+					}),
+			)
+			.child(
+				section("外置账户")
+					.child(self.server.clone())
+					.child(
+						div()
+							.flex()
+							.gap_3()
+							.child(div().flex_1().child(self.username.clone()))
+							.child(div().flex_1().child(self.password.clone())),
+					)
+					.child(
+						div()
+							.flex()
+							.items_center()
+							.gap_3()
+							.child(div().flex_grow().child(self.profile.clone()))
+							.child(button("登录", idle).when(idle, |d| {
+								d.on_mouse_down(
+									gpui::MouseButton::Left,
+									cx.listener(|this, _, _, cx| this.login_yggdrasil(cx)),
+								)
+							})),
+					),
+			)
+			.when_some(self.notice.clone(), |d, notice| {
+				d.child(div().text_sm().text_color(rgb(0x888888)).child(notice))
+			})
+	}
+}
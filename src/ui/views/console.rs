@@ -0,0 +1,423 @@
+use crate::core::paths;
+use crate::core::state::AppState;
+use crate::game::crash::{CrashReport, export_bundle};
+use crate::game::log4j::LogLevel;
+use crate::game::process::{GameProcess, LogLine, LogStream, ProcessStatus};
+use gpui::{Context, Render, Window, div, prelude::*, px, rgb};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 界面中显示的最近日志行数，完整内容保留在缓冲中
+const VISIBLE_LINES: usize = 500;
+
+const LEVEL_FILTERS: [(&str, Option<LogLevel>); 4] = [
+	("全部", None),
+	("信息", Some(LogLevel::Info)),
+	("警告", Some(LogLevel::Warn)),
+	("错误", Some(LogLevel::Error)),
+];
+
+pub struct ConsoleView {
+	selected: Option<u64>,
+	/// 只显示不低于该级别的日志
+	min_level: Option<LogLevel>,
+	notice: Option<String>,
+}
+
+impl ConsoleView {
+	pub fn new(_cx: &mut Context<Self>) -> Self {
+		Self {
+			selected: None,
+			min_level: None,
+			notice: None,
+		}
+	}
+
+	fn select(&mut self, id: u64, cx: &mut Context<Self>) {
+		self.selected = Some(id);
+		self.notice = None;
+		cx.notify();
+	}
+
+	fn shows(&self, line: &LogLine) -> bool {
+		match self.min_level {
+			None => true,
+			Some(min) => line_level(line).is_some_and(|level| level >= min),
+		}
+	}
+
+	fn render_level_filter(
+		&self,
+		label: &'static str,
+		level: Option<LogLevel>,
+		cx: &mut Context<Self>,
+	) -> impl IntoElement + use<> {
+		let on = self.min_level == level;
+		div()
+			.px_2()
+			.py_1()
+			.rounded_md()
+			.text_xs()
+			.cursor_pointer()
+			.bg(if on { rgb(0x1e3a5f) } else { rgb(0x1a1a1a) })
+			.border_1()
+			.border_color(if on { rgb(0x3b82f6) } else { rgb(0x333333) })
+			.text_color(rgb(if on { 0xffffff } else { 0x888888 }))
+			.child(label)
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(move |this, _, _, cx| {
+					this.min_level = level;
+					cx.notify();
+				}),
+			)
+	}
+
+	fn export_crash(&mut self, game: Arc<GameProcess>, cx: &mut Context<Self>) {
+		let Some(report) = game.crash() else {
+			return;
+		};
+		self.notice = Some("正在导出崩溃报告...".into());
+		cx.notify();
+
+		let rt = tokio::runtime::Handle::current();
+		cx.spawn(async move |this, cx| {
+			let result = rt
+				.spawn_blocking(move || -> anyhow::Result<std::path::PathBuf> {
+					let stamp = SystemTime::now()
+						.duration_since(UNIX_EPOCH)
+						.map(|d| d.as_secs())
+						.unwrap_or_default();
+					let dest = paths::data_dir()?
+						.join("crash-bundles")
+						.join(format!("{}-{}.zip", report.instance, stamp));
+					export_bundle(&report, &game.game_dir, &dest)?;
+					Ok(dest)
+				})
+				.await;
+			let _ = this.update(cx, |view, cx| {
+				view.notice = Some(match result {
+					Ok(Ok(dest)) => format!("已导出到 {}", dest.display()),
+					Ok(Err(e)) => format!("导出失败: {e:#}"),
+					Err(e) => format!("导出失败: {e}"),
+				});
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+
+	fn render_crash(
+		&self,
+		game: &Arc<GameProcess>,
+		report: &CrashReport,
+		cx: &mut Context<Self>,
+	) -> impl IntoElement {
+		let game = Arc::clone(game);
+
+		div()
+			.flex()
+			.flex_col()
+			.gap_2()
+			.p_3()
+			.rounded_md()
+			.bg(rgb(0x2a1414))
+			.border_1()
+			.border_color(rgb(0x7f1d1d))
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.justify_between()
+					.child(
+						div()
+							.text_color(rgb(0xffffff))
+							.child(format!("游戏崩溃：{}", report.headline())),
+					)
+					.child(
+						div()
+							.px_2()
+							.py_1()
+							.rounded_sm()
+							.bg(rgb(0x3b82f6))
+							.hover(|s| s.bg(rgb(0x2563eb)))
+							.cursor_pointer()
+							.text_color(rgb(0xffffff))
+							.text_xs()
+							.child("导出崩溃报告")
+							.on_mouse_down(
+								gpui::MouseButton::Left,
+								cx.listener(move |this, _, _, cx| {
+									this.export_crash(Arc::clone(&game), cx)
+								}),
+							),
+					),
+			)
+			.children(report.diagnoses.iter().map(|d| {
+				div()
+					.flex()
+					.flex_col()
+					.child(div().text_sm().text_color(rgb(0xfca5a5)).child(format!(
+						"{}：{}",
+						d.kind.title(),
+						d.detail
+					)))
+					.child(
+						div()
+							.text_xs()
+							.text_color(rgb(0x888888))
+							.child(d.kind.advice()),
+					)
+			}))
+			.when_some(self.notice.clone(), |d, notice| {
+				d.child(div().text_xs().text_color(rgb(0x888888)).child(notice))
+			})
+	}
+
+	fn render_game_tab(
+		&self,
+		game: &Arc<GameProcess>,
+		cx: &mut Context<Self>,
+	) -> impl IntoElement + use<> {
+		let is_sel = self.selected == Some(game.id);
+		let id = game.id;
+
+		div()
+			.flex()
+			.items_center()
+			.gap_2()
+			.px_3()
+			.py_2()
+			.rounded_md()
+			.bg(if is_sel { rgb(0x1e3a5f) } else { rgb(0x1a1a1a) })
+			.border_1()
+			.border_color(if is_sel { rgb(0x3b82f6) } else { rgb(0x333333) })
+			.hover(|s| s.bg(rgb(0x252525)))
+			.cursor_pointer()
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(move |this, _, _, cx| this.select(id, cx)),
+			)
+			.child(
+				div()
+					.w(px(8.))
+					.h(px(8.))
+					.rounded_full()
+					.bg(status_color(game.status())),
+			)
+			.child(
+				div()
+					.text_sm()
+					.text_color(rgb(0xffffff))
+					.child(game.instance.clone()),
+			)
+			.child(
+				div()
+					.text_xs()
+					.text_color(rgb(0x888888))
+					.child(format_uptime(game.uptime())),
+			)
+	}
+
+	fn render_game(&self, game: &Arc<GameProcess>, cx: &mut Context<Self>) -> impl IntoElement {
+		let logs: Vec<_> = game.logs().into_iter().filter(|l| self.shows(l)).collect();
+		let skipped = logs.len().saturating_sub(VISIBLE_LINES) + game.dropped_lines();
+		let running = game.is_running();
+		let id = game.id;
+		let pid = game.pid.map(|p| p.to_string());
+		let summary = format!(
+			"{} | PID {} | 运行 {}",
+			status_text(game.status()),
+			pid.as_deref().unwrap_or("-"),
+			format_uptime(game.uptime())
+		);
+		let action = {
+			let game = Arc::clone(game);
+			div()
+				.px_2()
+				.py_1()
+				.rounded_sm()
+				.bg(if running {
+					rgb(0xef4444)
+				} else {
+					rgb(0x333333)
+				})
+				.hover(|s| {
+					s.bg(if running {
+						rgb(0xdc2626)
+					} else {
+						rgb(0x444444)
+					})
+				})
+				.cursor_pointer()
+				.text_color(rgb(0xffffff))
+				.text_xs()
+				.child(if running { "结束进程" } else { "移除" })
+				.on_mouse_down(
+					gpui::MouseButton::Left,
+					cx.listener(move |this, _, _, cx| {
+						if game.is_running() {
+							game.kill();
+						} else {
+							AppState::get().games.remove(id);
+							this.selected = None;
+						}
+						cx.notify();
+					}),
+				)
+		};
+
+		div()
+			.flex()
+			.flex_col()
+			.gap_2()
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.justify_between()
+					.child(div().text_sm().text_color(rgb(0x888888)).child(summary))
+					.child(action),
+			)
+			.child(
+				div().flex().gap_2().children(
+					LEVEL_FILTERS
+						.iter()
+						.map(|&(label, level)| self.render_level_filter(label, level, cx)),
+				),
+			)
+			.when_some(game.crash(), |d, report| {
+				d.child(self.render_crash(game, &report, cx))
+			})
+			.child(
+				div()
+					.id("console-log")
+					.flex()
+					.flex_col()
+					.h(px(360.))
+					.overflow_y_scroll()
+					.p_2()
+					.rounded_md()
+					.bg(rgb(0x111111))
+					.border_1()
+					.border_color(rgb(0x252525))
+					.when(skipped > 0, |d| {
+						d.child(
+							div()
+								.text_xs()
+								.text_color(rgb(0x666666))
+								.child(format!("… 省略了较早的 {skipped} 行")),
+						)
+					})
+					.children(
+						logs.into_iter()
+							.rev()
+							.take(VISIBLE_LINES)
+							.rev()
+							.map(|line| {
+								div()
+									.text_xs()
+									.text_color(line_color(&line))
+									.child(line.text)
+							}),
+					),
+			)
+	}
+}
+
+impl Render for ConsoleView {
+	fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+		let games = AppState::get().games.list();
+		let selected = self
+			.selected
+			.and_then(|id| games.iter().find(|g| g.id == id))
+			.or_else(|| games.last())
+			.cloned();
+		self.selected = selected.as_ref().map(|g| g.id);
+
+		div()
+			.flex()
+			.flex_col()
+			.flex_grow()
+			.p_4()
+			.gap_3()
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.justify_between()
+					.child(
+						div()
+							.text_xl()
+							.text_color(rgb(0xffffff))
+							.child("游戏控制台"),
+					)
+					.child(div().text_sm().text_color(rgb(0x888888)).child(format!(
+						"{} 个运行中",
+						games.iter().filter(|g| g.is_running()).count()
+					))),
+			)
+			.child(match selected {
+				None => div()
+					.flex()
+					.items_center()
+					.justify_center()
+					.py_8()
+					.child(div().text_color(rgb(0x888888)).child("没有运行中的游戏"))
+					.into_any_element(),
+				Some(game) => div()
+					.flex()
+					.flex_col()
+					.gap_3()
+					.child(
+						div()
+							.flex()
+							.flex_wrap()
+							.gap_2()
+							.children(games.iter().map(|g| self.render_game_tab(g, cx))),
+					)
+					.child(self.render_game(&game, cx))
+					.into_any_element(),
+			})
+	}
+}
+
+/// 没有级别的 stderr 输出多为异常堆栈，按错误处理
+fn line_level(line: &LogLine) -> Option<LogLevel> {
+	match (line.level, line.stream) {
+		(Some(level), _) => Some(level),
+		(None, LogStream::Stderr) => Some(LogLevel::Error),
+		(None, LogStream::Stdout) => None,
+	}
+}
+
+fn line_color(line: &LogLine) -> gpui::Rgba {
+	match line_level(line) {
+		Some(LogLevel::Fatal | LogLevel::Error) => rgb(0xf87171),
+		Some(LogLevel::Warn) => rgb(0xfbbf24),
+		Some(LogLevel::Debug | LogLevel::Trace) => rgb(0x777777),
+		Some(LogLevel::Info) | None => rgb(0xcccccc),
+	}
+}
+
+fn status_color(status: ProcessStatus) -> gpui::Rgba {
+	match status {
+		ProcessStatus::Running => rgb(0x22c55e),
+		ProcessStatus::Exited(Some(0)) => rgb(0x888888),
+		ProcessStatus::Exited(_) | ProcessStatus::Killed => rgb(0xef4444),
+	}
+}
+
+fn status_text(status: ProcessStatus) -> String {
+	match status {
+		ProcessStatus::Running => "运行中".into(),
+		ProcessStatus::Exited(Some(code)) => format!("已退出 ({code})"),
+		ProcessStatus::Exited(None) => "已退出".into(),
+		ProcessStatus::Killed => "已结束".into(),
+	}
+}
+
+fn format_uptime(d: Duration) -> String {
+	let secs = d.as_secs();
+	format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
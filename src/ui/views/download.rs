@@ -1,14 +1,595 @@
-use gpui::{div, prelude::*, rgb};
+use crate::core::state::AppState;
+use crate::game::loader::{LoaderKind, LoaderVersion, fetch_loader_versions};
+use crate::game::manifest::{ManifestCache, ManifestVersion, VersionManifest, VersionType};
+use crate::task::game::download::{DownloadGameTask, ProgressRef, configured_client};
+use crate::task::game::forge::InstallForgeTask;
+use crate::task::game::loader::InstallLoaderTask;
+use crate::task::main_task::ConcurrentTask;
+use crate::ui::components::text_input::TextInput;
+use gpui::{Context, Entity, Render, Window, div, prelude::*, px, rgb};
+use std::sync::Arc;
 
-pub struct DownloadView;
+enum ManifestState {
+	Loading,
+	Loaded(Arc<VersionManifest>),
+	Failed(String),
+}
+
+enum LoaderVersionsState {
+	Idle,
+	Loading,
+	Loaded(Vec<LoaderVersion>),
+	Failed(String),
+}
+
+struct VersionFilter {
+	release: bool,
+	snapshot: bool,
+	old: bool,
+}
+
+impl VersionFilter {
+	fn allows(&self, version_type: VersionType) -> bool {
+		match version_type {
+			VersionType::Release => self.release,
+			VersionType::Snapshot => self.snapshot,
+			VersionType::OldBeta | VersionType::OldAlpha | VersionType::Unknown => self.old,
+		}
+	}
+}
+
+pub struct DownloadView {
+	search: Entity<TextInput>,
+	name: Entity<TextInput>,
+	manifest: ManifestState,
+	filter: VersionFilter,
+	selected: Option<String>,
+	/// 为空表示安装原版
+	loader: Option<LoaderKind>,
+	loader_versions: LoaderVersionsState,
+	loader_version: Option<String>,
+	notice: Option<String>,
+}
 
 impl DownloadView {
-	pub fn render() -> impl IntoElement {
-		div().flex().flex_col().flex_grow().p_4().gap_4().child(
+	pub fn new(cx: &mut Context<Self>) -> Self {
+		let search = cx.new(|cx| TextInput::new("搜索版本", cx));
+		let name = cx.new(|cx| TextInput::new("实例名（默认为版本号）", cx));
+		cx.observe(&search, |_, _, cx| cx.notify()).detach();
+		cx.observe(&name, |_, _, cx| cx.notify()).detach();
+
+		// 先展示磁盘缓存，再在后台重新验证
+		let manifest = ManifestCache::open_default()
+			.ok()
+			.and_then(|cache| cache.load_cached())
+			.map(|m| ManifestState::Loaded(Arc::new(m)))
+			.unwrap_or(ManifestState::Loading);
+
+		let mut view = Self {
+			search,
+			name,
+			manifest,
+			filter: VersionFilter {
+				release: true,
+				snapshot: false,
+				old: false,
+			},
+			selected: None,
+			loader: None,
+			loader_versions: LoaderVersionsState::Idle,
+			loader_version: None,
+			notice: None,
+		};
+		view.refresh(cx);
+		view
+	}
+
+	fn refresh(&mut self, cx: &mut Context<Self>) {
+		if !matches!(self.manifest, ManifestState::Loaded(_)) {
+			self.manifest = ManifestState::Loading;
+		}
+
+		let rt = tokio::runtime::Handle::current();
+		cx.spawn(async move |this, cx| {
+			let result = rt.spawn(fetch_manifest()).await;
+			let _ = this.update(cx, |view, cx| {
+				match result {
+					Ok(Ok(manifest)) => view.manifest = ManifestState::Loaded(Arc::new(manifest)),
+					Ok(Err(e)) => {
+						tracing::warn!("加载版本列表失败: {:#}", e);
+						if !matches!(view.manifest, ManifestState::Loaded(_)) {
+							view.manifest = ManifestState::Failed(format!("{e:#}"));
+						}
+					}
+					Err(e) => view.manifest = ManifestState::Failed(e.to_string()),
+				}
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+
+	fn select_version(&mut self, id: String, cx: &mut Context<Self>) {
+		self.selected = Some(id);
+		self.notice = None;
+		self.load_loader_versions(cx);
+		cx.notify();
+	}
+
+	fn set_loader(&mut self, loader: Option<LoaderKind>, cx: &mut Context<Self>) {
+		self.loader = loader;
+		self.load_loader_versions(cx);
+		cx.notify();
+	}
+
+	fn load_loader_versions(&mut self, cx: &mut Context<Self>) {
+		self.loader_version = None;
+		let (Some(kind), Some(game)) = (self.loader, self.selected.clone()) else {
+			self.loader_versions = LoaderVersionsState::Idle;
+			return;
+		};
+		self.loader_versions = LoaderVersionsState::Loading;
+
+		let rt = tokio::runtime::Handle::current();
+		let requested = game.clone();
+		cx.spawn(async move |this, cx| {
+			let result = rt
+				.spawn(async move {
+					let client = configured_client()?;
+					fetch_loader_versions(&client, kind, &requested).await
+				})
+				.await;
+			let _ = this.update(cx, |view, cx| {
+				// 期间切换了版本或加载器则丢弃结果
+				if view.loader != Some(kind) || view.selected.as_deref() != Some(game.as_str()) {
+					return;
+				}
+				view.loader_versions = match result {
+					Ok(Ok(versions)) => {
+						view.loader_version = versions
+							.iter()
+							.find(|v| v.is_stable())
+							.or(versions.first())
+							.map(|v| v.version.clone());
+						LoaderVersionsState::Loaded(versions)
+					}
+					Ok(Err(e)) => LoaderVersionsState::Failed(format!("{e:#}")),
+					Err(e) => LoaderVersionsState::Failed(e.to_string()),
+				};
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+
+	fn install(&mut self, cx: &mut Context<Self>) {
+		let Some(version) = self.selected.clone() else {
+			return;
+		};
+		let loader = match (self.loader, self.loader_version.clone()) {
+			(None, _) => None,
+			(Some(kind), Some(loader_version)) => Some((kind, loader_version)),
+			(Some(kind), None) => {
+				self.notice = Some(format!("请选择 {} 版本", kind.name()));
+				cx.notify();
+				return;
+			}
+		};
+		let default_name = match &loader {
+			Some((kind, loader_version)) => kind.default_instance_name(&version, loader_version),
+			None => version.clone(),
+		};
+		let input = self.name.read(cx).text().trim().to_string();
+		let name = (!input.is_empty() && input != default_name).then_some(input);
+		let instance = name.clone().unwrap_or(default_name);
+
+		let cluster_path = AppState::get().cluster_path();
+		self.notice = Some(match validate_instance_name(&instance) {
+			Err(msg) => msg,
+			Ok(()) if cluster_path.join("versions").join(&instance).exists() => {
+				format!("实例 {} 已存在", instance)
+			}
+			Ok(()) => {
+				let progress = ProgressRef::default();
+				match loader {
+					Some((loader, loader_version)) if let Some(flavor) = loader.forge_flavor() => {
+						submit_install(
+							InstallForgeTask {
+								cluster_path,
+								game_version: version,
+								flavor,
+								version: loader_version,
+								name,
+								progress: Some(Arc::clone(&progress)),
+							},
+							progress,
+							instance.clone(),
+						)
+					}
+					Some((loader, loader_version)) => submit_install(
+						InstallLoaderTask {
+							cluster_path,
+							game_version: version,
+							loader,
+							loader_version,
+							name,
+							progress: Some(Arc::clone(&progress)),
+						},
+						progress,
+						instance.clone(),
+					),
+					None => submit_install(
+						DownloadGameTask {
+							cluster_path,
+							version,
+							name,
+							progress: Some(Arc::clone(&progress)),
+						},
+						progress,
+						instance.clone(),
+					),
+				}
+				self.name.update(cx, |input, cx| input.set_text("", cx));
+				format!("已添加下载任务 {}，可在任务列表查看进度", instance)
+			}
+		});
+		cx.notify();
+	}
+
+	fn render_toggle(
+		&self,
+		label: &'static str,
+		on: bool,
+		toggle: fn(&mut VersionFilter),
+		cx: &mut Context<Self>,
+	) -> impl IntoElement {
+		div()
+			.px_3()
+			.py_1()
+			.rounded_md()
+			.text_sm()
+			.cursor_pointer()
+			.bg(if on { rgb(0x1e3a5f) } else { rgb(0x1a1a1a) })
+			.border_1()
+			.border_color(if on { rgb(0x3b82f6) } else { rgb(0x333333) })
+			.text_color(rgb(if on { 0xffffff } else { 0x888888 }))
+			.child(label)
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(move |this, _, _, cx| {
+					toggle(&mut this.filter);
+					cx.notify();
+				}),
+			)
+	}
+
+	fn render_version_item(
+		&self,
+		version: &ManifestVersion,
+		cx: &mut Context<Self>,
+	) -> impl IntoElement + use<> {
+		let is_sel = self.selected.as_deref() == Some(version.id.as_str());
+		let id = version.id.clone();
+		let date = version
+			.release_time
+			.get(..10)
+			.unwrap_or(&version.release_time)
+			.to_string();
+
+		div()
+			.flex()
+			.items_center()
+			.justify_between()
+			.px_3()
+			.py_2()
+			.rounded_md()
+			.bg(if is_sel { rgb(0x1e3a5f) } else { rgb(0x1a1a1a) })
+			.border_1()
+			.border_color(if is_sel { rgb(0x3b82f6) } else { rgb(0x333333) })
+			.hover(|s| s.bg(rgb(0x252525)))
+			.cursor_pointer()
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(move |this, _, _, cx| this.select_version(id.clone(), cx)),
+			)
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.gap_2()
+					.child(div().text_color(rgb(0xffffff)).child(version.id.clone()))
+					.child(
+						div()
+							.px_2()
+							.py_1()
+							.rounded_sm()
+							.bg(rgb(0x252525))
+							.text_color(rgb(0xaaaaaa))
+							.text_xs()
+							.child(type_label(version.version_type)),
+					),
+			)
+			.child(div().text_sm().text_color(rgb(0x666666)).child(date))
+	}
+
+	fn render_versions(&self, cx: &mut Context<Self>) -> impl IntoElement {
+		let manifest = match &self.manifest {
+			ManifestState::Loading => return placeholder("正在加载版本列表…").into_any_element(),
+			ManifestState::Failed(e) => {
+				return placeholder(&format!("加载版本列表失败: {}", e)).into_any_element();
+			}
+			ManifestState::Loaded(m) => Arc::clone(m),
+		};
+
+		let query = self.search.read(cx).text().trim().to_lowercase();
+		let versions: Vec<_> = manifest
+			.versions
+			.iter()
+			.filter(|v| self.filter.allows(v.version_type))
+			.filter(|v| query.is_empty() || v.id.to_lowercase().contains(&query))
+			.collect();
+
+		if versions.is_empty() {
+			return placeholder("没有符合条件的版本").into_any_element();
+		}
+
+		div()
+			.flex()
+			.flex_col()
+			.gap_2()
+			.children(
+				versions
+					.into_iter()
+					.map(|v| self.render_version_item(v, cx)),
+			)
+			.into_any_element()
+	}
+
+	fn render_loader_picker(&self, cx: &mut Context<Self>) -> impl IntoElement {
+		let chip = |label: String, on: bool| {
 			div()
-				.text_xl()
-				.text_color(rgb(0xffffff))
-				.child("Download views"),
-		)
+				.px_3()
+				.py_1()
+				.rounded_md()
+				.text_sm()
+				.cursor_pointer()
+				.bg(if on { rgb(0x1e3a5f) } else { rgb(0x1a1a1a) })
+				.border_1()
+				.border_color(if on { rgb(0x3b82f6) } else { rgb(0x333333) })
+				.text_color(rgb(if on { 0xffffff } else { 0x888888 }))
+				.child(label)
+		};
+
+		let kinds = std::iter::once(None)
+			.chain(LoaderKind::ALL.map(Some))
+			.map(|kind| {
+				chip(
+					kind.map_or("原版", |k| k.name()).to_string(),
+					self.loader == kind,
+				)
+				.on_mouse_down(
+					gpui::MouseButton::Left,
+					cx.listener(move |this, _, _, cx| this.set_loader(kind, cx)),
+				)
+			});
+
+		let versions = match &self.loader_versions {
+			LoaderVersionsState::Idle => div(),
+			LoaderVersionsState::Loading => div()
+				.text_sm()
+				.text_color(rgb(0x888888))
+				.child("正在加载加载器版本…"),
+			LoaderVersionsState::Failed(e) => div()
+				.text_sm()
+				.text_color(rgb(0xef4444))
+				.child(format!("加载失败: {}", e)),
+			LoaderVersionsState::Loaded(versions) if versions.is_empty() => div()
+				.text_sm()
+				.text_color(rgb(0x888888))
+				.child("该版本没有可用的加载器"),
+			LoaderVersionsState::Loaded(versions) => {
+				div()
+					.flex()
+					.flex_wrap()
+					.gap_2()
+					.children(versions.iter().take(12).map(|v| {
+						let id = v.version.clone();
+						let label = if v.is_stable() {
+							v.version.clone()
+						} else {
+							format!("{} (beta)", v.version)
+						};
+						chip(label, self.loader_version.as_deref() == Some(id.as_str()))
+							.on_mouse_down(
+								gpui::MouseButton::Left,
+								cx.listener(move |this, _, _, cx| {
+									this.loader_version = Some(id.clone());
+									cx.notify();
+								}),
+							)
+					}))
+			}
+		};
+
+		div()
+			.flex()
+			.flex_col()
+			.gap_2()
+			.child(div().flex().gap_2().children(kinds))
+			.child(versions)
+	}
+
+	fn render_install_bar(&self, version: &str, cx: &mut Context<Self>) -> impl IntoElement {
+		div()
+			.flex()
+			.flex_col()
+			.gap_3()
+			.px_3()
+			.py_3()
+			.rounded_md()
+			.bg(rgb(0x141414))
+			.border_1()
+			.border_color(rgb(0x252525))
+			.child(self.render_loader_picker(cx))
+			.child(self.render_install_row(version, cx))
+	}
+
+	fn render_install_row(&self, version: &str, cx: &mut Context<Self>) -> impl IntoElement {
+		div()
+			.flex()
+			.items_center()
+			.gap_3()
+			.child(
+				div()
+					.text_sm()
+					.text_color(rgb(0xaaaaaa))
+					.child(format!("安装 {}", version)),
+			)
+			.child(div().flex_grow().child(self.name.clone()))
+			.child(
+				div()
+					.px_4()
+					.py_2()
+					.rounded_md()
+					.bg(rgb(0x22c55e))
+					.hover(|s| s.bg(rgb(0x16a34a)))
+					.cursor_pointer()
+					.text_color(rgb(0xffffff))
+					.text_sm()
+					.child("安装")
+					.on_mouse_down(
+						gpui::MouseButton::Left,
+						cx.listener(|this, _, _, cx| this.install(cx)),
+					),
+			)
+	}
+}
+
+impl Render for DownloadView {
+	fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+		let latest = match &self.manifest {
+			ManifestState::Loaded(m) => format!(
+				"最新正式版 {} · 最新快照 {}",
+				m.latest.release, m.latest.snapshot
+			),
+			_ => String::new(),
+		};
+		let selected = self.selected.clone();
+
+		div()
+			.flex()
+			.flex_col()
+			.flex_grow()
+			.p_4()
+			.gap_3()
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.justify_between()
+					.child(div().text_xl().text_color(rgb(0xffffff)).child("下载游戏"))
+					.child(div().text_sm().text_color(rgb(0x888888)).child(latest)),
+			)
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.gap_2()
+					.child(div().w(px(240.)).child(self.search.clone()))
+					.child(self.render_toggle(
+						"正式版",
+						self.filter.release,
+						|f| f.release = !f.release,
+						cx,
+					))
+					.child(self.render_toggle(
+						"快照",
+						self.filter.snapshot,
+						|f| f.snapshot = !f.snapshot,
+						cx,
+					))
+					.child(self.render_toggle("远古版", self.filter.old, |f| f.old = !f.old, cx))
+					.child(div().flex_grow())
+					.child(
+						div()
+							.px_3()
+							.py_1()
+							.rounded_md()
+							.text_sm()
+							.text_color(rgb(0xaaaaaa))
+							.hover(|s| s.bg(rgb(0x252525)))
+							.cursor_pointer()
+							.child("刷新")
+							.on_mouse_down(
+								gpui::MouseButton::Left,
+								cx.listener(|this, _, _, cx| this.refresh(cx)),
+							),
+					),
+			)
+			.when_some(selected, |d, version| {
+				d.child(self.render_install_bar(&version, cx))
+			})
+			.when_some(self.notice.clone(), |d, notice| {
+				d.child(div().text_sm().text_color(rgb(0x888888)).child(notice))
+			})
+			.child(self.render_versions(cx))
 	}
 }
+
+async fn fetch_manifest() -> anyhow::Result<VersionManifest> {
+	let client = configured_client()?;
+	ManifestCache::open_default()?.load(&client).await
+}
+
+/// 提交安装任务，完成后重新扫描实例，失败时把错误写入进度
+fn submit_install<T>(task: T, progress: ProgressRef, label: String)
+where
+	T: ConcurrentTask<Output = ()> + 'static,
+{
+	let tm = AppState::get().task_manager.clone();
+	tokio::runtime::Handle::current().spawn(async move {
+		match tm.submit_concurrent(task).await {
+			Ok(mut h) => {
+				AppState::get().track_progress(h.id, Arc::clone(&progress));
+				match h.result().await {
+					Ok(()) => AppState::get().scan_instances(),
+					Err(e) => {
+						tracing::error!("下载 {} 失败: {}", label, e);
+						progress.write().await.message = format!("{} 下载失败: {}", label, e);
+					}
+				}
+			}
+			Err(e) => tracing::error!("提交下载任务失败: {}", e),
+		}
+	});
+}
+
+fn type_label(version_type: VersionType) -> &'static str {
+	match version_type {
+		VersionType::Release => "正式版",
+		VersionType::Snapshot => "快照",
+		VersionType::OldBeta => "Beta",
+		VersionType::OldAlpha => "Alpha",
+		VersionType::Unknown => "其他",
+	}
+}
+
+fn validate_instance_name(name: &str) -> Result<(), String> {
+	const INVALID: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+	if name.is_empty() || name == "." || name == ".." {
+		return Err("实例名无效".into());
+	}
+	if name.contains(INVALID) || name.ends_with('.') || name.ends_with(' ') {
+		return Err(format!("实例名不能包含 {} 或以点、空格结尾", "/\\:*?\"<>|"));
+	}
+	Ok(())
+}
+
+fn placeholder(text: &str) -> impl IntoElement {
+	div()
+		.flex()
+		.items_center()
+		.justify_center()
+		.py_8()
+		.child(div().text_color(rgb(0x888888)).child(text.to_string()))
+}
@@ -1,10 +1,135 @@
 use crate::core::state::AppState;
-use gpui::{div, prelude::*, rgb};
+use crate::game::instance::GameInstance;
+use crate::game::script::ScriptFormat;
+use crate::task::error::TaskError;
+use crate::task::game::start::ExportLaunchScriptTask;
+use gpui::{Context, Render, Window, div, prelude::*, rgb};
 
-pub struct InstancesView;
+const SCRIPT_FORMATS: [(&str, ScriptFormat); 2] = [
+	("Shell (.sh)", ScriptFormat::Shell),
+	("批处理 (.bat)", ScriptFormat::Batch),
+];
+
+pub struct InstancesView {
+	format: ScriptFormat,
+	/// 脚本中以环境变量代替访问令牌
+	redact_token: bool,
+	notice: Option<String>,
+}
 
 impl InstancesView {
-	pub fn render() -> impl IntoElement {
+	pub fn new(_cx: &mut Context<Self>) -> Self {
+		Self {
+			format: ScriptFormat::native(),
+			redact_token: true,
+			notice: None,
+		}
+	}
+
+	/// 导出到版本目录，结果显示在导出选项下方
+	fn export_script(&mut self, instance: GameInstance, cx: &mut Context<Self>) {
+		let tm = AppState::get().task_manager.clone();
+		let task = ExportLaunchScriptTask {
+			dest: instance
+				.version_path
+				.join(format!("launch.{}", self.format.extension())),
+			format: self.format,
+			redact_token: self.redact_token,
+			instance,
+		};
+		let ver = task.instance.version.clone();
+		self.notice = Some(format!("正在导出 {ver} 的启动脚本..."));
+		cx.notify();
+
+		let rt = tokio::runtime::Handle::current();
+		cx.spawn(async move |this, cx| {
+			let result = rt
+				.spawn(async move {
+					let mut h = tm.submit_blocking(task).await?;
+					h.result().await
+				})
+				.await;
+			let _ = this.update(cx, |view, cx| {
+				view.notice = Some(match result {
+					Ok(Ok(dest)) => format!("已导出到 {}", dest.display()),
+					Ok(Err(TaskError::Cancelled)) => "已取消导出".into(),
+					Ok(Err(e)) => {
+						tracing::error!("导出启动脚本失败: {}", e);
+						format!("导出失败: {e}")
+					}
+					Err(e) => format!("导出失败: {e}"),
+				});
+				cx.notify();
+			});
+		})
+		.detach();
+	}
+
+	fn render_chip<F: Fn(&mut Self) + 'static>(
+		&self,
+		label: &'static str,
+		on: bool,
+		cx: &mut Context<Self>,
+		f: F,
+	) -> impl IntoElement + use<F> {
+		div()
+			.px_2()
+			.py_1()
+			.rounded_md()
+			.text_xs()
+			.cursor_pointer()
+			.bg(if on { rgb(0x1e3a5f) } else { rgb(0x1a1a1a) })
+			.border_1()
+			.border_color(if on { rgb(0x3b82f6) } else { rgb(0x333333) })
+			.text_color(rgb(if on { 0xffffff } else { 0x888888 }))
+			.child(label)
+			.on_mouse_down(
+				gpui::MouseButton::Left,
+				cx.listener(move |this, _, _, cx| {
+					f(this);
+					cx.notify();
+				}),
+			)
+	}
+
+	fn render_export_options(&self, cx: &mut Context<Self>) -> impl IntoElement {
+		div()
+			.flex()
+			.flex_col()
+			.gap_1()
+			.child(
+				div()
+					.flex()
+					.items_center()
+					.gap_2()
+					.child(div().text_sm().text_color(rgb(0x888888)).child("启动脚本"))
+					.children(SCRIPT_FORMATS.iter().map(|&(label, format)| {
+						self.render_chip(label, self.format == format, cx, move |this| {
+							this.format = format
+						})
+					}))
+					.child(
+						self.render_chip("隐藏访问令牌", self.redact_token, cx, |this| {
+							this.redact_token = !this.redact_token
+						}),
+					),
+			)
+			.when(!self.redact_token, |d| {
+				d.child(
+					div()
+						.text_xs()
+						.text_color(rgb(0xfbbf24))
+						.child("脚本将包含账户的访问令牌，请勿分享"),
+				)
+			})
+			.when_some(self.notice.clone(), |d, notice| {
+				d.child(div().text_xs().text_color(rgb(0x888888)).child(notice))
+			})
+	}
+}
+
+impl Render for InstancesView {
+	fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
 		let state = AppState::get();
 		let instances = state.instances.read().unwrap().clone();
 		let current_idx = *state.current_instance.lock().unwrap();
@@ -28,6 +153,7 @@ impl InstancesView {
 							.child(format!("{}", cluster_path.display())),
 					),
 			)
+			.child(self.render_export_options(cx))
 			.child(match instances.is_empty() {
 				true => div()
 					.flex()
@@ -98,6 +224,24 @@ impl InstancesView {
 									)
 									.child(div().text_sm().text_color(rgb(0x666666)).child(path)),
 							)
+							.child(
+								div()
+									.px_2()
+									.py_1()
+									.rounded_sm()
+									.bg(rgb(0x333333))
+									.hover(|s| s.bg(rgb(0x444444)))
+									.text_color(rgb(0xffffff))
+									.text_xs()
+									.child("导出启动脚本")
+									.on_mouse_down(
+										gpui::MouseButton::Left,
+										cx.listener(move |this, _, _, cx| {
+											cx.stop_propagation();
+											this.export_script(inst.clone(), cx);
+										}),
+									),
+							)
 					}))
 					.into_any_element(),
 			})
@@ -1,3 +1,5 @@
+pub mod accounts;
+pub mod console;
 pub mod download;
 pub mod home;
 pub mod instances;
@@ -7,6 +7,7 @@ impl SettingsView {
 	pub fn render() -> impl IntoElement {
 		let config = AppState::get().config.get();
 		let cluster_path = AppState::get().cluster_path();
+		let javas = AppState::get().java_installations();
 
 		div()
 			.flex()
@@ -61,10 +62,14 @@ impl SettingsView {
 					))
 					.child(Self::render_setting_item(
 						"窗口大小",
-						&format!(
-							"{} x {}",
-							config.game.window_width, config.game.window_height
-						),
+						&if config.game.fullscreen {
+							"全屏".to_string()
+						} else {
+							format!(
+								"{} x {}",
+								config.game.window_width, config.game.window_height
+							)
+						},
 						"游戏窗口默认尺寸",
 					))
 					.child(Self::render_setting_item(
@@ -77,6 +82,28 @@ impl SettingsView {
 						"额外的 JVM 启动参数",
 					)),
 			))
+			.child(Self::render_section(
+				"Java 运行时",
+				div()
+					.flex()
+					.flex_col()
+					.gap_3()
+					.when(javas.is_empty(), |d| {
+						d.child(
+							div()
+								.text_sm()
+								.text_color(rgb(0x666666))
+								.child("正在检测或未找到 Java"),
+						)
+					})
+					.children(javas.iter().map(|java| {
+						Self::render_setting_item(
+							&format!("Java {}", java.major),
+							&java.path.display().to_string(),
+							&format!("{} · {} · {}", java.version, java.vendor, java.arch),
+						)
+					})),
+			))
 			.child(Self::render_section(
 				"网络设置",
 				div()
@@ -87,6 +114,15 @@ impl SettingsView {
 						"下载并发数",
 						&config.download_concurrency.to_string(),
 						"同时下载的文件数量",
+					))
+					.child(Self::render_setting_item(
+						"下载源",
+						&config.download_source.name(),
+						if config.download_fallback {
+							"下载失败时自动切换到其他源"
+						} else {
+							"仅使用所选下载源"
+						},
 					)),
 			))
 	}
@@ -109,7 +145,7 @@ impl SettingsView {
 			.child(content)
 	}
 
-	fn render_setting_item(label: &str, value: &str, desc: &str) -> impl IntoElement {
+	fn render_setting_item(label: &str, value: &str, desc: &str) -> impl IntoElement + use<> {
 		div()
 			.flex()
 			.items_center()
@@ -188,10 +188,29 @@ impl TasksView {
 							.child(format!("{}%", percent)),
 					),
 			)
+			.when(!p.phases.is_empty(), |d| {
+				d.child(
+					div()
+						.text_xs()
+						.text_color(rgb(0x888888))
+						.child(Self::phase_text(&p)),
+				)
+			})
 			.child(div().text_xs().text_color(rgb(0x666666)).child(format!(
 				"ID: {} | {}",
 				&task_id.to_string()[..8],
 				size_text
 			)))
 	}
+
+	fn phase_text(p: &DownloadProgressState) -> String {
+		let phases = p
+			.phases
+			.iter()
+			.filter(|ph| ph.total > 0)
+			.map(|ph| format!("{} {}/{}", ph.label, ph.completed, ph.total))
+			.collect::<Vec<_>>()
+			.join(" · ");
+		format!("文件 {}/{} | {}", p.files_completed, p.files_total, phases)
+	}
 }